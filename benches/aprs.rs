@@ -0,0 +1,37 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use ogn_web_gateway::ogn::aprs;
+
+const LINES: &[&str] = &[
+    r"FLRDD9612>APRS,qAS,VillaBlau:/141956h4911.18N/00815.93E'126/059/A=003716 !W75! id06DD9612 -355fpm -1.2rot 3.0dB 2e -1.3kHz gps3x3",
+    r"ICA4060D7>APRS,qAS,UKDUN2:/141953h5147.03N\00109.00W^210/143/A=003405 !W50! id214060D7 +079fpm +0.0rot 8.0dB 0e -11.9kHz gps3x4",
+    r"OGN2FD00F>OGNTRK,qAS,LZHL:/093213h4848.78N/01708.32E'000/000/A=000538 !W12! id072FD00F -058fpm +0.0rot FL003.12 32.8dB 0e -0.8kHz gps3x5 +12.7dBm",
+    r"FNT1103CE>OGNFNT,qAS,FNB1103CE:/183727h/5L!!<*e7>7P[/A=001042 id1E1103CE +03fpm",
+    r"Koenigsdf>APRS,TCPIP*,qAC,GLIDERN1:>132052h v0.2.7.RPI-GPU CPU:0.7 RAM:770.2/968.2MB NTP:1.8ms/-3.3ppm +55.7C",
+    r"# aprsc 2.1.4-g408ed49 17 Apr 2018 14:20:01 GMT GLIDERN1 37.187.40.234:10152",
+];
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("aprs::parse");
+
+    group.bench_function("bytes", |b| {
+        b.iter(|| {
+            for line in LINES {
+                let _ = black_box(aprs::parse(black_box(line)));
+            }
+        })
+    });
+
+    group.bench_function("regex", |b| {
+        b.iter(|| {
+            for line in LINES {
+                let _ = black_box(aprs::parse_with_regex(black_box(line)));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
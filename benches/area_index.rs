@@ -0,0 +1,121 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use ogn_web_gateway::area_index::AreaIndex;
+use ogn_web_gateway::filter::{AircraftCategory, PositionFilter};
+use ogn_web_gateway::geo::{Area, BoundingBox};
+
+const SUBSCRIBERS: usize = 5000;
+const POSITIONS: usize = 1000;
+
+/// Minimal linear congruential generator, to get the same areas and
+/// positions for every run
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> f64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + self.next() * (max - min)
+    }
+}
+
+/// Map views of about 1x0.5 degrees spread over central Europe
+fn areas(random: &mut Random) -> Vec<(usize, Area)> {
+    (0..SUBSCRIBERS)
+        .map(|i| {
+            let left = random.range(-5., 20.);
+            let bottom = random.range(40., 55.);
+            let bbox = BoundingBox::new(left, bottom, left + 1., bottom + 0.5).unwrap();
+            (i, Area::BoundingBox(bbox))
+        })
+        .collect()
+}
+
+/// Zoomed out map views of about 80x40 degrees spread over the world, which
+/// are too large for the 1x1 degree grid
+fn continents(random: &mut Random) -> Vec<(usize, Area)> {
+    (0..SUBSCRIBERS)
+        .map(|i| {
+            let left = random.range(-180., 180.);
+            let bottom = random.range(-60., 50.);
+            let right = if left + 80. > 180. {
+                left - 280.
+            } else {
+                left + 80.
+            };
+            let bbox = BoundingBox::new(left, bottom, right, bottom + 40.).unwrap();
+            (i, Area::BoundingBox(bbox))
+        })
+        .collect()
+}
+
+fn positions(random: &mut Random) -> Vec<(f64, f64)> {
+    (0..POSITIONS)
+        .map(|_| (random.range(-5., 21.), random.range(40., 56.)))
+        .collect()
+}
+
+fn bench_areas(c: &mut Criterion, name: &str, areas: &[(usize, Area)], positions: &[(f64, f64)]) {
+    let mut index = AreaIndex::default();
+    for (key, area) in areas {
+        index.insert(
+            *key,
+            "default".to_owned(),
+            area.clone(),
+            PositionFilter::default(),
+        );
+    }
+
+    let mut group = c.benchmark_group(name);
+
+    group.bench_function("linear", |b| {
+        b.iter(|| {
+            for &(lon, lat) in positions {
+                let (lon, lat) = (black_box(lon), black_box(lat));
+                for (key, area) in areas {
+                    if area.contains(lon, lat) {
+                        black_box(key);
+                    }
+                }
+            }
+        })
+    });
+
+    group.bench_function("grid", |b| {
+        b.iter(|| {
+            for &(lon, lat) in positions {
+                let (lon, lat) = (black_box(lon), black_box(lat));
+                for key in index.query(lon, lat, 1000., 90., AircraftCategory::Glider) {
+                    black_box(key);
+                }
+            }
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_query(c: &mut Criterion) {
+    let mut random = Random(42);
+    let areas = areas(&mut random);
+    let positions = positions(&mut random);
+
+    bench_areas(c, "area_index::query", &areas, &positions);
+}
+
+fn bench_query_continents(c: &mut Criterion) {
+    let mut random = Random(42);
+    let areas = continents(&mut random);
+    let positions = positions(&mut random);
+
+    bench_areas(c, "area_index::query_continents", &areas, &positions);
+}
+
+criterion_group!(benches, bench_query, bench_query_continents);
+criterion_main!(benches);
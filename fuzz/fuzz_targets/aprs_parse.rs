@@ -0,0 +1,14 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use ogn_web_gateway::ogn::aprs;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(line) = std::str::from_utf8(data) {
+        // the hand-written parser must never panic and always agree with the
+        // original regex-based implementation
+        let result = format!("{:?}", aprs::parse(line));
+        let expected = format!("{:?}", aprs::parse_with_regex(line));
+        assert_eq!(result, expected);
+    }
+});
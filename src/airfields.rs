@@ -0,0 +1,233 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use actix::prelude::*;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::geo;
+use crate::redis::*;
+
+/// Maximum distance in meters between a takeoff or landing and the
+/// airfield that it is attributed to
+const MAX_AIRFIELD_DISTANCE: f64 = 5_000.;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Airfield {
+    /// ICAO code (e.g. `EDKA`)
+    pub icao: String,
+    pub name: String,
+    pub longitude: f64,
+    pub latitude: f64,
+    /// elevation in meters
+    pub elevation: Option<f32>,
+}
+
+/// Returns the airfield that is closest to the given position, if it is
+/// within `MAX_AIRFIELD_DISTANCE`.
+pub fn nearest(airfields: &[Airfield], longitude: f64, latitude: f64) -> Option<&Airfield> {
+    airfields
+        .iter()
+        .map(|airfield| {
+            let distance =
+                geo::distance(airfield.longitude, airfield.latitude, longitude, latitude);
+            (airfield, distance)
+        })
+        .filter(|(_, distance)| *distance <= MAX_AIRFIELD_DISTANCE)
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+        .map(|(airfield, _)| airfield)
+}
+
+/// Parses the airfields from a SeeYou CUP waypoint file.
+///
+/// Only waypoints with an airfield style and a `code` are used, and the
+/// `code` is expected to be the ICAO code of the airfield.
+pub fn parse_cup(text: &str) -> Vec<Airfield> {
+    text.lines()
+        // the waypoints are followed by an optional task section
+        .take_while(|line| !line.starts_with("-----Related Tasks-----"))
+        .filter_map(parse_cup_line)
+        .collect()
+}
+
+fn parse_cup_line(line: &str) -> Option<Airfield> {
+    let fields = split_cup_line(line);
+
+    // 2: grass surface, 4: gliding site, 5: solid surface
+    match fields.get(6).map(|it| it.trim()) {
+        Some("2") | Some("4") | Some("5") => {}
+        _ => return None,
+    }
+
+    let icao = fields.get(1)?.trim().to_uppercase();
+    if icao.is_empty() {
+        return None;
+    }
+
+    Some(Airfield {
+        icao,
+        name: fields.first()?.trim().to_owned(),
+        latitude: parse_cup_coordinate(fields.get(3)?, 2, 'N', 'S')?,
+        longitude: parse_cup_coordinate(fields.get(4)?, 3, 'E', 'W')?,
+        elevation: fields.get(5).and_then(|it| parse_cup_elevation(it)),
+    })
+}
+
+/// Splits a CSV line, taking quoted fields into account.
+fn split_cup_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields
+}
+
+/// Parses a `DDMM.mmmN` latitude or `DDDMM.mmmE` longitude.
+fn parse_cup_coordinate(
+    value: &str,
+    degree_digits: usize,
+    positive: char,
+    negative: char,
+) -> Option<f64> {
+    let value = value.trim();
+    let sign = match value.chars().last()? {
+        c if c == positive => 1.,
+        c if c == negative => -1.,
+        _ => return None,
+    };
+
+    let degrees: f64 = value.get(..degree_digits)?.parse().ok()?;
+    let minutes: f64 = value.get(degree_digits..value.len() - 1)?.parse().ok()?;
+    if minutes >= 60. {
+        return None;
+    }
+
+    Some(sign * (degrees + minutes / 60.))
+}
+
+/// Parses an elevation like `123.0m` or `400ft` into meters.
+fn parse_cup_elevation(value: &str) -> Option<f32> {
+    let value = value.trim();
+    if let Some(feet) = value.strip_suffix("ft") {
+        feet.parse::<f32>().ok().map(|it| it * 0.3048)
+    } else {
+        value.strip_suffix('m')?.parse().ok()
+    }
+}
+
+/// `AirfieldsUpdater` periodically imports the airfields from a local
+/// CUP file into redis.
+pub struct AirfieldsUpdater {
+    pub path: PathBuf,
+    pub redis: Addr<RedisExecutor>,
+}
+
+impl Actor for AirfieldsUpdater {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.notify(Update);
+
+        ctx.run_interval(Duration::from_secs(3 * 60 * 60), |_act, ctx| {
+            ctx.notify(Update);
+        });
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+struct Update;
+
+impl Handler<Update> for AirfieldsUpdater {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Update, _ctx: &mut Self::Context) {
+        info!("Reading airfields from {}…", self.path.display());
+        let text = match fs::read_to_string(&self.path) {
+            Err(error) => {
+                error!("Could not read {}: {}", self.path.display(), error);
+                return;
+            }
+            Ok(text) => text,
+        };
+
+        let airfields = parse_cup(&text);
+        info!("Airfield database contains {} airfields", airfields.len());
+
+        match self.redis.try_send(WriteAirfields(airfields)) {
+            Ok(_) => info!("Updated airfield database"),
+            Err(error) => error!("Airfield database update failed: {}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    const CUP: &str = r#"name,code,country,lat,lon,elev,style,rwdir,rwlen,freq,desc
+"Aachen Merzbrueck",EDKA,DE,5049.383N,00611.800E,189.0m,5,070,1050.0m,122.875,"Flugplatz, Aachen"
+"Dahlemer Binz",EDKV,DE,5024.367N,00631.750E,1896ft,5,060,1070.0m,122.875,
+"Bruehl",,DE,5049.933N,00654.117E,59.0m,4,,,,
+"Windmill",WIND,DE,5049.933N,00654.117E,59.0m,1,,,,
+-----Related Tasks-----
+"Task",,"Aachen Merzbrueck","Dahlemer Binz"
+"#;
+
+    #[test]
+    fn test_parse_cup() {
+        let airfields = parse_cup(CUP);
+        assert_eq!(airfields.len(), 2);
+
+        assert_eq!(airfields[0].icao, "EDKA");
+        assert_eq!(airfields[0].name, "Aachen Merzbrueck");
+        assert_relative_eq!(airfields[0].latitude, 50.823_05, epsilon = 0.000_01);
+        assert_relative_eq!(airfields[0].longitude, 6.196_667, epsilon = 0.000_01);
+        assert_eq!(airfields[0].elevation, Some(189.));
+
+        assert_eq!(airfields[1].icao, "EDKV");
+        assert_relative_eq!(airfields[1].elevation.unwrap(), 577.9, epsilon = 0.1);
+    }
+
+    #[test]
+    fn test_split_cup_line() {
+        assert_eq!(
+            split_cup_line(r#""Flugplatz, Aachen",EDKA,,5"#),
+            vec!["Flugplatz, Aachen", "EDKA", "", "5"]
+        );
+    }
+
+    #[test]
+    fn test_parse_cup_coordinate() {
+        assert_eq!(parse_cup_coordinate("5030.000N", 2, 'N', 'S'), Some(50.5));
+        assert_eq!(parse_cup_coordinate("3330.000S", 2, 'N', 'S'), Some(-33.5));
+        assert_eq!(parse_cup_coordinate("07030.000W", 3, 'E', 'W'), Some(-70.5));
+        assert_eq!(parse_cup_coordinate("5049.500E", 2, 'N', 'S'), None);
+        assert_eq!(parse_cup_coordinate("5070.000N", 2, 'N', 'S'), None);
+        assert_eq!(parse_cup_coordinate("", 2, 'N', 'S'), None);
+    }
+
+    #[test]
+    fn test_nearest() {
+        let airfields = parse_cup(CUP);
+
+        let airfield = nearest(&airfields, 6.19, 50.82).unwrap();
+        assert_eq!(airfield.icao, "EDKA");
+
+        let airfield = nearest(&airfields, 6.52, 50.41).unwrap();
+        assert_eq!(airfield.icao, "EDKV");
+
+        assert_eq!(nearest(&airfields, 7.0, 50.6), None);
+    }
+}
@@ -0,0 +1,114 @@
+use actix::prelude::*;
+use actix_web::{error::ErrorBadRequest, error::ErrorInternalServerError, error::ErrorNotFound};
+use actix_web::{web, Responder};
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::ogn_ddb::DeviceInfo;
+use crate::redis::{LogbookEntry, ReadAirfield, ReadLogbook, ReadOGNDevices, RedisExecutor};
+
+#[derive(Deserialize, Debug)]
+pub struct LogbookQueryParams {
+    /// UTC date in `YYYY-MM-DD` format (default: today)
+    date: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Logbook {
+    icao: String,
+    name: String,
+    date: String,
+    flights: Vec<Flight>,
+}
+
+#[derive(Serialize)]
+struct Flight {
+    flight_id: String,
+    id: String,
+    registration: Option<String>,
+    callsign: Option<String>,
+    model: Option<String>,
+    takeoff: Option<i64>,
+    takeoff_airfield: Option<String>,
+    landing: Option<i64>,
+    landing_airfield: Option<String>,
+    /// duration in seconds
+    duration: Option<i64>,
+}
+
+impl Flight {
+    fn new(entry: LogbookEntry, device: Option<&DeviceInfo>) -> Self {
+        Flight {
+            registration: device.and_then(|it| it.registration.clone()),
+            callsign: device.and_then(|it| it.callsign.clone()),
+            model: device.and_then(|it| it.model.clone()),
+            takeoff: entry.takeoff.map(|it| it.timestamp()),
+            landing: entry.landing.map(|it| it.timestamp()),
+            duration: entry
+                .takeoff
+                .zip(entry.landing)
+                .map(|(takeoff, landing)| (landing - takeoff).num_seconds()),
+            takeoff_airfield: entry.takeoff_airfield,
+            landing_airfield: entry.landing_airfield,
+            flight_id: entry.flight_id,
+            id: entry.id,
+        }
+    }
+}
+
+pub async fn logbook(
+    (icao, query, redis): (
+        web::Path<String>,
+        web::Query<LogbookQueryParams>,
+        web::Data<Addr<RedisExecutor>>,
+    ),
+) -> impl Responder {
+    let date = match &query.date {
+        Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| ErrorBadRequest("Invalid date"))?,
+        None => Utc::now().naive_utc().date(),
+    };
+
+    let airfield = redis
+        .send(ReadAirfield {
+            icao: icao.into_inner(),
+        })
+        .await
+        .map_err(ErrorInternalServerError)?
+        .map_err(ErrorInternalServerError)?
+        .ok_or_else(|| ErrorNotFound("Unknown airfield"))?;
+
+    let entries = redis
+        .send(ReadLogbook {
+            icao: airfield.icao.clone(),
+            date,
+        })
+        .await
+        .map_err(ErrorInternalServerError)?
+        .map_err(ErrorInternalServerError)?;
+
+    let mut ids: Vec<String> = entries.iter().map(|entry| entry.id.clone()).collect();
+    ids.sort_unstable();
+    ids.dedup();
+
+    let devices = redis
+        .send(ReadOGNDevices { ids })
+        .await
+        .map_err(ErrorInternalServerError)?
+        .map_err(ErrorInternalServerError)?;
+
+    let flights = entries
+        .into_iter()
+        .map(|entry| {
+            let device = devices.get(&entry.id);
+            Flight::new(entry, device)
+        })
+        .collect();
+
+    Ok::<_, actix_web::Error>(web::Json(Logbook {
+        icao: airfield.icao,
+        name: airfield.name,
+        date: date.format("%Y-%m-%d").to_string(),
+        flights,
+    }))
+}
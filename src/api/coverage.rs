@@ -0,0 +1,67 @@
+use actix::prelude::*;
+use actix_web::{error::ErrorBadRequest, error::ErrorInternalServerError, web, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::coverage::Tile;
+use crate::geo::BoundingBox;
+use crate::redis::{CoverageCell, ReadCoverage, RedisExecutor};
+
+/// Maximum number of 1x1 degree tiles that can be requested at once
+const MAX_TILES: usize = 100;
+
+#[derive(Deserialize, Debug)]
+pub struct GetQueryParams {
+    bbox: String,
+    receiver: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Cell {
+    receiver: String,
+    longitude: f64,
+    latitude: f64,
+    count: u32,
+    min_altitude: i16,
+    max_range: Option<f32>,
+    average_snr: Option<f32>,
+}
+
+impl From<CoverageCell> for Cell {
+    fn from(cell: CoverageCell) -> Self {
+        Cell {
+            longitude: cell.cell.longitude(),
+            latitude: cell.cell.latitude(),
+            count: cell.stats.count,
+            min_altitude: cell.stats.min_altitude,
+            max_range: Some(cell.stats.max_range).filter(|it| *it > 0.),
+            average_snr: cell.stats.average_snr(),
+            receiver: cell.receiver,
+        }
+    }
+}
+
+pub async fn get(
+    (query, redis): (web::Query<GetQueryParams>, web::Data<Addr<RedisExecutor>>),
+) -> impl Responder {
+    let query = query.into_inner();
+
+    let bbox =
+        BoundingBox::try_parse(&query.bbox).ok_or_else(|| ErrorBadRequest("Invalid bbox"))?;
+
+    if Tile::covering(&bbox).len() > MAX_TILES {
+        return Err(ErrorBadRequest("Bounding box too large"));
+    }
+
+    let cells = redis
+        .send(ReadCoverage {
+            bbox,
+            receiver: query.receiver,
+        })
+        .await
+        .map_err(ErrorInternalServerError)?
+        .map_err(ErrorInternalServerError)?;
+
+    let cells: Vec<Cell> = cells.into_iter().map(Cell::from).collect();
+
+    Ok(web::Json(cells))
+}
@@ -0,0 +1,103 @@
+use actix::prelude::*;
+use actix_web::{error::ErrorInternalServerError, error::ErrorNotFound, web, Responder};
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::redis::{OGNFlight, OGNFlightLocation, ReadOGNFlight, ReadOGNFlights, RedisExecutor};
+
+#[derive(Deserialize, Debug)]
+pub struct ListQueryParams {
+    id: String,
+    before: Option<i64>,
+    after: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct Location {
+    time: i64,
+    longitude: f32,
+    latitude: f32,
+    altitude: i16,
+}
+
+impl From<OGNFlightLocation> for Location {
+    fn from(location: OGNFlightLocation) -> Self {
+        Location {
+            time: location.time.timestamp(),
+            longitude: location.longitude,
+            latitude: location.latitude,
+            altitude: location.altitude,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Flight {
+    id: String,
+    aircraft: String,
+    start: i64,
+    end: Option<i64>,
+    /// duration in seconds
+    duration: Option<i64>,
+    takeoff: Option<Location>,
+    landing: Option<Location>,
+}
+
+impl From<OGNFlight> for Flight {
+    fn from(flight: OGNFlight) -> Self {
+        Flight {
+            id: flight.flight_id(),
+            start: flight.start.timestamp(),
+            end: flight.end.map(|it| it.timestamp()),
+            duration: flight.end.map(|it| (it - flight.start).num_seconds()),
+            takeoff: flight.takeoff.map(Location::from),
+            landing: flight.landing.map(Location::from),
+            aircraft: flight.id,
+        }
+    }
+}
+
+pub async fn list(
+    (query, redis): (web::Query<ListQueryParams>, web::Data<Addr<RedisExecutor>>),
+) -> impl Responder {
+    let after = query
+        .after
+        .and_then(|it| NaiveDateTime::from_timestamp_opt(it, 0))
+        .map(|it| DateTime::from_utc(it, Utc));
+
+    let before = query
+        .before
+        .and_then(|it| NaiveDateTime::from_timestamp_opt(it, 0))
+        .map(|it| DateTime::from_utc(it, Utc));
+
+    let ids: Vec<_> = query.id.split(',').map(|s| s.to_owned()).collect();
+
+    let flights = redis
+        .send(ReadOGNFlights { ids, after, before })
+        .await
+        .map_err(ErrorInternalServerError)?
+        .map_err(ErrorInternalServerError)?;
+
+    let mut flights: Vec<OGNFlight> = flights.into_values().flatten().collect();
+
+    flights.sort_by_key(|it| it.start);
+
+    let flights: Vec<Flight> = flights.into_iter().map(Flight::from).collect();
+
+    Ok::<_, actix_web::Error>(web::Json(flights))
+}
+
+pub async fn get(
+    (flight_id, redis): (web::Path<String>, web::Data<Addr<RedisExecutor>>),
+) -> impl Responder {
+    let flight = redis
+        .send(ReadOGNFlight {
+            flight_id: flight_id.into_inner(),
+        })
+        .await
+        .map_err(ErrorInternalServerError)?
+        .map_err(ErrorInternalServerError)?
+        .ok_or_else(|| ErrorNotFound("Unknown flight"))?;
+
+    Ok::<_, actix_web::Error>(web::Json(Flight::from(flight)))
+}
@@ -1,15 +1,102 @@
 use actix::prelude::*;
+use actix_web::http::header;
 use actix_web::{web, HttpRequest, Responder};
 use actix_web_actors::ws;
+use serde::Deserialize;
 
 use crate::gateway::Gateway;
+use crate::protocol::{Protocol, BINARY_SUBPROTOCOL, JSON_SUBPROTOCOL};
 use crate::ws_client::WSClient;
 
+#[derive(Deserialize, Debug)]
+pub struct GetQueryParams {
+    protocol: Option<Protocol>,
+}
+
 pub async fn get(
     req: HttpRequest,
     stream: web::Payload,
+    query: web::Query<GetQueryParams>,
     gateway_addr: web::Data<Addr<Gateway>>,
 ) -> impl Responder {
+    let protocol = query.protocol.unwrap_or_else(|| requested_protocol(&req));
+
+    // only the subprotocol of the selected protocol is echoed, so that
+    // clients requesting a different subprotocol than the `protocol` query
+    // parameter fail the handshake instead of misreading the messages
     let gateway = gateway_addr.into_inner();
-    ws::start(WSClient::new(gateway), &req, stream)
+    ws::start_with_protocols(
+        WSClient::new(gateway, protocol),
+        protocol.subprotocols(),
+        &req,
+        stream,
+    )
+}
+
+/// Picks the first supported protocol from the `Sec-WebSocket-Protocol`
+/// header, falling back to the text protocol.
+fn requested_protocol(req: &HttpRequest) -> Protocol {
+    req.headers()
+        .get_all(header::SEC_WEBSOCKET_PROTOCOL)
+        .filter_map(|it| it.to_str().ok())
+        .flat_map(|it| it.split(','))
+        .find_map(|it| match it.trim() {
+            BINARY_SUBPROTOCOL => Some(Protocol::BinaryV1),
+            JSON_SUBPROTOCOL => Some(Protocol::JsonV1),
+            _ => None,
+        })
+        .unwrap_or(Protocol::Text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn handshake(requested: &str, protocol: Protocol) -> Option<String> {
+        let req = TestRequest::default()
+            .header(header::UPGRADE, "websocket")
+            .header(header::CONNECTION, "upgrade")
+            .header(header::SEC_WEBSOCKET_VERSION, "13")
+            .header(header::SEC_WEBSOCKET_KEY, "13")
+            .header(header::SEC_WEBSOCKET_PROTOCOL, requested)
+            .to_http_request();
+
+        let response = ws::handshake_with_protocols(&req, protocol.subprotocols())
+            .unwrap()
+            .finish();
+
+        response
+            .headers()
+            .get(header::SEC_WEBSOCKET_PROTOCOL)
+            .map(|it| it.to_str().unwrap().to_owned())
+    }
+
+    #[test]
+    fn test_requested_protocol() {
+        let req = TestRequest::default()
+            .header(header::SEC_WEBSOCKET_PROTOCOL, "foo, ogn-json.v1")
+            .to_http_request();
+        assert_eq!(requested_protocol(&req), Protocol::JsonV1);
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(requested_protocol(&req), Protocol::Text);
+    }
+
+    #[test]
+    fn test_echoed_subprotocol() {
+        let both = "ogn-json.v1, ogn-binary.v1";
+        assert_eq!(
+            handshake(both, Protocol::BinaryV1).as_deref(),
+            Some(BINARY_SUBPROTOCOL)
+        );
+        assert_eq!(
+            handshake(both, Protocol::JsonV1).as_deref(),
+            Some(JSON_SUBPROTOCOL)
+        );
+
+        // conflicting `protocol` query parameter
+        assert_eq!(handshake(JSON_SUBPROTOCOL, Protocol::BinaryV1), None);
+        assert_eq!(handshake(JSON_SUBPROTOCOL, Protocol::Text), None);
+    }
 }
@@ -1,4 +1,8 @@
+pub mod airfields;
+pub mod coverage;
 pub mod ddb;
+pub mod flights;
 pub mod live;
+pub mod receivers;
 pub mod records;
 pub mod status;
@@ -0,0 +1,84 @@
+use actix::prelude::*;
+use actix_web::{error::ErrorInternalServerError, error::ErrorNotFound, web, Responder};
+use chrono::prelude::*;
+use chrono::Duration;
+use serde::Serialize;
+
+use crate::redis::{OGNReceiver, ReadOGNReceiver, ReadOGNReceivers, RedisExecutor};
+
+/// Receivers that have not sent any beacon for this many minutes are considered offline
+const OFFLINE_AFTER_MINUTES: i64 = 15;
+
+#[derive(Serialize)]
+struct Receiver {
+    name: String,
+    online: bool,
+    last_seen: Option<i64>,
+    longitude: Option<f32>,
+    latitude: Option<f32>,
+    altitude: Option<i16>,
+    version: Option<String>,
+    platform: Option<String>,
+    cpu_load: Option<f32>,
+    ram_free: Option<f32>,
+    ram_total: Option<f32>,
+    ntp_offset: Option<f32>,
+    ntp_correction: Option<f32>,
+    cpu_temperature: Option<f32>,
+}
+
+impl From<OGNReceiver> for Receiver {
+    fn from(receiver: OGNReceiver) -> Self {
+        let last_seen = receiver.last_seen();
+        let online = last_seen
+            .filter(|it| Utc::now() - *it < Duration::minutes(OFFLINE_AFTER_MINUTES))
+            .is_some();
+
+        let position = receiver.position;
+        let status = receiver.status;
+
+        Receiver {
+            name: receiver.name,
+            online,
+            last_seen: last_seen.map(|it| it.timestamp()),
+            longitude: position.as_ref().map(|it| it.longitude),
+            latitude: position.as_ref().map(|it| it.latitude),
+            altitude: position.as_ref().map(|it| it.altitude),
+            version: status.as_ref().and_then(|it| it.version.clone()),
+            platform: status.as_ref().and_then(|it| it.platform.clone()),
+            cpu_load: status.as_ref().and_then(|it| it.cpu_load),
+            ram_free: status.as_ref().and_then(|it| it.ram_free),
+            ram_total: status.as_ref().and_then(|it| it.ram_total),
+            ntp_offset: status.as_ref().and_then(|it| it.ntp_offset),
+            ntp_correction: status.as_ref().and_then(|it| it.ntp_correction),
+            cpu_temperature: status.as_ref().and_then(|it| it.cpu_temperature),
+        }
+    }
+}
+
+pub async fn list(redis: web::Data<Addr<RedisExecutor>>) -> impl Responder {
+    let receivers = redis
+        .send(ReadOGNReceivers)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .map_err(ErrorInternalServerError)?;
+
+    let receivers: Vec<Receiver> = receivers.into_iter().map(Receiver::from).collect();
+
+    Ok::<_, actix_web::Error>(web::Json(receivers))
+}
+
+pub async fn get(
+    (name, redis): (web::Path<String>, web::Data<Addr<RedisExecutor>>),
+) -> impl Responder {
+    let receiver = redis
+        .send(ReadOGNReceiver {
+            name: name.into_inner(),
+        })
+        .await
+        .map_err(ErrorInternalServerError)?
+        .map_err(ErrorInternalServerError)?
+        .ok_or_else(|| ErrorNotFound("Unknown receiver"))?;
+
+    Ok::<_, actix_web::Error>(web::Json(Receiver::from(receiver)))
+}
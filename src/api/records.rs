@@ -1,16 +1,50 @@
 use std::collections::HashMap;
 
 use actix::prelude::*;
-use actix_web::{error::ErrorInternalServerError, web, Responder};
+use actix_web::http::header;
+use actix_web::{error::ErrorInternalServerError, error::ErrorNotFound, web};
+use actix_web::{HttpResponse, Responder};
 use chrono::prelude::*;
 use serde::Deserialize;
 
-use crate::redis::{OGNPosition, ReadOGNPositions, RedisExecutor};
+use crate::export;
+use crate::igc;
+use crate::ogn::optional;
+use crate::redis::{OGNPosition, ReadOGNDevices, ReadOGNPositions, RedisExecutor};
 
 #[derive(Deserialize, Debug)]
 pub struct GetQueryParams {
     before: Option<i64>,
     after: Option<i64>,
+    #[serde(default)]
+    receivers: bool,
+    #[serde(default)]
+    format: Format,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    /// pipe-delimited records inside of a JSON object
+    #[default]
+    Json,
+    Gpx,
+    Kml,
+    GeoJson,
+}
+
+impl GetQueryParams {
+    fn after(&self) -> Option<DateTime<Utc>> {
+        self.after
+            .and_then(|it| NaiveDateTime::from_timestamp_opt(it, 0))
+            .map(|it| DateTime::from_utc(it, Utc))
+    }
+
+    fn before(&self) -> Option<DateTime<Utc>> {
+        self.before
+            .and_then(|it| NaiveDateTime::from_timestamp_opt(it, 0))
+            .map(|it| DateTime::from_utc(it, Utc))
+    }
 }
 
 pub async fn get(
@@ -20,45 +54,129 @@ pub async fn get(
         web::Data<Addr<RedisExecutor>>,
     ),
 ) -> impl Responder {
-    let after = query
-        .after
-        .and_then(|it| NaiveDateTime::from_timestamp_opt(it, 0))
-        .map(|it| DateTime::from_utc(it, Utc));
-
-    let before = query
-        .before
-        .and_then(|it| NaiveDateTime::from_timestamp_opt(it, 0))
-        .map(|it| DateTime::from_utc(it, Utc));
+    let after = query.after();
+    let before = query.before();
 
     let ids: Vec<_> = id.split(',').map(|s| s.to_owned()).collect();
 
     let map = redis
-        .send(ReadOGNPositions { ids, after, before })
+        .send(ReadOGNPositions {
+            ids,
+            after,
+            before,
+            include_receivers: query.receivers,
+        })
+        .await
+        .map_err(ErrorInternalServerError)?
+        .map_err(ErrorInternalServerError)?;
+
+    let response = match query.format {
+        Format::Json => HttpResponse::Ok().json(map.serialize(query.receivers)),
+        Format::Gpx => HttpResponse::Ok()
+            .content_type("application/gpx+xml")
+            .body(export::render_gpx(&sorted_tracks(map))),
+        Format::Kml => HttpResponse::Ok()
+            .content_type("application/vnd.google-earth.kml+xml")
+            .body(export::render_kml(&sorted_tracks(map))),
+        Format::GeoJson => HttpResponse::Ok()
+            .content_type("application/geo+json")
+            .body(export::render_geojson(&sorted_tracks(map)).to_string()),
+    };
+
+    Ok::<_, actix_web::Error>(response)
+}
+
+fn sorted_tracks(map: HashMap<String, Vec<OGNPosition>>) -> Vec<(String, Vec<OGNPosition>)> {
+    let mut tracks: Vec<_> = map.into_iter().collect();
+    tracks.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+    tracks
+}
+
+/// Renders the positions of a single aircraft as an IGC file
+pub async fn get_igc(
+    (id, query, redis): (
+        web::Path<String>,
+        web::Query<GetQueryParams>,
+        web::Data<Addr<RedisExecutor>>,
+    ),
+) -> impl Responder {
+    let id = id.into_inner();
+
+    let mut map = redis
+        .send(ReadOGNPositions {
+            ids: vec![id.clone()],
+            after: query.after(),
+            before: query.before(),
+            include_receivers: false,
+        })
         .await
         .map_err(ErrorInternalServerError)?
         .map_err(ErrorInternalServerError)?;
 
-    Ok::<_, actix_web::Error>(web::Json(map.serialize()))
+    let positions = map.remove(&id).unwrap_or_default();
+    if positions.is_empty() {
+        return Err(ErrorNotFound("No records found"));
+    }
+
+    let devices = redis
+        .send(ReadOGNDevices {
+            ids: vec![id.clone()],
+        })
+        .await
+        .map_err(ErrorInternalServerError)?
+        .map_err(ErrorInternalServerError)?;
+
+    let file_name = format!("{}-{}.igc", positions[0].time.format("%Y-%m-%d"), id);
+
+    let igc = igc::render(&id, devices.get(&id), &positions);
+
+    Ok::<_, actix_web::Error>(
+        HttpResponse::Ok()
+            .content_type("application/vnd.fai.igc")
+            .header(
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", file_name),
+            )
+            .body(igc),
+    )
 }
 
 trait SerializeRecords {
-    fn serialize(self) -> HashMap<String, Vec<String>>;
+    fn serialize(self, include_receivers: bool) -> HashMap<String, Vec<String>>;
 }
 
 impl SerializeRecords for HashMap<String, Vec<OGNPosition>> {
-    fn serialize(self) -> HashMap<String, Vec<String>> {
+    fn serialize(self, include_receivers: bool) -> HashMap<String, Vec<String>> {
         self.into_iter()
             .map(|(id, records)| {
                 let serialized = records
                     .into_iter()
                     .map(|record| {
-                        format!(
-                            "{}|{:.6}|{:.6}|{}",
+                        let mut line = format!(
+                            "{}|{:.6}|{:.6}|{}|{}|{}|{}|{}|{}|{}|{:.0}",
                             record.time.timestamp(),
                             record.longitude,
                             record.latitude,
                             record.altitude,
-                        )
+                            optional(record.climb_rate.map(|it| format!("{:.1}", it))),
+                            optional(record.turn_rate.map(|it| format!("{:.1}", it))),
+                            optional(record.signal_quality.map(|it| format!("{:.1}", it))),
+                            optional(record.error_count),
+                            optional(record.frequency_offset.map(|it| format!("{:.1}", it))),
+                            optional(record.gps_quality),
+                            record.speed,
+                        );
+
+                        if include_receivers {
+                            let reception = record.reception.as_ref();
+                            line += &format!(
+                                "|{}|{}",
+                                optional(reception.map(|it| &it.receiver)),
+                                optional(reception.map(|it| &it.q_construct)),
+                            );
+                        }
+
+                        line
                     })
                     .collect();
 
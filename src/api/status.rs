@@ -1,16 +1,48 @@
+use std::collections::BTreeMap;
+
 use actix::prelude::*;
 use actix_web::{error::ErrorInternalServerError, web, Responder};
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 
 use systemstat::{self, Platform};
 
 use crate::gateway;
+use crate::upstream::{ConnectionState, UpstreamStatus};
 
 #[derive(Serialize)]
 struct Status {
     load: Option<(f32, f32, f32)>,
     users: usize,
     positions: Option<u64>,
+    rejected: BTreeMap<&'static str, u64>,
+    /// `None` if the gateway is not connected to an APRS server (e.g. replay mode)
+    upstream: Option<UpstreamHealth>,
+    upstreams: Vec<UpstreamStatus>,
+}
+
+/// Combined health of all upstream feeds
+#[derive(Serialize)]
+struct UpstreamHealth {
+    connected: bool,
+    last_message_at: Option<DateTime<Utc>>,
+    messages_per_minute: u32,
+}
+
+impl UpstreamHealth {
+    fn from_feeds(feeds: &[UpstreamStatus]) -> Option<UpstreamHealth> {
+        if feeds.is_empty() {
+            return None;
+        }
+
+        Some(UpstreamHealth {
+            connected: feeds
+                .iter()
+                .any(|feed| feed.state == ConnectionState::Connected),
+            last_message_at: feeds.iter().filter_map(|feed| feed.last_message_at).max(),
+            messages_per_minute: feeds.iter().map(|feed| feed.messages_per_minute).sum(),
+        })
+    }
 }
 
 pub async fn get(gateway: web::Data<Addr<gateway::Gateway>>) -> impl Responder {
@@ -26,9 +58,18 @@ pub async fn get(gateway: web::Data<Addr<gateway::Gateway>>) -> impl Responder {
         .ok()
         .map(|load| (load.one, load.five, load.fifteen));
 
+    let rejected = gateway_status
+        .rejected_lines
+        .iter()
+        .map(|(error, count)| (error.as_str(), *count))
+        .collect();
+
     Ok::<_, actix_web::Error>(web::Json(Status {
         load,
         users: gateway_status.users,
         positions: gateway_status.record_count,
+        rejected,
+        upstream: UpstreamHealth::from_feeds(&gateway_status.upstreams),
+        upstreams: gateway_status.upstreams,
     }))
 }
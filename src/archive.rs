@@ -0,0 +1,252 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use actix::prelude::*;
+use chrono::prelude::*;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::{error, info, warn};
+
+/// Interval in which buffered lines are flushed to disk
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// `ArchiveRecorder` writes raw APRS lines to gzip compressed files,
+/// one per UTC day (e.g. `ogn-2018-04-17.log.gz`), and removes files that
+/// are older than the configured retention.
+///
+/// Existing files are never appended to, because the gzip stream of a
+/// killed process lacks its trailer and decoders would stop reading there.
+/// Instead, a restart on the same day writes to the next free file name
+/// (e.g. `ogn-2018-04-17.1.log.gz`).
+///
+/// It is meant to run in a `SyncArbiter` so that disk I/O does not block
+/// the `Gateway`.
+pub struct ArchiveRecorder {
+    directory: PathBuf,
+    /// number of days to keep the archive files for (`0` keeps all files)
+    retention_days: u32,
+    current: Option<(NaiveDate, GzEncoder<File>)>,
+}
+
+impl ArchiveRecorder {
+    pub fn new(directory: PathBuf, retention_days: u32) -> ArchiveRecorder {
+        ArchiveRecorder {
+            directory,
+            retention_days,
+            current: None,
+        }
+    }
+
+    fn write_line(&mut self, time: DateTime<Utc>, raw: &str) -> io::Result<()> {
+        let date = time.naive_utc().date();
+        if self.current.as_ref().map(|(current_date, _)| *current_date) != Some(date) {
+            self.rotate(date)?;
+        }
+
+        if let Some((_, encoder)) = &mut self.current {
+            encoder.write_all(raw.as_bytes())?;
+            encoder.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.current {
+            Some((_, encoder)) => encoder.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Finishes the file of the previous day and creates a new file for `date`.
+    fn rotate(&mut self, date: NaiveDate) -> io::Result<()> {
+        if let Some((_, encoder)) = self.current.take() {
+            encoder.finish()?;
+        }
+
+        let (path, file) = self.create_file(date)?;
+        info!("Writing APRS archive to {}", path.display());
+
+        self.current = Some((date, GzEncoder::new(file, Compression::default())));
+
+        self.remove_expired(date);
+
+        Ok(())
+    }
+
+    fn create_file(&self, date: NaiveDate) -> io::Result<(PathBuf, File)> {
+        let mut sequence = 0;
+        loop {
+            let path = self.directory.join(file_name(date, sequence));
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => return Ok((path, file)),
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => sequence += 1,
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    fn remove_expired(&self, today: NaiveDate) {
+        if self.retention_days == 0 {
+            return;
+        }
+
+        let oldest_date = today - chrono::Duration::days(i64::from(self.retention_days) - 1);
+
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(error) => {
+                warn!("Could not read APRS archive directory: {}", error);
+                return;
+            }
+        };
+
+        for entry in entries.filter_map(Result::ok) {
+            let date = entry.file_name().to_str().and_then(parse_file_name);
+            if date.filter(|date| *date < oldest_date).is_some() {
+                let path = entry.path();
+                match fs::remove_file(&path) {
+                    Ok(()) => info!("Removed expired APRS archive {}", path.display()),
+                    Err(error) => warn!("Could not remove {}: {}", path.display(), error),
+                }
+            }
+        }
+    }
+}
+
+impl Actor for ArchiveRecorder {
+    type Context = SyncContext<Self>;
+}
+
+/// Raw APRS line that should be written to the archive.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct ArchiveLine {
+    pub time: DateTime<Utc>,
+    pub raw: String,
+}
+
+impl Handler<ArchiveLine> for ArchiveRecorder {
+    type Result = ();
+
+    fn handle(&mut self, msg: ArchiveLine, _ctx: &mut Self::Context) {
+        if let Err(error) = self.write_line(msg.time, &msg.raw) {
+            error!("Could not write to APRS archive: {}", error);
+        }
+    }
+}
+
+/// Writes the buffered lines to disk.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct FlushArchive;
+
+impl Handler<FlushArchive> for ArchiveRecorder {
+    type Result = ();
+
+    fn handle(&mut self, _msg: FlushArchive, _ctx: &mut Self::Context) {
+        if let Err(error) = self.flush() {
+            error!("Could not flush APRS archive: {}", error);
+        }
+    }
+}
+
+fn file_name(date: NaiveDate, sequence: u32) -> String {
+    match sequence {
+        0 => format!("ogn-{}.log.gz", date.format("%Y-%m-%d")),
+        _ => format!("ogn-{}.{}.log.gz", date.format("%Y-%m-%d"), sequence),
+    }
+}
+
+fn parse_file_name(file_name: &str) -> Option<NaiveDate> {
+    let name = file_name.strip_prefix("ogn-")?.strip_suffix(".log.gz")?;
+    let date = match name.split_once('.') {
+        Some((date, sequence)) => {
+            sequence.parse::<u32>().ok()?;
+            date
+        }
+        None => name,
+    };
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::MultiGzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_file_name() {
+        let date = NaiveDate::from_ymd_opt(2018, 4, 17).unwrap();
+        assert_eq!(file_name(date, 0), "ogn-2018-04-17.log.gz");
+        assert_eq!(file_name(date, 2), "ogn-2018-04-17.2.log.gz");
+        assert_eq!(parse_file_name("ogn-2018-04-17.log.gz"), Some(date));
+        assert_eq!(parse_file_name("ogn-2018-04-17.2.log.gz"), Some(date));
+        assert_eq!(parse_file_name("ogn-2018-04-17.log"), None);
+        assert_eq!(parse_file_name("ogn-2018-04-17.x.log.gz"), None);
+        assert_eq!(parse_file_name("foo-2018-04-17.log.gz"), None);
+    }
+
+    #[test]
+    fn test_write_and_rotate() {
+        let directory = std::env::temp_dir().join(format!("ogn-archive-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        let expired = directory.join("ogn-2018-03-01.log.gz");
+        let unrelated = directory.join("notes.txt");
+        File::create(&expired).unwrap();
+        File::create(&unrelated).unwrap();
+
+        let mut recorder = ArchiveRecorder::new(directory.clone(), 30);
+        recorder
+            .write_line(
+                Utc.with_ymd_and_hms(2018, 4, 17, 23, 59, 58).unwrap(),
+                "line 1",
+            )
+            .unwrap();
+        recorder
+            .write_line(
+                Utc.with_ymd_and_hms(2018, 4, 17, 23, 59, 59).unwrap(),
+                "line 2",
+            )
+            .unwrap();
+        recorder
+            .write_line(
+                Utc.with_ymd_and_hms(2018, 4, 18, 0, 0, 0).unwrap(),
+                "line 3",
+            )
+            .unwrap();
+        drop(recorder);
+
+        // a restart must not append to the file of the previous process
+        let mut recorder = ArchiveRecorder::new(directory.clone(), 30);
+        recorder
+            .write_line(
+                Utc.with_ymd_and_hms(2018, 4, 18, 0, 0, 1).unwrap(),
+                "line 4",
+            )
+            .unwrap();
+        recorder.flush().unwrap();
+        drop(recorder);
+
+        let read = |name: &str| {
+            let mut content = String::new();
+            let file = File::open(directory.join(name)).unwrap();
+            MultiGzDecoder::new(file)
+                .read_to_string(&mut content)
+                .unwrap();
+            content
+        };
+
+        assert_eq!(read("ogn-2018-04-17.log.gz"), "line 1\nline 2\n");
+        assert_eq!(read("ogn-2018-04-18.log.gz"), "line 3\n");
+        assert_eq!(read("ogn-2018-04-18.1.log.gz"), "line 4\n");
+        assert!(!expired.exists());
+        assert!(unrelated.exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
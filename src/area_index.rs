@@ -0,0 +1,424 @@
+//! Spatial index of the named area subscriptions of the websocket clients.
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::ops::RangeInclusive;
+
+use crate::filter::{AircraftCategory, PositionFilter};
+use crate::geo::{Area, BoundingBox};
+
+/// Areas that overlap more cells of the fine grid than this are added to
+/// the coarse grid instead. This keeps the memory usage of zoomed out map
+/// clients in check.
+const MAX_GRID_CELLS: usize = 2500;
+
+/// Areas that overlap more cells of the coarse grid than this (i.e. larger
+/// than a continent) are not added to any grid, but are checked for every
+/// position instead.
+const MAX_COARSE_GRID_CELLS: usize = 100;
+
+/// Size of the cells of the fine grid in degrees
+const FINE_CELL_SIZE: i16 = 1;
+
+/// Size of the cells of the coarse grid in degrees
+const COARSE_CELL_SIZE: i16 = 10;
+
+/// Cell of a grid, as `(longitude, latitude)` of its south-western corner
+/// divided by the cell size
+type GridCell = (i16, i16);
+
+/// Grid of square cells, which stores the subscribers with areas
+/// overlapping a cell and the number of their areas overlapping it
+struct Grid<K> {
+    /// cell size in degrees, which has to divide 180
+    cell_size: i16,
+    cells: HashMap<GridCell, HashMap<K, usize>>,
+}
+
+impl<K> Grid<K> {
+    fn new(cell_size: i16) -> Self {
+        Grid {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash> Grid<K> {
+    fn cell(&self, longitude: f64, latitude: f64) -> GridCell {
+        let size = f64::from(self.cell_size);
+        let columns = 180 / self.cell_size;
+        let rows = 90 / self.cell_size;
+
+        (
+            ((longitude / size).floor() as i16).clamp(-columns, columns - 1),
+            ((latitude / size).floor() as i16).clamp(-rows, rows - 1),
+        )
+    }
+
+    fn columns(&self, bbox: &BoundingBox) -> Vec<i16> {
+        let (left, _) = self.cell(bbox.left(), 0.);
+        let (right, _) = self.cell(bbox.right(), 0.);
+        let columns = 180 / self.cell_size;
+
+        if bbox.left() > bbox.right() {
+            (left..columns).chain(-columns..=right).collect()
+        } else {
+            (left..=right).collect()
+        }
+    }
+
+    fn rows(&self, bbox: &BoundingBox) -> RangeInclusive<i16> {
+        let (_, bottom) = self.cell(0., bbox.bottom());
+        let (_, top) = self.cell(0., bbox.top());
+
+        bottom..=top
+    }
+
+    /// Returns the number of cells that the bounding box overlaps.
+    fn count(&self, bbox: &BoundingBox) -> usize {
+        self.columns(bbox).len() * self.rows(bbox).len()
+    }
+
+    fn get(&self, longitude: f64, latitude: f64) -> Option<&HashMap<K, usize>> {
+        self.cells.get(&self.cell(longitude, latitude))
+    }
+
+    fn add(&mut self, key: &K, bbox: &BoundingBox) {
+        let columns = self.columns(bbox);
+        for y in self.rows(bbox) {
+            for x in &columns {
+                let keys = self.cells.entry((*x, y)).or_default();
+                *keys.entry(key.clone()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &K, bbox: &BoundingBox) {
+        let columns = self.columns(bbox);
+        for y in self.rows(bbox) {
+            for x in &columns {
+                if let Some(keys) = self.cells.get_mut(&(*x, y)) {
+                    decrement(keys, key);
+                    if keys.is_empty() {
+                        self.cells.remove(&(*x, y));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Named area of a subscriber and the filter of the positions inside of it
+#[derive(Debug, Clone, PartialEq)]
+pub struct AreaSubscription {
+    pub area: Area,
+    pub filter: PositionFilter,
+}
+
+/// `AreaIndex` stores the named areas of each subscriber (e.g. a websocket
+/// client) and finds the subscribers with an area containing a position
+/// without checking the areas of all subscribers.
+pub struct AreaIndex<K> {
+    areas: HashMap<K, BTreeMap<String, AreaSubscription>>,
+    /// 1x1 degree grid of the small areas (e.g. zoomed in map views)
+    grid: Grid<K>,
+    /// 10x10 degree grid of the areas that are too large for `grid`
+    coarse_grid: Grid<K>,
+    /// subscribers with areas that are too large for both grids, and the
+    /// number of their large areas
+    large: HashMap<K, usize>,
+}
+
+impl<K> Default for AreaIndex<K> {
+    fn default() -> Self {
+        AreaIndex {
+            areas: HashMap::new(),
+            grid: Grid::new(FINE_CELL_SIZE),
+            coarse_grid: Grid::new(COARSE_CELL_SIZE),
+            large: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash> AreaIndex<K> {
+    /// Returns the areas of the subscriber by name.
+    pub fn areas(&self, key: &K) -> Option<&BTreeMap<String, AreaSubscription>> {
+        self.areas.get(key)
+    }
+
+    /// Adds an area, or replaces the area of the subscriber with the same name.
+    pub fn insert(&mut self, key: K, name: String, area: Area, filter: PositionFilter) {
+        self.remove(&key, &name);
+        self.add_to_grid(&key, &area);
+        self.areas
+            .entry(key)
+            .or_default()
+            .insert(name, AreaSubscription { area, filter });
+    }
+
+    /// Removes an area, returning `false` if the subscriber has no area
+    /// with that name.
+    pub fn remove(&mut self, key: &K, name: &str) -> bool {
+        let areas = match self.areas.get_mut(key) {
+            Some(areas) => areas,
+            None => return false,
+        };
+
+        let subscription = match areas.remove(name) {
+            Some(subscription) => subscription,
+            None => return false,
+        };
+
+        if areas.is_empty() {
+            self.areas.remove(key);
+        }
+
+        self.remove_from_grid(key, &subscription.area);
+
+        true
+    }
+
+    /// Removes all areas of the subscriber.
+    pub fn remove_all(&mut self, key: &K) {
+        if let Some(areas) = self.areas.remove(key) {
+            for subscription in areas.values() {
+                self.remove_from_grid(key, &subscription.area);
+            }
+        }
+    }
+
+    /// Returns the subscribers that have at least one area containing the
+    /// position with a filter matching the aircraft.
+    pub fn query(
+        &self,
+        longitude: f64,
+        latitude: f64,
+        altitude: f64,
+        speed: f64,
+        category: AircraftCategory,
+    ) -> impl Iterator<Item = &K> {
+        let cell = self.grid.get(longitude, latitude);
+        let coarse_cell = self.coarse_grid.get(longitude, latitude);
+
+        // subscribers can be in multiple grids with different areas
+        let coarse = coarse_cell
+            .into_iter()
+            .flat_map(|keys| keys.keys())
+            .filter(move |key| cell.is_none_or(|keys| !keys.contains_key(*key)));
+
+        let large = self.large.keys().filter(move |key| {
+            cell.is_none_or(|keys| !keys.contains_key(*key))
+                && coarse_cell.is_none_or(|keys| !keys.contains_key(*key))
+        });
+
+        cell.into_iter()
+            .flat_map(|keys| keys.keys())
+            .chain(coarse)
+            .chain(large)
+            .filter(move |key| {
+                self.areas.get(*key).is_some_and(|areas| {
+                    areas.values().any(|it| {
+                        it.area.contains(longitude, latitude)
+                            && it.filter.matches(altitude, speed, category)
+                    })
+                })
+            })
+    }
+
+    fn add_to_grid(&mut self, key: &K, area: &Area) {
+        let bbox = area.bbox();
+        if self.grid.count(&bbox) <= MAX_GRID_CELLS {
+            self.grid.add(key, &bbox);
+        } else if self.coarse_grid.count(&bbox) <= MAX_COARSE_GRID_CELLS {
+            self.coarse_grid.add(key, &bbox);
+        } else {
+            *self.large.entry(key.clone()).or_insert(0) += 1;
+        }
+    }
+
+    fn remove_from_grid(&mut self, key: &K, area: &Area) {
+        let bbox = area.bbox();
+        if self.grid.count(&bbox) <= MAX_GRID_CELLS {
+            self.grid.remove(key, &bbox);
+        } else if self.coarse_grid.count(&bbox) <= MAX_COARSE_GRID_CELLS {
+            self.coarse_grid.remove(key, &bbox);
+        } else {
+            decrement(&mut self.large, key);
+        }
+    }
+}
+
+/// Decrements the counter of `key`, removing it once it reaches zero.
+fn decrement<K: Eq + Hash>(counts: &mut HashMap<K, usize>, key: &K) {
+    if let Some(count) = counts.get_mut(key) {
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::{Circle, Polygon};
+
+    fn bbox(text: &str) -> Area {
+        Area::BoundingBox(BoundingBox::try_parse(text).unwrap())
+    }
+
+    fn insert(index: &mut AreaIndex<u32>, key: u32, name: String, area: Area) {
+        index.insert(key, name, area, PositionFilter::default());
+    }
+
+    fn query(index: &AreaIndex<u32>, longitude: f64, latitude: f64) -> Vec<u32> {
+        let mut keys: Vec<u32> = index
+            .query(longitude, latitude, 1000., 90., AircraftCategory::Glider)
+            .cloned()
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    #[test]
+    fn test_query() {
+        let mut index = AreaIndex::default();
+        insert(&mut index, 1, "a".to_owned(), bbox("5|49|7|51"));
+        insert(&mut index, 1, "b".to_owned(), bbox("6|50|8|52"));
+        insert(
+            &mut index,
+            2,
+            "c".to_owned(),
+            Area::Circle(Circle::new(6.5, 50.5, 10_000.).unwrap()),
+        );
+        insert(
+            &mut index,
+            3,
+            "d".to_owned(),
+            Area::Polygon(Polygon::new(vec![(6., 50.), (7., 50.), (7., 51.)]).unwrap()),
+        );
+
+        assert_eq!(query(&index, 6.5, 50.55), vec![1, 2]);
+        assert_eq!(query(&index, 6.9, 50.5), vec![1, 3]);
+        assert_eq!(query(&index, 7.5, 51.5), vec![1]);
+        assert_eq!(query(&index, 9., 50.), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_large_areas() {
+        let mut index = AreaIndex::default();
+        insert(&mut index, 1, "world".to_owned(), bbox("-180|-90|180|90"));
+        insert(&mut index, 1, "home".to_owned(), bbox("6|50|7|51"));
+        insert(&mut index, 2, "europe".to_owned(), bbox("-25|34|45|72"));
+        insert(&mut index, 2, "home".to_owned(), bbox("6|50|7|51"));
+        insert(&mut index, 3, "pacific".to_owned(), bbox("150|-40|-120|10"));
+
+        // the small areas are in the fine grid, the continent-sized areas in
+        // the coarse grid and only the whole world is checked every time
+        assert_eq!(index.grid.cells.len(), 4);
+        assert_eq!(index.coarse_grid.cells.len(), 40 + 60);
+        assert_eq!(index.large.len(), 1);
+        assert!(index.large.contains_key(&1));
+
+        // subscriber 2 is in both grids, but is only returned once
+        assert_eq!(query(&index, 6.5, 50.5), vec![1, 2]);
+        assert_eq!(query(&index, 10., 60.), vec![1, 2]);
+        assert_eq!(query(&index, -70., -33.), vec![1]);
+        assert_eq!(query(&index, 179.9, -39.9), vec![1, 3]);
+        assert_eq!(query(&index, -120.5, 9.5), vec![1, 3]);
+        assert_eq!(query(&index, -119.5, 9.5), vec![1]);
+
+        index.remove_all(&1);
+        index.remove_all(&2);
+        index.remove_all(&3);
+        assert!(index.grid.cells.is_empty());
+        assert!(index.coarse_grid.cells.is_empty());
+        assert!(index.large.is_empty());
+    }
+
+    #[test]
+    fn test_grid_cell() {
+        let grid = Grid::<u32>::new(COARSE_CELL_SIZE);
+        assert_eq!(grid.cell(6.5, 50.5), (0, 5));
+        assert_eq!(grid.cell(-6.5, -50.5), (-1, -6));
+        assert_eq!(grid.cell(180., 90.), (17, 8));
+        assert_eq!(grid.cell(-180., -90.), (-18, -9));
+
+        let bbox = BoundingBox::new(175., -5., -175., 5.).unwrap();
+        assert_eq!(grid.columns(&bbox), vec![17, -18]);
+        assert_eq!(grid.rows(&bbox), -1..=0);
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut index = AreaIndex::default();
+        let gliders = PositionFilter {
+            categories: vec![AircraftCategory::Glider].into_iter().collect(),
+            ..PositionFilter::default()
+        };
+        let high = PositionFilter {
+            min_altitude: Some(2000.),
+            ..PositionFilter::default()
+        };
+        index.insert(1, "gliders".to_owned(), bbox("5|49|7|51"), gliders);
+        index.insert(1, "high".to_owned(), bbox("6|50|8|52"), high);
+        insert(&mut index, 2, "all".to_owned(), bbox("5|49|7|51"));
+
+        let query = |longitude, latitude, altitude, category| {
+            let mut keys: Vec<u32> = index
+                .query(longitude, latitude, altitude, 90., category)
+                .cloned()
+                .collect();
+            keys.sort_unstable();
+            keys
+        };
+
+        use AircraftCategory::*;
+        assert_eq!(query(5.5, 49.5, 1000., Glider), vec![1, 2]);
+        assert_eq!(query(5.5, 49.5, 1000., Paraglider), vec![2]);
+        assert_eq!(query(6.5, 50.5, 3000., Paraglider), vec![1, 2]);
+        assert_eq!(query(7.5, 51.5, 3000., Paraglider), vec![1]);
+        assert_eq!(query(7.5, 51.5, 1000., Glider), Vec::<u32>::new());
+
+        assert_eq!(
+            index.areas(&1).unwrap()["high"].filter.min_altitude,
+            Some(2000.)
+        );
+    }
+
+    #[test]
+    fn test_wrap_around() {
+        let mut index = AreaIndex::default();
+        insert(&mut index, 1, "pacific".to_owned(), bbox("175|10|-160|12"));
+
+        assert_eq!(query(&index, 179.5, 11.), vec![1]);
+        assert_eq!(query(&index, -179.5, 11.), vec![1]);
+        assert_eq!(query(&index, 170., 11.), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut index = AreaIndex::default();
+        insert(&mut index, 1, "a".to_owned(), bbox("5|49|7|51"));
+        insert(&mut index, 1, "a".to_owned(), bbox("6|50|7|51"));
+        insert(&mut index, 1, "world".to_owned(), bbox("-180|-90|180|90"));
+        insert(&mut index, 2, "b".to_owned(), bbox("5|49|7|51"));
+
+        assert_eq!(query(&index, 5.5, 49.5), vec![1, 2]);
+
+        assert!(index.remove(&1, "world"));
+        assert!(!index.remove(&1, "world"));
+        assert_eq!(query(&index, 5.5, 49.5), vec![2]);
+        assert_eq!(query(&index, 6.5, 50.5), vec![1, 2]);
+
+        index.remove_all(&2);
+        assert_eq!(query(&index, 6.5, 50.5), vec![1]);
+        assert!(index.areas(&2).is_none());
+
+        assert!(index.remove(&1, "a"));
+        assert!(index.areas(&1).is_none());
+        assert!(index.grid.cells.is_empty());
+        assert!(index.large.is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::geo::BoundingBox;
+
+/// Number of coverage cells per degree of latitude/longitude
+pub const CELLS_PER_DEGREE: i32 = 100;
+
+/// Position of a coverage cell on the global latitude/longitude grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cell {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Cell {
+    pub fn from_position(longitude: f64, latitude: f64) -> Cell {
+        let cells_per_degree = f64::from(CELLS_PER_DEGREE);
+        Cell {
+            x: (longitude * cells_per_degree).floor() as i32,
+            y: (latitude * cells_per_degree).floor() as i32,
+        }
+    }
+
+    /// Longitude of the western edge of the cell
+    pub fn longitude(&self) -> f64 {
+        f64::from(self.x) / f64::from(CELLS_PER_DEGREE)
+    }
+
+    /// Latitude of the southern edge of the cell
+    pub fn latitude(&self) -> f64 {
+        f64::from(self.y) / f64::from(CELLS_PER_DEGREE)
+    }
+
+    /// The 1x1 degree tile that contains this cell
+    pub fn tile(&self) -> Tile {
+        Tile {
+            x: self.x.div_euclid(CELLS_PER_DEGREE),
+            y: self.y.div_euclid(CELLS_PER_DEGREE),
+        }
+    }
+
+    pub fn is_in(&self, bbox: &BoundingBox) -> bool {
+        let half_size = 0.5 / f64::from(CELLS_PER_DEGREE);
+        bbox.contains(self.longitude() + half_size, self.latitude() + half_size)
+    }
+}
+
+/// 1x1 degree tile of coverage cells, used to group the cells in redis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tile {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Tile {
+    /// Returns all tiles that intersect the `bbox`
+    pub fn covering(bbox: &BoundingBox) -> Vec<Tile> {
+        let bottom = bbox.bottom().floor() as i32;
+        let top = (bbox.top().floor() as i32).min(89);
+        let left = bbox.left().floor() as i32;
+        let right = (bbox.right().floor() as i32).min(179);
+
+        let columns: Vec<i32> = if bbox.left() > bbox.right() {
+            (left..=179).chain(-180..=right).collect()
+        } else {
+            (left..=right).collect()
+        };
+
+        let mut tiles = Vec::new();
+        for y in bottom..=top {
+            for x in &columns {
+                tiles.push(Tile { x: *x, y });
+            }
+        }
+        tiles
+    }
+}
+
+/// Reception statistics of a single receiver in a single coverage cell
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct CoverageStats {
+    pub count: u32,
+    /// lowest altitude in meters
+    pub min_altitude: i16,
+    /// maximum distance from the receiver in meters, `0` if unknown
+    pub max_range: f32,
+    pub snr_count: u32,
+    pub snr_sum: f32,
+}
+
+impl CoverageStats {
+    pub fn new(altitude: i16, range: Option<f32>, signal_quality: Option<f32>) -> CoverageStats {
+        CoverageStats {
+            count: 1,
+            min_altitude: altitude,
+            max_range: range.unwrap_or(0.),
+            snr_count: if signal_quality.is_some() { 1 } else { 0 },
+            snr_sum: signal_quality.unwrap_or(0.),
+        }
+    }
+
+    pub fn merge(&mut self, other: &CoverageStats) {
+        self.count += other.count;
+        self.min_altitude = self.min_altitude.min(other.min_altitude);
+        self.max_range = self.max_range.max(other.max_range);
+        self.snr_count += other.snr_count;
+        self.snr_sum += other.snr_sum;
+    }
+
+    /// Average signal-to-noise ratio in dB
+    pub fn average_snr(&self) -> Option<f32> {
+        if self.snr_count == 0 {
+            None
+        } else {
+            Some(self.snr_sum / self.snr_count as f32)
+        }
+    }
+}
+
+/// In-memory aggregation of coverage statistics that have not been saved yet,
+/// grouped by bucket time, receiver and cell.
+#[derive(Default)]
+pub struct CoverageBuffer {
+    cells: HashMap<(i64, String, Cell), CoverageStats>,
+}
+
+impl CoverageBuffer {
+    pub fn add(&mut self, bucket_time: i64, receiver: &str, cell: Cell, stats: CoverageStats) {
+        self.cells
+            .entry((bucket_time, receiver.to_owned(), cell))
+            .and_modify(|it| it.merge(&stats))
+            .or_insert(stats);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn take(&mut self) -> HashMap<(i64, String, Cell), CoverageStats> {
+        std::mem::take(&mut self.cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_cell() {
+        let cell = Cell::from_position(8.2627, 49.9969);
+        assert_eq!(cell, Cell { x: 826, y: 4999 });
+        assert_eq!(cell.tile(), Tile { x: 8, y: 49 });
+        assert_relative_eq!(cell.longitude(), 8.26);
+        assert_relative_eq!(cell.latitude(), 49.99);
+
+        let cell = Cell::from_position(-0.001, -33.5);
+        assert_eq!(cell, Cell { x: -1, y: -3350 });
+        assert_eq!(cell.tile(), Tile { x: -1, y: -34 });
+    }
+
+    #[test]
+    fn test_tiles_covering() {
+        let bbox = BoundingBox::try_parse("5.5|49.5|7.2|50.1").unwrap();
+        assert_eq!(
+            Tile::covering(&bbox),
+            vec![
+                Tile { x: 5, y: 49 },
+                Tile { x: 6, y: 49 },
+                Tile { x: 7, y: 49 },
+                Tile { x: 5, y: 50 },
+                Tile { x: 6, y: 50 },
+                Tile { x: 7, y: 50 },
+            ]
+        );
+
+        let bbox = BoundingBox::try_parse("178.5|10|-179.5|10.5").unwrap();
+        assert_eq!(
+            Tile::covering(&bbox),
+            vec![
+                Tile { x: 178, y: 10 },
+                Tile { x: 179, y: 10 },
+                Tile { x: -180, y: 10 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stats_merge() {
+        let mut stats = CoverageStats::new(1200, Some(35_000.), Some(10.));
+        stats.merge(&CoverageStats::new(800, Some(12_000.), None));
+        stats.merge(&CoverageStats::new(1500, None, Some(4.)));
+
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.min_altitude, 800);
+        assert_relative_eq!(stats.max_range, 35_000.);
+        assert_relative_eq!(stats.average_snr().unwrap(), 7.);
+    }
+}
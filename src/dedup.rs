@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+use std::mem;
+
+use chrono::NaiveTime;
+
+/// Number of position fractions per degree that are compared to detect
+/// duplicates (the APRS positions are less precise than this)
+const FRACTIONS_PER_DEGREE: f64 = 1_000_000.;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ReportKey {
+    id: String,
+    /// only set for the receptions of a report
+    receiver: Option<String>,
+    time: NaiveTime,
+    x: i32,
+    y: i32,
+}
+
+impl ReportKey {
+    fn new(
+        id: &str,
+        receiver: Option<&str>,
+        time: NaiveTime,
+        longitude: f64,
+        latitude: f64,
+    ) -> ReportKey {
+        ReportKey {
+            id: id.to_owned(),
+            receiver: receiver.map(|it| it.to_owned()),
+            time,
+            x: (longitude * FRACTIONS_PER_DEGREE).round() as i32,
+            y: (latitude * FRACTIONS_PER_DEGREE).round() as i32,
+        }
+    }
+}
+
+/// `DuplicateFilter` detects identical `(id, time, position)` reports that
+/// are received more than once, e.g. through multiple upstream feeds, and
+/// identical receptions of a report by the same receiver.
+///
+/// The seen reports are kept in two generations: `rotate()` drops the older
+/// generation, so that a report is remembered for at least one and at most
+/// two rotation intervals.
+#[derive(Debug, Default)]
+pub struct DuplicateFilter {
+    current: HashSet<ReportKey>,
+    previous: HashSet<ReportKey>,
+}
+
+impl DuplicateFilter {
+    /// Returns `true` if the report was already seen, or remembers it
+    /// otherwise.
+    pub fn is_duplicate(
+        &mut self,
+        id: &str,
+        time: NaiveTime,
+        longitude: f64,
+        latitude: f64,
+    ) -> bool {
+        self.check(ReportKey::new(id, None, time, longitude, latitude))
+    }
+
+    /// Returns `true` if the report was already seen from the same receiver,
+    /// or remembers it otherwise. Reports of other receivers are not
+    /// considered duplicates.
+    pub fn is_duplicate_reception(
+        &mut self,
+        id: &str,
+        receiver: &str,
+        time: NaiveTime,
+        longitude: f64,
+        latitude: f64,
+    ) -> bool {
+        self.check(ReportKey::new(
+            id,
+            Some(receiver),
+            time,
+            longitude,
+            latitude,
+        ))
+    }
+
+    fn check(&mut self, key: ReportKey) -> bool {
+        if self.previous.contains(&key) {
+            return true;
+        }
+
+        !self.current.insert(key)
+    }
+
+    pub fn rotate(&mut self) {
+        self.previous = mem::take(&mut self.current);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, minute: u32, second: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, second).unwrap()
+    }
+
+    #[test]
+    fn test_is_duplicate() {
+        let mut filter = DuplicateFilter::default();
+
+        assert!(!filter.is_duplicate("FLRDD9612", time(14, 19, 56), 8.265_5, 49.186_333));
+        assert!(filter.is_duplicate("FLRDD9612", time(14, 19, 56), 8.265_5, 49.186_333));
+
+        // different sender, time or position
+        assert!(!filter.is_duplicate("FLRDD9613", time(14, 19, 56), 8.265_5, 49.186_333));
+        assert!(!filter.is_duplicate("FLRDD9612", time(14, 19, 57), 8.265_5, 49.186_333));
+        assert!(!filter.is_duplicate("FLRDD9612", time(14, 19, 56), 8.265_6, 49.186_333));
+        assert!(!filter.is_duplicate("FLRDD9612", time(14, 19, 56), 8.265_5, 49.186_4));
+
+        assert_eq!(filter.current.len() + filter.previous.len(), 5);
+    }
+
+    #[test]
+    fn test_is_duplicate_reception() {
+        let mut filter = DuplicateFilter::default();
+        let time = time(14, 19, 56);
+
+        assert!(!filter.is_duplicate_reception("FLRDD9612", "LFNW", time, 8.265_5, 49.186_333));
+        assert!(filter.is_duplicate_reception("FLRDD9612", "LFNW", time, 8.265_5, 49.186_333));
+        assert!(!filter.is_duplicate_reception("FLRDD9612", "EDKA", time, 8.265_5, 49.186_333));
+
+        // receptions and reports are tracked separately
+        assert!(!filter.is_duplicate("FLRDD9612", time, 8.265_5, 49.186_333));
+    }
+
+    #[test]
+    fn test_rotate() {
+        let mut filter = DuplicateFilter::default();
+
+        assert!(!filter.is_duplicate("FLRDD9612", time(14, 19, 56), 8.265_5, 49.186_333));
+
+        filter.rotate();
+        assert!(filter.is_duplicate("FLRDD9612", time(14, 19, 56), 8.265_5, 49.186_333));
+        assert_eq!(filter.current.len() + filter.previous.len(), 1);
+
+        filter.rotate();
+        assert_eq!(filter.current.len() + filter.previous.len(), 0);
+        assert!(!filter.is_duplicate("FLRDD9612", time(14, 19, 56), 8.265_5, 49.186_333));
+    }
+}
@@ -0,0 +1,250 @@
+//! Rendering of stored tracks in common GIS formats.
+
+use std::fmt::Write;
+
+use chrono::SecondsFormat;
+use serde_json::json;
+
+use crate::redis::OGNPosition;
+
+/// Escapes the XML special characters in `text`.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Returns the tracks with at least one position, since the formats do not
+/// allow empty geometries.
+fn non_empty(
+    tracks: &[(String, Vec<OGNPosition>)],
+) -> impl Iterator<Item = &(String, Vec<OGNPosition>)> {
+    tracks.iter().filter(|(_, positions)| !positions.is_empty())
+}
+
+/// Renders the tracks as a GPX file with one `trk` per aircraft.
+pub fn render_gpx(tracks: &[(String, Vec<OGNPosition>)]) -> String {
+    let mut gpx = String::new();
+
+    gpx.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    gpx.push_str(
+        "<gpx version=\"1.1\" creator=\"ogn-web-gateway\" \
+         xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+    );
+
+    for (id, positions) in non_empty(tracks) {
+        writeln!(gpx, "  <trk>").unwrap();
+        writeln!(gpx, "    <name>{}</name>", escape_xml(id)).unwrap();
+        writeln!(gpx, "    <trkseg>").unwrap();
+        for position in positions {
+            writeln!(
+                gpx,
+                "      <trkpt lat=\"{:.6}\" lon=\"{:.6}\"><ele>{}</ele><time>{}</time></trkpt>",
+                position.latitude,
+                position.longitude,
+                position.altitude,
+                position.time.to_rfc3339_opts(SecondsFormat::Secs, true),
+            )
+            .unwrap();
+        }
+        writeln!(gpx, "    </trkseg>").unwrap();
+        writeln!(gpx, "  </trk>").unwrap();
+    }
+
+    gpx.push_str("</gpx>\n");
+
+    gpx
+}
+
+/// Renders the tracks as a KML file with one extruded `LineString` per
+/// aircraft, so that the altitude is visible in Google Earth. Aircraft with
+/// a single position are rendered as `Point`.
+pub fn render_kml(tracks: &[(String, Vec<OGNPosition>)]) -> String {
+    let mut kml = String::new();
+
+    kml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    kml.push_str("<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n");
+    kml.push_str("  <Document>\n");
+
+    for (id, positions) in non_empty(tracks) {
+        let geometry = if positions.len() == 1 {
+            "Point"
+        } else {
+            "LineString"
+        };
+
+        writeln!(kml, "    <Placemark>").unwrap();
+        writeln!(kml, "      <name>{}</name>", escape_xml(id)).unwrap();
+        writeln!(kml, "      <{}>", geometry).unwrap();
+        writeln!(kml, "        <extrude>1</extrude>").unwrap();
+        writeln!(kml, "        <altitudeMode>absolute</altitudeMode>").unwrap();
+        write!(kml, "        <coordinates>").unwrap();
+        for (i, position) in positions.iter().enumerate() {
+            if i > 0 {
+                kml.push(' ');
+            }
+            write!(
+                kml,
+                "{:.6},{:.6},{}",
+                position.longitude, position.latitude, position.altitude
+            )
+            .unwrap();
+        }
+        writeln!(kml, "</coordinates>").unwrap();
+        writeln!(kml, "      </{}>", geometry).unwrap();
+        writeln!(kml, "    </Placemark>").unwrap();
+    }
+
+    kml.push_str("  </Document>\n");
+    kml.push_str("</kml>\n");
+
+    kml
+}
+
+/// Renders the tracks as a GeoJSON `FeatureCollection` with one
+/// `LineString` feature per aircraft, or a `Point` feature for aircraft
+/// with a single position. The timestamps of the coordinates are saved in
+/// the `times` property of the features.
+pub fn render_geojson(tracks: &[(String, Vec<OGNPosition>)]) -> serde_json::Value {
+    let features: Vec<_> = non_empty(tracks)
+        .map(|(id, positions)| {
+            let mut coordinates: Vec<_> = positions
+                .iter()
+                .map(|it| json!([round6(it.longitude), round6(it.latitude), it.altitude]))
+                .collect();
+
+            let geometry = if coordinates.len() == 1 {
+                json!({
+                    "type": "Point",
+                    "coordinates": coordinates.remove(0),
+                })
+            } else {
+                json!({
+                    "type": "LineString",
+                    "coordinates": coordinates,
+                })
+            };
+
+            let times: Vec<_> = positions.iter().map(|it| it.time.timestamp()).collect();
+
+            json!({
+                "type": "Feature",
+                "geometry": geometry,
+                "properties": {
+                    "id": id,
+                    "times": times,
+                },
+            })
+        })
+        .collect();
+
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+/// Rounds a coordinate to six decimals, like the other output formats
+fn round6(value: f32) -> f64 {
+    (f64::from(value) * 1_000_000.).round() / 1_000_000.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::prelude::*;
+
+    fn tracks() -> Vec<(String, Vec<OGNPosition>)> {
+        let position = |second, altitude| {
+            let time = Utc.with_ymd_and_hms(2018, 4, 17, 14, 19, second).unwrap();
+            OGNPosition::fixture(time, altitude, 109.)
+        };
+
+        vec![(
+            "FLRDD9612".to_owned(),
+            vec![position(56, 1133), position(58, 1135)],
+        )]
+    }
+
+    #[test]
+    fn test_gpx() {
+        let gpx = render_gpx(&tracks());
+        assert!(gpx.starts_with("<?xml"));
+        assert!(gpx.contains("<name>FLRDD9612</name>"));
+        assert!(gpx.contains(
+            "<trkpt lat=\"49.186333\" lon=\"8.265500\"><ele>1133</ele><time>2018-04-17T14:19:56Z</time></trkpt>"
+        ));
+        assert!(gpx.ends_with("</gpx>\n"));
+    }
+
+    #[test]
+    fn test_kml() {
+        let kml = render_kml(&tracks());
+        assert!(kml.contains("<extrude>1</extrude>"));
+        assert!(kml.contains(
+            "<coordinates>8.265500,49.186333,1133 8.265500,49.186333,1135</coordinates>"
+        ));
+    }
+
+    #[test]
+    fn test_geojson() {
+        assert_eq!(
+            render_geojson(&tracks()),
+            json!({
+                "type": "FeatureCollection",
+                "features": [{
+                    "type": "Feature",
+                    "geometry": {
+                        "type": "LineString",
+                        "coordinates": [[8.2655, 49.186333, 1133], [8.2655, 49.186333, 1135]],
+                    },
+                    "properties": {
+                        "id": "FLRDD9612",
+                        "times": [1523974796, 1523974798],
+                    },
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_single_position() {
+        let mut tracks = tracks();
+        tracks[0].1.truncate(1);
+
+        let kml = render_kml(&tracks);
+        assert!(kml.contains("<Point>"));
+        assert!(kml.contains("<coordinates>8.265500,49.186333,1133</coordinates>"));
+        assert!(!kml.contains("LineString"));
+
+        assert_eq!(
+            render_geojson(&tracks)["features"][0]["geometry"],
+            json!({
+                "type": "Point",
+                "coordinates": [8.2655, 49.186333, 1133],
+            })
+        );
+    }
+
+    #[test]
+    fn test_empty_tracks() {
+        let mut tracks = tracks();
+        tracks.push(("FLRDD9613".to_owned(), Vec::new()));
+
+        assert!(!render_gpx(&tracks).contains("FLRDD9613"));
+        assert!(!render_kml(&tracks).contains("FLRDD9613"));
+        assert_eq!(
+            render_geojson(&tracks)["features"]
+                .as_array()
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(escape_xml("a<b>&\"c\""), "a&lt;b&gt;&amp;&quot;c&quot;");
+    }
+}
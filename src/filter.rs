@@ -0,0 +1,228 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// Aircraft category that live position subscriptions can be filtered by.
+///
+/// The category is derived from the aircraft type in the on-air ID token,
+/// or from the OGN Device Database if the sender does not transmit a type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AircraftCategory {
+    Glider,
+    Plane,
+    Ultralight,
+    Helicopter,
+    Drone,
+    Paraglider,
+    HangGlider,
+    Parachute,
+    Balloon,
+    Ground,
+    Other,
+    Unknown,
+}
+
+const ALL_CATEGORIES: [AircraftCategory; 12] = [
+    AircraftCategory::Glider,
+    AircraftCategory::Plane,
+    AircraftCategory::Ultralight,
+    AircraftCategory::Helicopter,
+    AircraftCategory::Drone,
+    AircraftCategory::Paraglider,
+    AircraftCategory::HangGlider,
+    AircraftCategory::Parachute,
+    AircraftCategory::Balloon,
+    AircraftCategory::Ground,
+    AircraftCategory::Other,
+    AircraftCategory::Unknown,
+];
+
+impl AircraftCategory {
+    /// Detects the category from the on-air aircraft type (e.g. `7` for
+    /// paragliders) and the `DeviceInfo.category` of the OGN Device Database.
+    pub fn detect(aircraft_type: Option<u8>, ddb_category: Option<i16>) -> AircraftCategory {
+        match aircraft_type {
+            Some(1) => AircraftCategory::Glider,
+            Some(2) | Some(5) | Some(8) | Some(9) => AircraftCategory::Plane,
+            Some(3) => AircraftCategory::Helicopter,
+            Some(4) => AircraftCategory::Parachute,
+            Some(6) => AircraftCategory::HangGlider,
+            Some(7) => AircraftCategory::Paraglider,
+            Some(10) => AircraftCategory::Other,
+            Some(11) | Some(12) => AircraftCategory::Balloon,
+            Some(13) => AircraftCategory::Drone,
+            Some(14) | Some(15) => AircraftCategory::Ground,
+            _ => match ddb_category {
+                Some(1) => AircraftCategory::Glider,
+                Some(2) => AircraftCategory::Plane,
+                Some(3) => AircraftCategory::Ultralight,
+                Some(4) => AircraftCategory::Helicopter,
+                Some(5) => AircraftCategory::Drone,
+                Some(6) => AircraftCategory::Other,
+                _ => AircraftCategory::Unknown,
+            },
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AircraftCategory::Glider => "glider",
+            AircraftCategory::Plane => "plane",
+            AircraftCategory::Ultralight => "ultralight",
+            AircraftCategory::Helicopter => "helicopter",
+            AircraftCategory::Drone => "drone",
+            AircraftCategory::Paraglider => "paraglider",
+            AircraftCategory::HangGlider => "hang-glider",
+            AircraftCategory::Parachute => "parachute",
+            AircraftCategory::Balloon => "balloon",
+            AircraftCategory::Ground => "ground",
+            AircraftCategory::Other => "other",
+            AircraftCategory::Unknown => "unknown",
+        }
+    }
+}
+
+impl FromStr for AircraftCategory {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        ALL_CATEGORIES
+            .iter()
+            .find(|it| it.as_str() == text)
+            .cloned()
+            .ok_or(())
+    }
+}
+
+/// Restrictions of an area subscription of a websocket client
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PositionFilter {
+    /// minimum altitude in meters
+    pub min_altitude: Option<f64>,
+    /// maximum altitude in meters
+    pub max_altitude: Option<f64>,
+    /// minimum ground speed in km/h, to suppress stationary objects
+    pub min_speed: Option<f64>,
+    /// empty if all categories are allowed
+    pub categories: HashSet<AircraftCategory>,
+}
+
+impl PositionFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == PositionFilter::default()
+    }
+
+    pub fn matches(&self, altitude: f64, speed: f64, category: AircraftCategory) -> bool {
+        self.min_altitude.is_none_or(|min| altitude >= min)
+            && self.max_altitude.is_none_or(|max| altitude <= max)
+            && self.min_speed.is_none_or(|min| speed >= min)
+            && (self.categories.is_empty() || self.categories.contains(&category))
+    }
+
+    /// Parses the `<min altitude>|<max altitude>|<min speed>|<categories>`
+    /// arguments of the `filter|` text command. Empty values are not
+    /// restricted.
+    pub fn try_parse(text: &str) -> Option<PositionFilter> {
+        let mut parts = text.split('|');
+
+        let mut parse_number = || match parts.next() {
+            None | Some("") => Ok(None),
+            Some(value) => match value.parse::<f64>() {
+                Ok(number) if number.is_finite() => Ok(Some(number)),
+                _ => Err(()),
+            },
+        };
+
+        let min_altitude = parse_number().ok()?;
+        let max_altitude = parse_number().ok()?;
+        let min_speed = parse_number().ok()?;
+
+        let categories = parts
+            .next()
+            .unwrap_or("")
+            .split(',')
+            .filter(|it| !it.is_empty())
+            .map(|it| it.parse::<AircraftCategory>().ok())
+            .collect::<Option<_>>()?;
+
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(PositionFilter {
+            min_altitude,
+            max_altitude,
+            min_speed,
+            categories,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        use AircraftCategory::*;
+
+        assert_eq!(AircraftCategory::detect(Some(7), Some(1)), Paraglider);
+        assert_eq!(AircraftCategory::detect(Some(9), None), Plane);
+        assert_eq!(AircraftCategory::detect(Some(0), Some(3)), Ultralight);
+        assert_eq!(AircraftCategory::detect(None, Some(1)), Glider);
+        assert_eq!(AircraftCategory::detect(None, None), Unknown);
+    }
+
+    #[test]
+    fn test_parse_category() {
+        for category in ALL_CATEGORIES.iter() {
+            assert_eq!(category.as_str().parse(), Ok(*category));
+        }
+
+        assert_eq!("car".parse::<AircraftCategory>(), Err(()));
+    }
+
+    #[test]
+    fn test_matches() {
+        let filter = PositionFilter {
+            min_altitude: Some(500.),
+            max_altitude: Some(3000.),
+            min_speed: Some(10.),
+            categories: vec![AircraftCategory::Glider].into_iter().collect(),
+        };
+
+        assert!(filter.matches(1000., 90., AircraftCategory::Glider));
+        assert!(!filter.matches(400., 90., AircraftCategory::Glider));
+        assert!(!filter.matches(3500., 90., AircraftCategory::Glider));
+        assert!(!filter.matches(1000., 0., AircraftCategory::Glider));
+        assert!(!filter.matches(1000., 90., AircraftCategory::Paraglider));
+
+        assert!(PositionFilter::default().matches(0., 0., AircraftCategory::Unknown));
+    }
+
+    #[test]
+    fn test_try_parse() {
+        assert_eq!(
+            PositionFilter::try_parse("500||10|glider,hang-glider"),
+            Some(PositionFilter {
+                min_altitude: Some(500.),
+                max_altitude: None,
+                min_speed: Some(10.),
+                categories: vec![AircraftCategory::Glider, AircraftCategory::HangGlider]
+                    .into_iter()
+                    .collect(),
+            })
+        );
+
+        assert_eq!(
+            PositionFilter::try_parse(""),
+            Some(PositionFilter::default())
+        );
+        assert!(PositionFilter::try_parse("").unwrap().is_empty());
+        assert_eq!(PositionFilter::try_parse("high"), None);
+        assert_eq!(PositionFilter::try_parse("|||car"), None);
+        assert_eq!(PositionFilter::try_parse("NaN"), None);
+        assert_eq!(PositionFilter::try_parse("|inf"), None);
+        assert_eq!(PositionFilter::try_parse("||-infinity"), None);
+        assert_eq!(PositionFilter::try_parse("500|||glider|1000"), None);
+    }
+}
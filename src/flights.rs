@@ -0,0 +1,494 @@
+use std::collections::HashMap;
+
+use chrono::prelude::*;
+use chrono::Duration;
+
+use crate::filter::AircraftCategory;
+use crate::redis::{OGNFlight, OGNFlightLocation, OGNPosition};
+
+/// Seconds within which the takeoff height has to be gained
+const TAKEOFF_SECONDS: i64 = 60;
+
+/// Maximum altitude change in meters between the slow fixes of a landing,
+/// to tell aircraft on the ground from aircraft hovering in the wind
+const LANDING_ALTITUDE_TOLERANCE: i16 = 10;
+
+/// Minutes without any position after which a flight is considered finished
+const SIGNAL_LOSS_MINUTES: i64 = 15;
+
+/// Takeoff and landing thresholds of an aircraft category
+#[derive(Debug, Clone, Copy)]
+struct Thresholds {
+    /// ground speed in km/h below which an aircraft on the ground is
+    /// considered to be standing (e.g. before a takeoff)
+    ground_speed: f32,
+    /// ground speed in km/h above which a takeoff is possible
+    takeoff_speed: f32,
+    /// height in meters that has to be gained within `TAKEOFF_SECONDS`,
+    /// so that cars and taxiing aircraft do not start flights
+    takeoff_height: i16,
+    /// `true` if losing the `takeoff_height` is a takeoff as well, e.g. for
+    /// foot launches from a slope
+    takeoff_descending: bool,
+    /// ground speed in km/h below which an aircraft may be landed
+    landing_speed: f32,
+    /// seconds that an aircraft has to stay slow at the same altitude to
+    /// confirm a landing, `0` if the next slow fix confirms it
+    landing_seconds: i64,
+    /// maximum height in meters above the takeoff elevation at which a slow
+    /// aircraft is considered to be landed (e.g. not wave soaring)
+    max_landing_height: i16,
+}
+
+impl Thresholds {
+    /// Returns the thresholds of the category, or `None` for ground vehicles.
+    fn of(category: AircraftCategory) -> Option<Thresholds> {
+        use AircraftCategory::*;
+
+        Some(match category {
+            Ground => return None,
+            Paraglider | HangGlider => Thresholds {
+                ground_speed: 15.,
+                takeoff_speed: 15.,
+                takeoff_height: 50,
+                takeoff_descending: true,
+                landing_speed: 5.,
+                landing_seconds: 120,
+                max_landing_height: 100,
+            },
+            Balloon | Parachute => Thresholds {
+                ground_speed: 5.,
+                takeoff_speed: 0.,
+                takeoff_height: 50,
+                takeoff_descending: false,
+                landing_speed: 5.,
+                landing_seconds: 120,
+                max_landing_height: 100,
+            },
+            Helicopter | Drone => Thresholds {
+                ground_speed: 10.,
+                takeoff_speed: 0.,
+                takeoff_height: 50,
+                takeoff_descending: false,
+                landing_speed: 10.,
+                landing_seconds: 60,
+                max_landing_height: 100,
+            },
+            Glider | Plane | Ultralight | Other | Unknown => Thresholds {
+                ground_speed: 50.,
+                takeoff_speed: 50.,
+                takeoff_height: 50,
+                takeoff_descending: false,
+                landing_speed: 20.,
+                landing_seconds: 0,
+                max_landing_height: 300,
+            },
+        })
+    }
+}
+
+#[derive(Debug)]
+enum State {
+    OnGround {
+        /// first fix of a possible takeoff, confirmed once the aircraft
+        /// climbed while staying fast
+        takeoff: Option<OGNFlightLocation>,
+    },
+    Airborne {
+        flight: OGNFlight,
+        /// first fix of a possible landing, confirmed by the following slow
+        /// fixes at the same altitude
+        landing: Option<OGNFlightLocation>,
+    },
+}
+
+#[derive(Debug)]
+struct Aircraft {
+    state: State,
+    last_fix: OGNFlightLocation,
+    /// `false` for aircraft that were already flying when first seen
+    seen_on_ground: bool,
+}
+
+/// `FlightTracker` detects takeoffs and landings in the position stream
+/// based on the ground speed and the height above ground, using different
+/// thresholds depending on the `AircraftCategory`.
+///
+/// A takeoff requires consecutive fixes above the takeoff speed that gain
+/// (or for foot launches lose) 50m of height within a minute. A landing
+/// requires slow fixes at a steady altitude for a category-specific time,
+/// so that single outliers and e.g. paragliders soaring in strong wind do
+/// not split flights. Ground vehicles are not tracked.
+///
+/// There is no terrain model, so the height above ground is approximated by
+/// the height above the takeoff fix. Aircraft that were first seen in the
+/// air have no takeoff fix, so their landings are only detected by the
+/// ground speed and the steady altitude.
+///
+/// The state is only kept in memory, so after a restart aircraft that are
+/// still airborne get a new flight without takeoff.
+#[derive(Debug, Default)]
+pub struct FlightTracker {
+    aircraft: HashMap<String, Aircraft>,
+}
+
+impl FlightTracker {
+    /// Processes the next position of an aircraft and returns the flight if
+    /// a takeoff or landing was detected.
+    pub fn update(
+        &mut self,
+        id: &str,
+        category: AircraftCategory,
+        position: &OGNPosition,
+    ) -> Option<OGNFlight> {
+        let thresholds = Thresholds::of(category)?;
+
+        let fix = OGNFlightLocation {
+            time: position.time,
+            longitude: position.longitude,
+            latitude: position.latitude,
+            altitude: position.altitude,
+        };
+
+        let aircraft = match self.aircraft.get_mut(id) {
+            // ignore outdated and duplicate fixes
+            Some(aircraft) if fix.time <= aircraft.last_fix.time => return None,
+            Some(aircraft) => {
+                aircraft.last_fix = fix;
+                aircraft
+            }
+            None => self.aircraft.entry(id.to_owned()).or_insert(Aircraft {
+                state: State::OnGround { takeoff: None },
+                last_fix: fix,
+                seen_on_ground: false,
+            }),
+        };
+
+        match &mut aircraft.state {
+            State::OnGround { takeoff } => {
+                if position.speed < thresholds.ground_speed {
+                    aircraft.seen_on_ground = true;
+                }
+
+                if position.speed < thresholds.takeoff_speed {
+                    *takeoff = None;
+                    return None;
+                }
+
+                let start = match *takeoff {
+                    Some(start) if fix.time - start.time <= Duration::seconds(TAKEOFF_SECONDS) => {
+                        start
+                    }
+                    _ => {
+                        *takeoff = Some(fix);
+                        return None;
+                    }
+                };
+
+                let height = if thresholds.takeoff_descending {
+                    (fix.altitude - start.altitude).abs()
+                } else {
+                    fix.altitude - start.altitude
+                };
+
+                if height < thresholds.takeoff_height {
+                    return None;
+                }
+
+                // aircraft that were already flying when they were first
+                // seen get a flight without takeoff
+                let flight = OGNFlight {
+                    id: id.to_owned(),
+                    start: start.time,
+                    end: None,
+                    takeoff: Some(start).filter(|_| aircraft.seen_on_ground),
+                    landing: None,
+                };
+
+                aircraft.state = State::Airborne {
+                    flight: flight.clone(),
+                    landing: None,
+                };
+
+                Some(flight)
+            }
+            State::Airborne { flight, landing } => {
+                let is_low = flight.takeoff.is_none_or(|takeoff| {
+                    fix.altitude - takeoff.altitude < thresholds.max_landing_height
+                });
+
+                if position.speed >= thresholds.landing_speed || !is_low {
+                    *landing = None;
+                    return None;
+                }
+
+                let start = match *landing {
+                    Some(start)
+                        if (fix.altitude - start.altitude).abs() <= LANDING_ALTITUDE_TOLERANCE =>
+                    {
+                        start
+                    }
+                    _ => {
+                        *landing = Some(fix);
+                        return None;
+                    }
+                };
+
+                if fix.time - start.time < Duration::seconds(thresholds.landing_seconds) {
+                    return None;
+                }
+
+                let landing = start;
+
+                let mut flight = flight.clone();
+                flight.end = Some(landing.time);
+                flight.landing = Some(landing);
+
+                aircraft.state = State::OnGround { takeoff: None };
+
+                Some(flight)
+            }
+        }
+    }
+
+    /// Forgets all aircraft that have not been seen for a while and returns
+    /// the flights that were ended by the signal loss.
+    pub fn expire(&mut self, now: DateTime<Utc>) -> Vec<OGNFlight> {
+        let cutoff = now - Duration::minutes(SIGNAL_LOSS_MINUTES);
+
+        let mut flights = Vec::new();
+        self.aircraft.retain(|_, aircraft| {
+            if aircraft.last_fix.time >= cutoff {
+                return true;
+            }
+
+            if let State::Airborne { flight, landing } = &aircraft.state {
+                // use the unconfirmed landing, if the aircraft was already slow
+                let mut flight = flight.clone();
+                flight.end = Some(landing.map_or(aircraft.last_fix.time, |it| it.time));
+                flight.landing = *landing;
+                flights.push(flight);
+            }
+
+            false
+        });
+
+        flights
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(minute: u32, second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2018, 4, 17, 14, minute, second)
+            .unwrap()
+    }
+
+    fn update(
+        tracker: &mut FlightTracker,
+        time: (u32, u32),
+        speed: f32,
+        altitude: i16,
+    ) -> Option<OGNFlight> {
+        update_with_category(tracker, AircraftCategory::Glider, time, speed, altitude)
+    }
+
+    fn update_with_category(
+        tracker: &mut FlightTracker,
+        category: AircraftCategory,
+        (minute, second): (u32, u32),
+        speed: f32,
+        altitude: i16,
+    ) -> Option<OGNFlight> {
+        let position = OGNPosition::fixture(time(minute, second), altitude, speed);
+
+        tracker.update("FLRDD9612", category, &position)
+    }
+
+    #[test]
+    fn test_takeoff_and_landing() {
+        let mut tracker = FlightTracker::default();
+
+        assert_eq!(update(&mut tracker, (0, 0), 0., 120), None);
+        assert_eq!(update(&mut tracker, (0, 10), 10., 120), None);
+
+        // a single fast fix is not a takeoff yet
+        assert_eq!(update(&mut tracker, (0, 20), 60., 120), None);
+        assert_eq!(update(&mut tracker, (0, 30), 5., 120), None);
+
+        // fast, but not climbing yet
+        assert_eq!(update(&mut tracker, (1, 0), 70., 121), None);
+        assert_eq!(update(&mut tracker, (1, 10), 90., 150), None);
+        let flight = update(&mut tracker, (1, 20), 90., 210).unwrap();
+        assert_eq!(flight.id, "FLRDD9612");
+        assert_eq!(flight.start, time(1, 0));
+        assert_eq!(flight.takeoff.unwrap().altitude, 121);
+        assert_eq!(flight.end, None);
+        assert_eq!(flight.landing, None);
+
+        // slow, but high above the takeoff elevation
+        assert_eq!(update(&mut tracker, (20, 0), 10., 1500), None);
+        assert_eq!(update(&mut tracker, (20, 10), 10., 1500), None);
+
+        assert_eq!(update(&mut tracker, (30, 0), 80., 200), None);
+        assert_eq!(update(&mut tracker, (30, 20), 15., 125), None);
+        let flight = update(&mut tracker, (30, 30), 0., 125).unwrap();
+        assert_eq!(flight.start, time(1, 0));
+        assert_eq!(flight.end, Some(time(30, 20)));
+        assert_eq!(flight.landing.unwrap().altitude, 125);
+    }
+
+    #[test]
+    fn test_first_seen_airborne() {
+        let mut tracker = FlightTracker::default();
+
+        assert_eq!(update(&mut tracker, (0, 0), 90., 800), None);
+        let flight = update(&mut tracker, (0, 10), 90., 860).unwrap();
+        assert_eq!(flight.start, time(0, 0));
+        assert_eq!(flight.takeoff, None);
+
+        // outdated fixes are ignored
+        assert_eq!(update(&mut tracker, (0, 0), 0., 800), None);
+
+        assert_eq!(update(&mut tracker, (5, 0), 10., 300), None);
+        let flight = update(&mut tracker, (5, 10), 0., 300).unwrap();
+        assert_eq!(flight.end, Some(time(5, 0)));
+    }
+
+    #[test]
+    fn test_expire() {
+        let mut tracker = FlightTracker::default();
+
+        update(&mut tracker, (0, 0), 0., 120);
+        update(&mut tracker, (0, 10), 90., 120);
+        update(&mut tracker, (0, 20), 90., 180);
+
+        assert!(tracker.expire(time(10, 0)).is_empty());
+        assert_eq!(tracker.aircraft.len(), 1);
+
+        let flights = tracker.expire(time(40, 0));
+        assert_eq!(flights.len(), 1);
+        assert_eq!(flights[0].end, Some(time(0, 20)));
+        assert_eq!(flights[0].landing, None);
+        assert!(tracker.aircraft.is_empty());
+
+        // the signal was lost during the landing
+        update(&mut tracker, (50, 0), 90., 500);
+        update(&mut tracker, (50, 10), 90., 560);
+        update(&mut tracker, (50, 20), 10., 120);
+
+        let flights = tracker.expire(time(59, 0) + Duration::minutes(10));
+        assert_eq!(flights.len(), 1);
+        assert_eq!(flights[0].end, Some(time(50, 20)));
+        assert_eq!(flights[0].landing.unwrap().altitude, 120);
+    }
+
+    #[test]
+    fn test_fast_without_climb() {
+        let mut tracker = FlightTracker::default();
+
+        // e.g. a car driving past a receiver on a slightly rising road
+        for (i, altitude) in (0..30).map(|i| (i, 300 + i as i16 * 4)) {
+            assert_eq!(
+                update(&mut tracker, (i / 6, i % 6 * 10), 80., altitude),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn test_ground_vehicles() {
+        let mut tracker = FlightTracker::default();
+
+        let ground = AircraftCategory::Ground;
+        assert_eq!(
+            update_with_category(&mut tracker, ground, (0, 0), 0., 120),
+            None
+        );
+        assert_eq!(
+            update_with_category(&mut tracker, ground, (0, 10), 90., 120),
+            None
+        );
+        assert_eq!(
+            update_with_category(&mut tracker, ground, (0, 20), 90., 500),
+            None
+        );
+        assert!(tracker.aircraft.is_empty());
+    }
+
+    #[test]
+    fn test_paraglider() {
+        let mut tracker = FlightTracker::default();
+        let paraglider = AircraftCategory::Paraglider;
+        let mut update = |time, speed, altitude| {
+            update_with_category(&mut tracker, paraglider, time, speed, altitude)
+        };
+
+        // foot launch from a slope, losing height while never reaching 50 km/h
+        assert_eq!(update((0, 0), 0., 1000), None);
+        assert_eq!(update((0, 10), 20., 990), None);
+        assert_eq!(update((0, 20), 25., 960), None);
+        let flight = update((0, 30), 25., 935).unwrap();
+        assert_eq!(flight.start, time(0, 10));
+        assert_eq!(flight.takeoff.unwrap().altitude, 990);
+
+        // slow ridge soaring in strong wind
+        for i in 0..12 {
+            let altitude = if i % 2 == 0 { 1020 } else { 1040 };
+            assert_eq!(update((10, i * 5), 3., altitude), None);
+        }
+
+        // landed in the valley
+        assert_eq!(update((30, 0), 4., 400), None);
+        assert_eq!(update((30, 30), 0., 401), None);
+        assert_eq!(update((31, 0), 0., 400), None);
+        let flight = update((32, 0), 0., 399).unwrap();
+        assert_eq!(flight.start, time(0, 10));
+        assert_eq!(flight.end, Some(time(30, 0)));
+        assert_eq!(flight.landing.unwrap().altitude, 400);
+    }
+
+    #[test]
+    fn test_slow_first_seen_airborne() {
+        let mut tracker = FlightTracker::default();
+        let paraglider = AircraftCategory::Paraglider;
+        let mut update = |time, speed, altitude| {
+            update_with_category(&mut tracker, paraglider, time, speed, altitude)
+        };
+
+        assert_eq!(update((0, 0), 30., 1500), None);
+        let flight = update((0, 10), 30., 1560).unwrap();
+        assert_eq!(flight.takeoff, None);
+
+        // two slow fixes do not end the flight
+        assert_eq!(update((5, 0), 10., 1550), None);
+        assert_eq!(update((5, 10), 2., 1540), None);
+        assert_eq!(update((5, 20), 1., 1560), None);
+        assert_eq!(update((6, 30), 1., 1580), None);
+        assert_eq!(update((7, 0), 2., 1560), None);
+
+        // but staying at the same altitude does
+        assert_eq!(update((20, 0), 0., 800), None);
+        assert_eq!(update((21, 0), 0., 800), None);
+        let flight = update((22, 0), 0., 800).unwrap();
+        assert_eq!(flight.end, Some(time(20, 0)));
+    }
+
+    #[test]
+    fn test_balloon() {
+        let mut tracker = FlightTracker::default();
+        let balloon = AircraftCategory::Balloon;
+        let mut update = |time, speed, altitude| {
+            update_with_category(&mut tracker, balloon, time, speed, altitude)
+        };
+
+        // launch without wind
+        assert_eq!(update((0, 0), 0., 200), None);
+        assert_eq!(update((0, 20), 2., 220), None);
+        let flight = update((0, 40), 3., 260).unwrap();
+        assert_eq!(flight.start, time(0, 0));
+        assert_eq!(flight.takeoff.unwrap().altitude, 200);
+    }
+}
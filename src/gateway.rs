@@ -1,39 +1,97 @@
 use std::collections::*;
 use std::iter::FromIterator;
+use std::sync::Arc;
 use std::time::Duration;
 
 use actix::prelude::*;
 use actix_ogn::OGNMessage;
+use anyhow::{anyhow, Result};
 use chrono::prelude::*;
 use log::{debug, error, warn};
 
-use crate::geo::BoundingBox;
+use crate::airfields::{self, Airfield};
+use crate::archive::{self, ArchiveLine, ArchiveRecorder, FlushArchive};
+use crate::area_index::{AreaIndex, AreaSubscription};
+use crate::coverage::{Cell, CoverageBuffer, CoverageStats};
+use crate::dedup::DuplicateFilter;
+use crate::filter::{AircraftCategory, PositionFilter};
+use crate::flights::FlightTracker;
+use crate::geo::{self, Area};
 use crate::ogn;
-use crate::redis::{self, RedisExecutor};
-use crate::ws_client::{SendTextFast, SendTextSlow, WSClient};
+use crate::ogn::aprs::{
+    APRSMessage, APRSPosition, ParseError, ReceiverPosition, ReceiverStatus, SourceNetwork,
+};
+use crate::protocol::LivePosition;
+use crate::redis::{self, RedisExecutor, ToBucketTime};
+use crate::upstream::UpstreamStatus;
+use crate::ws_client::{SendPositionFast, SendPositionSlow, WSClient};
+
+/// Maximum number of area subscriptions per websocket client
+const MAX_AREAS: usize = 32;
+
+/// Name of the area subscription that is set by the `bbox|` command
+pub const DEFAULT_AREA: &str = "default";
 
 /// `Gateway` manages connected websocket clients and distributes
 /// `OGNRecord` messages to them.
 pub struct Gateway {
     redis: Addr<RedisExecutor>,
     ws_clients: HashSet<Addr<WSClient>>,
-    id_subscriptions: HashMap<String, Vec<Addr<WSClient>>>,
-    bbox_subscriptions: HashMap<Addr<WSClient>, BoundingBox>,
+    id_subscriptions: HashMap<String, HashSet<Addr<WSClient>>>,
+    /// named area subscriptions of the websocket clients
+    area_subscriptions: AreaIndex<Addr<WSClient>>,
+    network_filters: HashMap<Addr<WSClient>, HashSet<SourceNetwork>>,
     ignore_list: HashSet<String>,
+    /// `DeviceInfo.category` of the devices in the OGN Device Database
+    device_categories: HashMap<String, i16>,
+    duplicates: DuplicateFilter,
     redis_buffer: Vec<(String, redis::OGNPosition)>,
+    receiver_positions_buffer: HashMap<String, redis::OGNReceiverPosition>,
+    receiver_status_buffer: HashMap<String, redis::OGNReceiverStatus>,
+    receiver_locations: HashMap<String, (f64, f64)>,
+    coverage_buffer: CoverageBuffer,
+    flight_tracker: FlightTracker,
+    flights_buffer: HashMap<String, redis::OGNFlight>,
+    airfields: Vec<Airfield>,
+    /// logbook entries by airfield, date and flight ID
+    logbook_buffer: HashMap<(String, NaiveDate, String), redis::LogbookEntry>,
     record_count: Option<u64>,
+    rejected_lines: HashMap<ParseError, u64>,
+    store_receivers: bool,
+    archive: Option<Addr<ArchiveRecorder>>,
+    /// connection state and health of the upstream feeds, by feed index
+    upstreams: BTreeMap<usize, UpstreamStatus>,
 }
 
 impl Gateway {
-    pub fn new(redis: Addr<RedisExecutor>) -> Gateway {
+    pub fn new(
+        redis: Addr<RedisExecutor>,
+        store_receivers: bool,
+        archive: Option<Addr<ArchiveRecorder>>,
+    ) -> Gateway {
         Gateway {
             redis,
             ws_clients: HashSet::new(),
             id_subscriptions: HashMap::new(),
-            bbox_subscriptions: HashMap::new(),
+            area_subscriptions: AreaIndex::default(),
+            network_filters: HashMap::new(),
             ignore_list: HashSet::new(),
+            device_categories: HashMap::new(),
+            duplicates: DuplicateFilter::default(),
             redis_buffer: Vec::new(),
+            receiver_positions_buffer: HashMap::new(),
+            receiver_status_buffer: HashMap::new(),
+            receiver_locations: HashMap::new(),
+            coverage_buffer: CoverageBuffer::default(),
+            flight_tracker: FlightTracker::default(),
+            flights_buffer: HashMap::new(),
+            airfields: Vec::new(),
+            logbook_buffer: HashMap::new(),
             record_count: None,
+            rejected_lines: HashMap::new(),
+            store_receivers,
+            archive,
+            upstreams: BTreeMap::new(),
         }
     }
 
@@ -86,6 +144,181 @@ impl Gateway {
         }
     }
 
+    fn flush_receivers(&mut self, ctx: &mut Context<Self>) {
+        if self.receiver_positions_buffer.is_empty() && self.receiver_status_buffer.is_empty() {
+            return;
+        }
+
+        let positions = self.receiver_positions_buffer.drain().collect();
+        let statuses = self.receiver_status_buffer.drain().collect();
+
+        let fut = self
+            .redis
+            .send(redis::UpdateOGNReceivers {
+                positions,
+                statuses,
+            })
+            .into_actor(self)
+            .map(|result, _act, _ctx| match result {
+                Ok(Ok(_)) => debug!("Flushed OGN receiver beacons to redis"),
+                Ok(Err(error)) => {
+                    error!("Could not flush OGN receiver beacons to redis: {}", error)
+                }
+                Err(error) => error!("Could not flush OGN receiver beacons to redis: {}", error),
+            });
+
+        ctx.spawn(fut);
+    }
+
+    fn flush_coverage(&mut self, ctx: &mut Context<Self>) {
+        if self.coverage_buffer.is_empty() {
+            return;
+        }
+
+        let cells = self.coverage_buffer.take();
+
+        let fut = self
+            .redis
+            .send(redis::AddCoverage { cells })
+            .into_actor(self)
+            .map(|result, _act, _ctx| match result {
+                Ok(Ok(_)) => debug!("Flushed coverage statistics to redis"),
+                Ok(Err(error)) => error!("Could not flush coverage statistics to redis: {}", error),
+                Err(error) => error!("Could not flush coverage statistics to redis: {}", error),
+            });
+
+        ctx.spawn(fut);
+    }
+
+    fn flush_flights(&mut self, ctx: &mut Context<Self>) {
+        if self.flights_buffer.is_empty() {
+            return;
+        }
+
+        let flights = self
+            .flights_buffer
+            .drain()
+            .map(|(_, flight)| flight)
+            .collect();
+
+        let fut = self
+            .redis
+            .send(redis::AddOGNFlights { flights })
+            .into_actor(self)
+            .map(|result, _act, _ctx| match result {
+                Ok(Ok(_)) => debug!("Flushed OGN flights to redis"),
+                Ok(Err(error)) => error!("Could not flush OGN flights to redis: {}", error),
+                Err(error) => error!("Could not flush OGN flights to redis: {}", error),
+            });
+
+        ctx.spawn(fut);
+    }
+
+    fn flush_logbook(&mut self, ctx: &mut Context<Self>) {
+        if self.logbook_buffer.is_empty() {
+            return;
+        }
+
+        let entries = self
+            .logbook_buffer
+            .drain()
+            .map(|((icao, date, _), entry)| (icao, date, entry))
+            .collect();
+
+        let fut = self
+            .redis
+            .send(redis::AddLogbookEntries { entries })
+            .into_actor(self)
+            .map(|result, _act, _ctx| match result {
+                Ok(Ok(_)) => debug!("Flushed logbook entries to redis"),
+                Ok(Err(error)) => error!("Could not flush logbook entries to redis: {}", error),
+                Err(error) => error!("Could not flush logbook entries to redis: {}", error),
+            });
+
+        ctx.spawn(fut);
+    }
+
+    fn add_flight(&mut self, flight: redis::OGNFlight) {
+        self.add_logbook_entry(&flight);
+        self.flights_buffer.insert(flight.flight_id(), flight);
+    }
+
+    /// Attributes the takeoff and landing of the flight to the nearest
+    /// airfields and adds the flight to their logbooks.
+    fn add_logbook_entry(&mut self, flight: &redis::OGNFlight) {
+        let find_airfield = |location: &redis::OGNFlightLocation| {
+            airfields::nearest(
+                &self.airfields,
+                location.longitude.into(),
+                location.latitude.into(),
+            )
+            .map(|airfield| (airfield.icao.clone(), location.time.naive_utc().date()))
+        };
+
+        let takeoff_airfield = flight.takeoff.as_ref().and_then(find_airfield);
+        let landing_airfield = flight.landing.as_ref().and_then(find_airfield);
+        if takeoff_airfield.is_none() && landing_airfield.is_none() {
+            return;
+        }
+
+        let flight_id = flight.flight_id();
+        let entry = redis::LogbookEntry {
+            flight_id: flight_id.clone(),
+            id: flight.id.clone(),
+            takeoff: flight.takeoff.map(|it| it.time),
+            takeoff_airfield: takeoff_airfield.as_ref().map(|(icao, _)| icao.clone()),
+            landing: flight.landing.map(|it| it.time),
+            landing_airfield: landing_airfield.as_ref().map(|(icao, _)| icao.clone()),
+        };
+
+        for (icao, date) in takeoff_airfield.into_iter().chain(landing_airfield) {
+            self.logbook_buffer
+                .insert((icao, date, flight_id.clone()), entry.clone());
+        }
+    }
+
+    fn update_airfields(&self, ctx: &mut Context<Self>) {
+        let fut =
+            self.redis
+                .send(redis::ReadAirfields)
+                .into_actor(self)
+                .map(|result, act, _ctx| match result {
+                    Err(error) => warn!("Could not read airfields from redis: {}", error),
+                    Ok(Err(error)) => warn!("Could not read airfields from redis: {}", error),
+                    Ok(Ok(airfields)) => {
+                        debug!(
+                            "Updated airfields from redis: {} airfields",
+                            airfields.len()
+                        );
+                        act.airfields = airfields;
+                    }
+                });
+
+        ctx.spawn(fut);
+    }
+
+    fn update_receiver_locations(&self, ctx: &mut Context<Self>) {
+        let fut = self
+            .redis
+            .send(redis::ReadOGNReceivers)
+            .into_actor(self)
+            .map(|result, act, _ctx| match result {
+                Err(error) => warn!("Could not read OGN receivers from redis: {}", error),
+                Ok(Err(error)) => warn!("Could not read OGN receivers from redis: {}", error),
+                Ok(Ok(receivers)) => {
+                    for receiver in receivers {
+                        if let Some(position) = receiver.position {
+                            act.receiver_locations
+                                .entry(receiver.name)
+                                .or_insert((position.longitude.into(), position.latitude.into()));
+                        }
+                    }
+                }
+            });
+
+        ctx.spawn(fut);
+    }
+
     fn drop_outdated_records(&self, ctx: &mut Context<Self>) {
         let fut = self
             .redis
@@ -106,6 +339,48 @@ impl Gateway {
             });
 
         ctx.spawn(fut);
+
+        let fut = self
+            .redis
+            .send(redis::DropOldOGNReceivers)
+            .into_actor(self)
+            .map(|result, _act, _ctx| match result {
+                Err(error) => warn!(
+                    "Could not drop outdated OGN receivers from redis: {}",
+                    error
+                ),
+                Ok(Err(error)) => {
+                    warn!(
+                        "Could not drop outdated OGN receivers from redis: {}",
+                        error
+                    )
+                }
+                Ok(Ok(_)) => {}
+            });
+
+        ctx.spawn(fut);
+    }
+
+    fn update_device_categories(&self, ctx: &mut Context<Self>) {
+        let fut = self
+            .redis
+            .send(redis::ReadOGNDeviceCategories)
+            .into_actor(self)
+            .map(|result, act, _ctx| match result {
+                Err(error) => warn!("Could not read OGN device categories from redis: {}", error),
+                Ok(Err(error)) => {
+                    warn!("Could not read OGN device categories from redis: {}", error)
+                }
+                Ok(Ok(categories)) => {
+                    act.device_categories = categories;
+                    debug!(
+                        "Updated OGN device categories from redis: {} records",
+                        act.device_categories.len()
+                    );
+                }
+            });
+
+        ctx.spawn(fut);
     }
 
     fn update_ignore_list(&self, ctx: &mut Context<Self>) {
@@ -143,6 +418,31 @@ impl Actor for Gateway {
 
         ctx.run_interval(Duration::from_secs(5), |act, ctx| {
             act.flush_records(ctx);
+            act.flush_receivers(ctx);
+            act.flush_flights(ctx);
+            act.flush_logbook(ctx);
+        });
+
+        self.update_receiver_locations(ctx);
+
+        ctx.run_interval(Duration::from_secs(60), |act, ctx| {
+            act.flush_coverage(ctx);
+        });
+
+        if self.archive.is_some() {
+            ctx.run_interval(archive::FLUSH_INTERVAL, |act, _ctx| {
+                if let Some(archive) = &act.archive {
+                    archive.do_send(FlushArchive);
+                }
+            });
+        }
+
+        ctx.run_interval(Duration::from_secs(60), |act, _ctx| {
+            act.duplicates.rotate();
+
+            for flight in act.flight_tracker.expire(Utc::now()) {
+                act.add_flight(flight);
+            }
         });
 
         ctx.run_later(Duration::from_secs(30), |act, ctx| {
@@ -160,6 +460,22 @@ impl Actor for Gateway {
                 act.update_ignore_list(ctx);
             });
         });
+
+        ctx.run_later(Duration::from_secs(10), |act, ctx| {
+            act.update_device_categories(ctx);
+
+            ctx.run_interval(Duration::from_secs(30 * 60), |act, ctx| {
+                act.update_device_categories(ctx);
+            });
+        });
+
+        ctx.run_later(Duration::from_secs(10), |act, ctx| {
+            act.update_airfields(ctx);
+
+            ctx.run_interval(Duration::from_secs(10 * 60), |act, ctx| {
+                act.update_airfields(ctx);
+            });
+        });
     }
 }
 
@@ -172,6 +488,10 @@ impl Message for RequestStatus {
 pub struct StatusResponse {
     pub users: usize,
     pub record_count: Option<u64>,
+    /// number of APRS lines that could not be decoded, by reason
+    pub rejected_lines: HashMap<ParseError, u64>,
+    /// empty if the gateway is not connected to an APRS server (e.g. replay mode)
+    pub upstreams: Vec<UpstreamStatus>,
 }
 
 impl Handler<RequestStatus> for Gateway {
@@ -181,10 +501,20 @@ impl Handler<RequestStatus> for Gateway {
         MessageResult(StatusResponse {
             users: self.ws_clients.len(),
             record_count: self.record_count,
+            rejected_lines: self.rejected_lines.clone(),
+            upstreams: self.upstreams.values().cloned().collect(),
         })
     }
 }
 
+impl Handler<UpstreamStatus> for Gateway {
+    type Result = ();
+
+    fn handle(&mut self, status: UpstreamStatus, _ctx: &mut Context<Self>) {
+        self.upstreams.insert(status.feed, status);
+    }
+}
+
 /// New websocket client has connected.
 #[derive(Message)]
 #[rtype(result = "()")]
@@ -213,12 +543,12 @@ impl Handler<Disconnect> for Gateway {
     type Result = ();
 
     fn handle(&mut self, msg: Disconnect, _: &mut Context<Self>) {
-        self.bbox_subscriptions.remove(&msg.addr);
+        self.area_subscriptions.remove_all(&msg.addr);
+        self.network_filters.remove(&msg.addr);
 
-        self.id_subscriptions.values_mut().for_each(|subscribers| {
-            if let Some(pos) = subscribers.iter().position(|x| *x == msg.addr) {
-                subscribers.remove(pos);
-            }
+        self.id_subscriptions.retain(|_, subscribers| {
+            subscribers.remove(&msg.addr);
+            !subscribers.is_empty()
         });
 
         self.ws_clients.remove(&msg.addr);
@@ -240,8 +570,8 @@ impl Handler<SubscribeToId> for Gateway {
     fn handle(&mut self, msg: SubscribeToId, _ctx: &mut Context<Self>) {
         self.id_subscriptions
             .entry(msg.id)
-            .or_insert_with(Vec::new)
-            .push(msg.addr);
+            .or_default()
+            .insert(msg.addr);
     }
 }
 
@@ -257,25 +587,134 @@ impl Handler<UnsubscribeFromId> for Gateway {
 
     fn handle(&mut self, msg: UnsubscribeFromId, _ctx: &mut Context<Self>) {
         if let Some(subscribers) = self.id_subscriptions.get_mut(&msg.id) {
-            if let Some(pos) = subscribers.iter_mut().position(|x| *x == msg.addr) {
-                subscribers.remove(pos);
+            subscribers.remove(&msg.addr);
+            if subscribers.is_empty() {
+                self.id_subscriptions.remove(&msg.id);
             }
         }
     }
 }
 
+/// Adds an area subscription, or replaces the area with the same name.
+/// Only the positions matching the filter are sent for the area.
+pub struct AddArea {
+    pub addr: Addr<WSClient>,
+    pub name: String,
+    pub area: Area,
+    pub filter: PositionFilter,
+}
+
+impl Message for AddArea {
+    type Result = Result<()>;
+}
+
+impl Handler<AddArea> for Gateway {
+    type Result = Result<()>;
+
+    fn handle(&mut self, msg: AddArea, _ctx: &mut Context<Self>) -> Self::Result {
+        if let Some(areas) = self.area_subscriptions.areas(&msg.addr) {
+            if areas.len() >= MAX_AREAS && !areas.contains_key(&msg.name) {
+                return Err(anyhow!("Too many areas (max. {})", MAX_AREAS));
+            }
+        }
+
+        self.area_subscriptions
+            .insert(msg.addr, msg.name, msg.area, msg.filter);
+
+        Ok(())
+    }
+}
+
+pub struct RemoveArea {
+    pub addr: Addr<WSClient>,
+    pub name: String,
+}
+
+impl Message for RemoveArea {
+    type Result = Result<()>;
+}
+
+impl Handler<RemoveArea> for Gateway {
+    type Result = Result<()>;
+
+    fn handle(&mut self, msg: RemoveArea, _ctx: &mut Context<Self>) -> Self::Result {
+        if !self.area_subscriptions.remove(&msg.addr, &msg.name) {
+            return Err(anyhow!("Unknown area: {}", msg.name));
+        }
+
+        Ok(())
+    }
+}
+
+/// Restricts the area subscriptions of a client to the given source
+/// networks. An empty set removes the restriction.
 #[derive(Message)]
 #[rtype(result = "()")]
-pub struct SetBoundingBox {
+pub struct SetNetworkFilter {
     pub addr: Addr<WSClient>,
-    pub bbox: BoundingBox,
+    pub networks: HashSet<SourceNetwork>,
 }
 
-impl Handler<SetBoundingBox> for Gateway {
+impl Handler<SetNetworkFilter> for Gateway {
     type Result = ();
 
-    fn handle(&mut self, msg: SetBoundingBox, _ctx: &mut Context<Self>) {
-        self.bbox_subscriptions.insert(msg.addr, msg.bbox);
+    fn handle(&mut self, msg: SetNetworkFilter, _ctx: &mut Context<Self>) {
+        if msg.networks.is_empty() {
+            self.network_filters.remove(&msg.addr);
+        } else {
+            self.network_filters.insert(msg.addr, msg.networks);
+        }
+    }
+}
+
+/// Lists the current subscriptions of a websocket client.
+pub struct ListSubscriptions {
+    pub addr: Addr<WSClient>,
+}
+
+impl Message for ListSubscriptions {
+    type Result = Subscriptions;
+}
+
+pub struct Subscriptions {
+    /// subscribed APRS sender IDs, in alphabetical order
+    pub ids: Vec<String>,
+    /// area subscriptions by name
+    pub areas: BTreeMap<String, AreaSubscription>,
+    /// empty if the area subscriptions are not restricted
+    pub networks: Vec<SourceNetwork>,
+}
+
+impl Handler<ListSubscriptions> for Gateway {
+    type Result = MessageResult<ListSubscriptions>;
+
+    fn handle(&mut self, msg: ListSubscriptions, _ctx: &mut Context<Self>) -> Self::Result {
+        let mut ids: Vec<String> = self
+            .id_subscriptions
+            .iter()
+            .filter(|(_, subscribers)| subscribers.contains(&msg.addr))
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        ids.sort_unstable();
+
+        let mut networks: Vec<SourceNetwork> = self
+            .network_filters
+            .get(&msg.addr)
+            .map(|it| it.iter().cloned().collect())
+            .unwrap_or_default();
+
+        networks.sort_unstable_by_key(|it| it.as_str());
+
+        MessageResult(Subscriptions {
+            ids,
+            areas: self
+                .area_subscriptions
+                .areas(&msg.addr)
+                .cloned()
+                .unwrap_or_default(),
+            networks,
+        })
     }
 }
 
@@ -283,65 +722,312 @@ impl Handler<OGNMessage> for Gateway {
     type Result = ();
 
     fn handle(&mut self, message: OGNMessage, _: &mut Context<Self>) {
-        if let Some(position) = ogn::aprs::parse(&message.raw) {
-            if self.ignore_list.contains(position.id) {
-                return;
-            }
+        let parsed = ogn::aprs::parse(&message.raw);
 
-            let now = Utc::now();
-            let time = ogn::time_to_datetime(now, position.time);
-            let age = time - now;
+        if let Some(archive) = &self.archive {
+            if self.is_archivable(&parsed) {
+                archive.do_send(ArchiveLine {
+                    time: Utc::now(),
+                    raw: message.raw.clone(),
+                });
+            }
+        }
 
-            // throw away records older than 15min or more than 5min into the future
-            if age.num_minutes() > 15 || age.num_minutes() < -5 {
-                return;
+        match parsed {
+            Ok(APRSMessage::Position(position)) => self.handle_position(position),
+            Ok(APRSMessage::ReceiverPosition(position)) => self.handle_receiver_position(position),
+            Ok(APRSMessage::ReceiverStatus(status)) => self.handle_receiver_status(status),
+            Ok(APRSMessage::ServerComment(comment)) => debug!("APRS server comment: {}", comment),
+            Err(error) => {
+                debug!("Could not parse APRS line ({}): {}", error, message.raw);
+                *self.rejected_lines.entry(error).or_insert(0) += 1;
             }
+        }
+    }
+}
 
-            // find subscribers
-            let id_subscribers = self.id_subscriptions.get(position.id);
-
-            let bbox_subscribers: Vec<&Addr<WSClient>> = self
-                .bbox_subscriptions
-                .iter()
-                .filter(|(_, bbox)| bbox.contains(position.longitude, position.latitude))
-                .map(|(addr, _)| addr)
-                .filter(|addr| id_subscribers.map_or(true, |list| !list.contains(addr)))
-                .collect();
-
-            // send record to subscribers
-            if !bbox_subscribers.is_empty() || id_subscribers.map_or(false, |list| !list.is_empty())
-            {
-                let ws_message = format!(
-                    "{}|{}|{:.6}|{:.6}|{}|{}",
-                    position.id,
-                    time.timestamp(),
-                    position.longitude,
-                    position.latitude,
-                    position.course,
-                    position.altitude as i32,
-                );
-
-                for subscriber in bbox_subscribers {
-                    subscriber.do_send(SendTextSlow(ws_message.clone()));
-                }
+impl Gateway {
+    /// Returns `true` if the sender of the position is on the ignore list
+    /// or has set the on-air privacy flags.
+    fn is_private(&self, position: &APRSPosition) -> bool {
+        self.ignore_list.contains(position.id)
+            || position
+                .aircraft_id
+                .is_some_and(|it| it.no_tracking || it.stealth)
+    }
 
-                if let Some(id_subscribers) = id_subscribers {
-                    for subscriber in id_subscribers {
-                        subscriber.do_send(SendTextFast(ws_message.clone()));
-                    }
+    /// Returns `true` if the line may be written to the APRS archive.
+    /// Positions of private senders must not be stored, and unparsable
+    /// lines are skipped because their sender can not be checked.
+    fn is_archivable(&self, parsed: &Result<APRSMessage, ParseError>) -> bool {
+        match parsed {
+            Ok(APRSMessage::Position(position)) => !self.is_private(position),
+            Ok(_) => true,
+            Err(_) => false,
+        }
+    }
+
+    fn handle_position(&mut self, position: APRSPosition) {
+        if self.is_private(&position) {
+            return;
+        }
+
+        let now = Utc::now();
+        let time = ogn::time_to_datetime(now, position.time);
+        let age = time - now;
+
+        // throw away records older than 15min or more than 5min into the future
+        if age.num_minutes() > 15 || age.num_minutes() < -5 {
+            return;
+        }
+
+        // update the coverage statistics of the receiver, which are collected for
+        // every receiver that reported the position, but only once per receiver
+        // even if the report arrives through multiple upstream feeds
+        let receiver = position.receiver.filter(|receiver| {
+            !self.duplicates.is_duplicate_reception(
+                position.id,
+                receiver,
+                position.time,
+                position.longitude,
+                position.latitude,
+            )
+        });
+
+        if let Some(receiver) = receiver {
+            let range = self
+                .receiver_locations
+                .get(receiver)
+                .map(|(longitude, latitude)| {
+                    geo::distance(*longitude, *latitude, position.longitude, position.latitude)
+                        as f32
+                });
+
+            self.coverage_buffer.add(
+                time.to_bucket_time(),
+                receiver,
+                Cell::from_position(position.longitude, position.latitude),
+                CoverageStats::new(
+                    position.altitude as i16,
+                    range,
+                    position.signal_quality.map(|it| it as f32),
+                ),
+            );
+        }
+
+        // skip reports that were already received through another upstream
+        // feed or receiver
+        if self.duplicates.is_duplicate(
+            position.id,
+            position.time,
+            position.longitude,
+            position.latitude,
+        ) {
+            return;
+        }
+
+        // find subscribers
+        let id_subscribers = self.id_subscriptions.get(position.id);
+
+        let category = AircraftCategory::detect(
+            position.aircraft_id.map(|it| it.aircraft_type as u8),
+            self.device_categories.get(position.id).cloned(),
+        );
+
+        let area_subscribers: Vec<&Addr<WSClient>> = self
+            .area_subscriptions
+            .query(
+                position.longitude,
+                position.latitude,
+                position.altitude,
+                position.speed,
+                category,
+            )
+            .filter(|addr| match self.network_filters.get(*addr) {
+                Some(networks) => networks.contains(&position.network),
+                None => true,
+            })
+            .filter(|addr| id_subscribers.is_none_or(|list| !list.contains(*addr)))
+            .collect();
+
+        // send record to subscribers
+        if !area_subscribers.is_empty() || id_subscribers.is_some_and(|list| !list.is_empty()) {
+            let live_position = Arc::new(LivePosition::new(&position, time));
+
+            for subscriber in area_subscribers {
+                subscriber.do_send(SendPositionSlow(live_position.clone()));
+            }
+
+            if let Some(id_subscribers) = id_subscribers {
+                for subscriber in id_subscribers {
+                    subscriber.do_send(SendPositionFast(live_position.clone()));
                 }
             }
+        }
 
-            // save record in the database
-            self.redis_buffer.push((
-                position.id.to_owned(),
-                redis::OGNPosition {
-                    time,
-                    longitude: position.longitude as f32,
-                    latitude: position.latitude as f32,
-                    altitude: position.altitude as i16,
-                },
-            ));
+        let reception = if self.store_receivers {
+            position
+                .receiver
+                .zip(position.q_construct)
+                .map(|(receiver, q_construct)| redis::OGNReception {
+                    receiver: receiver.to_owned(),
+                    q_construct: q_construct.to_owned(),
+                })
+        } else {
+            None
+        };
+
+        let record = redis::OGNPosition {
+            time,
+            longitude: position.longitude as f32,
+            latitude: position.latitude as f32,
+            altitude: position.altitude as i16,
+            speed: position.speed as f32,
+            climb_rate: position.climb_rate.map(|it| it as f32),
+            turn_rate: position.turn_rate.map(|it| it as f32),
+            signal_quality: position.signal_quality.map(|it| it as f32),
+            error_count: position.error_count,
+            frequency_offset: position.frequency_offset.map(|it| it as f32),
+            gps_quality: position.gps_quality,
+            reception,
+        };
+
+        // detect takeoffs and landings
+        if let Some(flight) = self.flight_tracker.update(position.id, category, &record) {
+            self.add_flight(flight);
         }
+
+        // save record in the database
+        self.redis_buffer.push((position.id.to_owned(), record));
+    }
+
+    fn handle_receiver_position(&mut self, position: ReceiverPosition) {
+        let time = ogn::time_to_datetime(Utc::now(), position.time);
+
+        self.receiver_locations.insert(
+            position.name.to_owned(),
+            (position.longitude, position.latitude),
+        );
+
+        self.receiver_positions_buffer.insert(
+            position.name.to_owned(),
+            redis::OGNReceiverPosition {
+                time,
+                longitude: position.longitude as f32,
+                latitude: position.latitude as f32,
+                altitude: position.altitude as i16,
+            },
+        );
+    }
+
+    fn handle_receiver_status(&mut self, status: ReceiverStatus) {
+        let time = ogn::time_to_datetime(Utc::now(), status.time);
+
+        self.receiver_status_buffer.insert(
+            status.name.to_owned(),
+            redis::OGNReceiverStatus {
+                time,
+                version: status.version.map(|it| it.to_owned()),
+                platform: status.platform.map(|it| it.to_owned()),
+                cpu_load: status.cpu_load.map(|it| it as f32),
+                ram_free: status.ram_free.map(|it| it as f32),
+                ram_total: status.ram_total.map(|it| it as f32),
+                ntp_offset: status.ntp_offset.map(|it| it as f32),
+                ntp_correction: status.ntp_correction.map(|it| it as f32),
+                cpu_temperature: status.cpu_temperature.map(|it| it as f32),
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix::dev::channel::channel;
+
+    fn gateway() -> Gateway {
+        let (redis, _) = channel(16);
+        Gateway::new(Addr::new(redis), true, None)
+    }
+
+    fn position_line(receiver: &str, time: &str) -> String {
+        format!(
+            "FLRDD9612>APRS,qAS,{}:/{}h4911.18N/00815.93E'126/059/A=003716 !W75! id06DD9612 -355fpm -1.2rot 3.0dB 2e -1.3kHz gps3x3",
+            receiver, time,
+        )
+    }
+
+    fn handle_line(gateway: &mut Gateway, line: &str) {
+        match ogn::aprs::parse(line) {
+            Ok(APRSMessage::Position(position)) => gateway.handle_position(position),
+            _ => panic!("Could not parse {}", line),
+        }
+    }
+
+    #[test]
+    fn test_duplicate_reports_of_different_receivers() {
+        let mut gateway = gateway();
+        let time = Utc::now().format("%H%M%S").to_string();
+
+        for receiver in &["VillaBlau", "LFNW", "VillaBlau"] {
+            handle_line(&mut gateway, &position_line(receiver, &time));
+        }
+
+        // coverage statistics are collected for both receivers
+        let receivers: HashSet<String> = gateway
+            .coverage_buffer
+            .take()
+            .into_iter()
+            .map(|((_, receiver, _), _)| receiver)
+            .collect();
+        assert_eq!(receivers.len(), 2);
+        assert!(receivers.contains("VillaBlau"));
+        assert!(receivers.contains("LFNW"));
+
+        // but the position itself is only stored once
+        assert_eq!(gateway.redis_buffer.len(), 1);
+        let reception = gateway.redis_buffer[0].1.reception.as_ref().unwrap();
+        assert_eq!(reception.receiver, "VillaBlau");
+    }
+
+    #[test]
+    fn test_duplicate_reports_of_the_same_receiver() {
+        let mut gateway = gateway();
+        let time = Utc::now().format("%H%M%S").to_string();
+
+        // the same line received through two upstream feeds
+        let line = position_line("VillaBlau", &time);
+        handle_line(&mut gateway, &line);
+        handle_line(&mut gateway, &line);
+
+        let coverage = gateway.coverage_buffer.take();
+        assert_eq!(coverage.len(), 1);
+        assert_eq!(coverage.values().next().unwrap().count, 1);
+        assert_eq!(gateway.redis_buffer.len(), 1);
+    }
+
+    #[test]
+    fn test_is_archivable() {
+        let mut gateway = gateway();
+        let line = position_line("VillaBlau", "141956");
+        assert!(gateway.is_archivable(&ogn::aprs::parse(&line)));
+
+        let no_tracking = line.replace("id06DD9612", "id46DD9612");
+        match ogn::aprs::parse(&no_tracking) {
+            Ok(APRSMessage::Position(position)) => {
+                assert!(position.aircraft_id.unwrap().no_tracking)
+            }
+            _ => panic!("Could not parse {}", no_tracking),
+        }
+        assert!(!gateway.is_archivable(&ogn::aprs::parse(&no_tracking)));
+
+        let stealth = line.replace("id06DD9612", "id86DD9612");
+        assert!(!gateway.is_archivable(&ogn::aprs::parse(&stealth)));
+
+        gateway.ignore_list.insert("FLRDD9612".to_owned());
+        assert!(!gateway.is_archivable(&ogn::aprs::parse(&line)));
+
+        assert!(gateway.is_archivable(&ogn::aprs::parse("# aprsc 2.1.4-g408ed49")));
+        assert!(!gateway.is_archivable(&ogn::aprs::parse("FLRDD9612>APRS:garbage")));
     }
 }
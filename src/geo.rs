@@ -1,7 +1,22 @@
 use lazy_static::lazy_static;
 use regex::Regex;
 
-#[derive(Debug)]
+/// Mean earth radius in meters
+const EARTH_RADIUS: f64 = 6_371_000.;
+
+/// Calculates the great-circle distance between two points in meters
+/// using the haversine formula.
+pub fn distance(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+    let lat1 = lat1.to_radians();
+    let lat2 = lat2.to_radians();
+    let d_lat = lat2 - lat1;
+    let d_lon = (lon2 - lon1).to_radians();
+
+    let a = (d_lat / 2.).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.).sin().powi(2);
+    2. * EARTH_RADIUS * a.sqrt().asin()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct BoundingBox {
     bottom: f64,
     left: f64,
@@ -10,6 +25,27 @@ pub struct BoundingBox {
 }
 
 impl BoundingBox {
+    /// Creates a bounding box from its sides in degrees, or returns `None`
+    /// if the sides are out of range or the top is below the bottom.
+    pub fn new(left: f64, bottom: f64, right: f64, top: f64) -> Option<BoundingBox> {
+        let longitudes = -180. ..=180.;
+        if !longitudes.contains(&left) || !longitudes.contains(&right) {
+            return None;
+        }
+
+        let latitudes = -90. ..=90.;
+        if !latitudes.contains(&top) || !latitudes.contains(&bottom) || top < bottom {
+            return None;
+        }
+
+        Some(BoundingBox {
+            left,
+            bottom,
+            right,
+            top,
+        })
+    }
+
     pub fn try_parse(text: &str) -> Option<BoundingBox> {
         lazy_static! {
             static ref RE: Regex = Regex::new(
@@ -38,21 +74,24 @@ impl BoundingBox {
             let right = caps.name("right").unwrap().as_str().parse::<f64>().unwrap();
             let top = caps.name("top").unwrap().as_str().parse::<f64>().unwrap();
 
-            if left < -180. || left > 180. || right < -180. || right > 180. {
-                return None;
-            }
+            BoundingBox::new(left, bottom, right, top)
+        })
+    }
 
-            if top < -90. || top > 90. || bottom < -90. || bottom > 90. || top < bottom {
-                return None;
-            }
+    pub fn bottom(&self) -> f64 {
+        self.bottom
+    }
 
-            Some(BoundingBox {
-                left,
-                bottom,
-                right,
-                top,
-            })
-        })
+    pub fn left(&self) -> f64 {
+        self.left
+    }
+
+    pub fn top(&self) -> f64 {
+        self.top
+    }
+
+    pub fn right(&self) -> f64 {
+        self.right
     }
 
     pub fn contains(&self, longitude: f64, latitude: f64) -> bool {
@@ -66,9 +105,197 @@ impl BoundingBox {
     }
 }
 
+/// Maximum number of points of a `Polygon`
+pub const MAX_POLYGON_POINTS: usize = 1000;
+
+fn is_valid_position(longitude: f64, latitude: f64) -> bool {
+    (-180. ..=180.).contains(&longitude) && (-90. ..=90.).contains(&latitude)
+}
+
+/// Area within a given distance of a center point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Circle {
+    longitude: f64,
+    latitude: f64,
+    /// radius in meters
+    radius: f64,
+}
+
+impl Circle {
+    pub fn new(longitude: f64, latitude: f64, radius: f64) -> Option<Circle> {
+        if !is_valid_position(longitude, latitude) || !radius.is_finite() || radius <= 0. {
+            return None;
+        }
+
+        Some(Circle {
+            longitude,
+            latitude,
+            radius,
+        })
+    }
+
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    pub fn contains(&self, longitude: f64, latitude: f64) -> bool {
+        distance(self.longitude, self.latitude, longitude, latitude) <= self.radius
+    }
+
+    /// Returns the smallest bounding box that contains the circle.
+    pub fn bbox(&self) -> BoundingBox {
+        let angle = self.radius / EARTH_RADIUS;
+        let d_lat = angle.to_degrees();
+        let bottom = (self.latitude - d_lat).max(-90.);
+        let top = (self.latitude + d_lat).min(90.);
+
+        // circles around the poles contain all longitudes
+        let lat = self.latitude.to_radians();
+        if bottom <= -90. || top >= 90. || angle.sin() >= lat.cos() {
+            return BoundingBox {
+                left: -180.,
+                bottom,
+                right: 180.,
+                top,
+            };
+        }
+
+        let d_lon = (angle.sin() / lat.cos()).asin().to_degrees();
+
+        let mut left = self.longitude - d_lon;
+        if left < -180. {
+            left += 360.;
+        }
+
+        let mut right = self.longitude + d_lon;
+        if right > 180. {
+            right -= 360.;
+        }
+
+        BoundingBox {
+            left,
+            bottom,
+            right,
+            top,
+        }
+    }
+}
+
+/// Polygon with straight edges between its `(longitude, latitude)` points.
+/// The last point is implicitly connected to the first one. Edges must not
+/// cross the antimeridian.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    points: Vec<(f64, f64)>,
+    /// bounding box of the points, to quickly rule out positions far away
+    bbox: BoundingBox,
+}
+
+impl Polygon {
+    pub fn new(points: Vec<(f64, f64)>) -> Option<Polygon> {
+        if points.len() < 3 || points.len() > MAX_POLYGON_POINTS {
+            return None;
+        }
+
+        if !points
+            .iter()
+            .all(|(lon, lat)| is_valid_position(*lon, *lat))
+        {
+            return None;
+        }
+
+        // an edge between e.g. 179° and -179° would span the rest of the
+        // globe instead of crossing the antimeridian
+        let previous_points = points.iter().cycle().skip(points.len() - 1);
+        if points
+            .iter()
+            .zip(previous_points)
+            .any(|((lon1, _), (lon2, _))| (lon2 - lon1).abs() > 180.)
+        {
+            return None;
+        }
+
+        let (left, bottom, right, top) = points.iter().fold(
+            (180., 90., -180., -90.),
+            |(left, bottom, right, top): (f64, f64, f64, f64), (lon, lat)| {
+                (
+                    left.min(*lon),
+                    bottom.min(*lat),
+                    right.max(*lon),
+                    top.max(*lat),
+                )
+            },
+        );
+
+        let bbox = BoundingBox::new(left, bottom, right, top)?;
+
+        Some(Polygon { points, bbox })
+    }
+
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    /// Checks whether the position is inside of the polygon using the
+    /// even-odd rule.
+    pub fn contains(&self, longitude: f64, latitude: f64) -> bool {
+        if !self.bbox.contains(longitude, latitude) {
+            return false;
+        }
+
+        let previous_points = self.points.iter().cycle().skip(self.points.len() - 1);
+
+        let mut inside = false;
+        for (&(lon1, lat1), &(lon2, lat2)) in self.points.iter().zip(previous_points) {
+            if (lat1 > latitude) != (lat2 > latitude)
+                && longitude < (lon2 - lon1) * (latitude - lat1) / (lat2 - lat1) + lon1
+            {
+                inside = !inside;
+            }
+        }
+
+        inside
+    }
+}
+
+/// Geographic area of a live position subscription
+#[derive(Debug, Clone, PartialEq)]
+pub enum Area {
+    BoundingBox(BoundingBox),
+    Circle(Circle),
+    Polygon(Polygon),
+}
+
+impl Area {
+    /// Returns a bounding box that contains the whole area.
+    pub fn bbox(&self) -> BoundingBox {
+        match self {
+            Area::BoundingBox(bbox) => *bbox,
+            Area::Circle(circle) => circle.bbox(),
+            Area::Polygon(polygon) => polygon.bbox,
+        }
+    }
+
+    pub fn contains(&self, longitude: f64, latitude: f64) -> bool {
+        match self {
+            Area::BoundingBox(bbox) => bbox.contains(longitude, latitude),
+            Area::Circle(circle) => circle.contains(longitude, latitude),
+            Area::Polygon(polygon) => polygon.contains(longitude, latitude),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::BoundingBox;
+    use super::{distance, BoundingBox, Circle, Polygon};
     use approx::assert_relative_eq;
 
     #[test]
@@ -131,4 +358,84 @@ mod tests {
         assert!(bbox.contains(-160., 11.));
         assert!(!bbox.contains(-159., 11.));
     }
+
+    #[test]
+    fn test_distance() {
+        assert_relative_eq!(distance(7., 50., 7., 50.), 0.);
+        assert_relative_eq!(distance(0., 0., 0., 1.), 111_194.9, epsilon = 0.1);
+        assert_relative_eq!(distance(0., 0., 1., 0.), 111_194.9, epsilon = 0.1);
+        assert_relative_eq!(
+            distance(6.1867, 50.8233, 8.2627, 49.9969),
+            173_443.,
+            max_relative = 0.001
+        );
+    }
+
+    #[test]
+    fn test_circle() {
+        let circle = Circle::new(6.1867, 50.8233, 10_000.).unwrap();
+        assert!(circle.contains(6.1867, 50.8233));
+        assert!(circle.contains(6.1867, 50.9));
+        assert!(!circle.contains(6.1867, 50.95));
+
+        assert!(Circle::new(6.1867, 50.8233, 0.).is_none());
+        assert!(Circle::new(6.1867, 95., 1000.).is_none());
+    }
+
+    #[test]
+    fn test_circle_bbox() {
+        let bbox = Circle::new(6.1867, 50.8233, 10_000.).unwrap().bbox();
+        assert_relative_eq!(bbox.bottom, 50.7334, epsilon = 0.0001);
+        assert_relative_eq!(bbox.top, 50.9132, epsilon = 0.0001);
+        assert_relative_eq!(bbox.left, 6.0443, epsilon = 0.0001);
+        assert_relative_eq!(bbox.right, 6.3291, epsilon = 0.0001);
+
+        let bbox = Circle::new(179.99, 0., 10_000.).unwrap().bbox();
+        assert!(bbox.left > bbox.right);
+        assert!(bbox.contains(-179.95, 0.));
+
+        let bbox = Circle::new(0., 89.99, 10_000.).unwrap().bbox();
+        assert_relative_eq!(bbox.left, -180.);
+        assert_relative_eq!(bbox.right, 180.);
+        assert_relative_eq!(bbox.top, 90.);
+    }
+
+    #[test]
+    fn test_polygon() {
+        // concave "L" shape
+        let polygon = Polygon::new(vec![
+            (0., 0.),
+            (2., 0.),
+            (2., 1.),
+            (1., 1.),
+            (1., 2.),
+            (0., 2.),
+        ])
+        .unwrap();
+
+        assert!(polygon.contains(0.5, 0.5));
+        assert!(polygon.contains(1.5, 0.5));
+        assert!(polygon.contains(0.5, 1.5));
+        assert!(!polygon.contains(1.5, 1.5));
+        assert!(!polygon.contains(-0.5, 0.5));
+        assert!(!polygon.contains(0.5, 2.5));
+    }
+
+    #[test]
+    fn test_polygon_invalid() {
+        assert!(Polygon::new(vec![(0., 0.), (1., 1.)]).is_none());
+        assert!(Polygon::new(vec![(0., 0.), (1., 1.), (181., 0.)]).is_none());
+        assert!(Polygon::new(vec![(0., 0.); 1001]).is_none());
+    }
+
+    #[test]
+    fn test_polygon_antimeridian() {
+        assert!(Polygon::new(vec![(179., -1.), (-179., -1.), (-179., 1.), (179., 1.)]).is_none());
+        assert!(Polygon::new(vec![(170., 0.), (175., 5.), (-170., 0.)]).is_none());
+
+        // touching the antimeridian is fine
+        let polygon = Polygon::new(vec![(179., -1.), (180., -1.), (180., 1.), (179., 1.)]).unwrap();
+        assert!(polygon.contains(179.5, 0.));
+        assert!(!polygon.contains(0., 0.));
+    }
 }
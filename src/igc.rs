@@ -0,0 +1,156 @@
+use std::fmt::Write;
+
+use chrono::prelude::*;
+
+use crate::ogn_ddb::DeviceInfo;
+use crate::redis::OGNPosition;
+
+/// Renders the positions of an aircraft as an IGC file.
+///
+/// The OGN network does not transmit the pressure altitude, so the pressure
+/// altitude of the B-records is always zero, which is also noted in an
+/// L-record.
+pub fn render(id: &str, device: Option<&DeviceInfo>, positions: &[OGNPosition]) -> String {
+    let mut igc = String::new();
+
+    let date = positions
+        .first()
+        .map_or_else(|| Utc::now().date_naive(), |it| it.time.date_naive());
+
+    let model = device.and_then(|it| it.model.as_deref()).unwrap_or("");
+    let registration = device
+        .and_then(|it| it.registration.as_deref())
+        .unwrap_or("");
+    let callsign = device.and_then(|it| it.callsign.as_deref()).unwrap_or("");
+
+    write_line(&mut igc, format!("AXXX{}", id));
+    write_line(&mut igc, format!("HFDTEDATE:{},01", date.format("%d%m%y")));
+    write_line(&mut igc, "HFPLTPILOTINCHARGE:");
+    write_line(&mut igc, format!("HFGTYGLIDERTYPE:{}", model));
+    write_line(&mut igc, format!("HFGIDGLIDERID:{}", registration));
+    write_line(&mut igc, format!("HFCIDCOMPETITIONID:{}", callsign));
+    write_line(&mut igc, "HFDTMGPSDATUM:WGS84");
+    write_line(&mut igc, "HFFTYFRTYPE:OGN Web Gateway");
+    write_line(&mut igc, "HFPRSPRESSALTSENSOR:NIL");
+    write_line(&mut igc, format!("LOGNOGN ID {}", id));
+    write_line(&mut igc, "LOGNPRESSURE ALTITUDE NOT AVAILABLE");
+
+    for position in positions {
+        write_line(&mut igc, b_record(position));
+    }
+
+    igc
+}
+
+fn write_line<S: AsRef<str>>(igc: &mut String, line: S) {
+    igc.push_str(line.as_ref());
+    igc.push_str("\r\n");
+}
+
+/// Formats a position as `BHHMMSSDDMMmmmNDDDMMmmmEVPPPPPGGGGG`
+fn b_record(position: &OGNPosition) -> String {
+    let mut record = String::with_capacity(35);
+
+    write!(record, "B{}", position.time.format("%H%M%S")).unwrap();
+    write_coordinate(&mut record, position.latitude.into(), 2, 'N', 'S');
+    write_coordinate(&mut record, position.longitude.into(), 3, 'E', 'W');
+    write!(
+        record,
+        "A{}{}",
+        format_altitude(0),
+        format_altitude(position.altitude)
+    )
+    .unwrap();
+
+    record
+}
+
+fn write_coordinate(
+    record: &mut String,
+    value: f64,
+    degree_digits: usize,
+    positive: char,
+    negative: char,
+) {
+    let hemisphere = if value < 0. { negative } else { positive };
+
+    // minutes with three decimals
+    let thousandths = (value.abs() * 60_000.).round() as u32;
+    let degrees = thousandths / 60_000;
+    let thousandths = thousandths % 60_000;
+
+    write!(
+        record,
+        "{:0width$}{:05}{}",
+        degrees,
+        thousandths,
+        hemisphere,
+        width = degree_digits
+    )
+    .unwrap();
+}
+
+fn format_altitude(altitude: i16) -> String {
+    if altitude < 0 {
+        format!("-{:04}", -i32::from(altitude))
+    } else {
+        format!("{:05}", altitude)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(hour: u32, minute: u32, second: u32, altitude: i16) -> OGNPosition {
+        let time = Utc
+            .with_ymd_and_hms(2018, 4, 17, hour, minute, second)
+            .unwrap();
+        OGNPosition::fixture(time, altitude, 109.)
+    }
+
+    #[test]
+    fn test_b_record() {
+        assert_eq!(
+            b_record(&position(14, 19, 56, 1133)),
+            "B1419564911180N00815930EA0000001133"
+        );
+
+        let mut position = position(8, 5, 3, -12);
+        position.longitude = -70.5;
+        position.latitude = -33.999_999;
+        assert_eq!(b_record(&position), "B0805033400000S07030000WA00000-0012");
+    }
+
+    #[test]
+    fn test_render() {
+        let device = DeviceInfo {
+            model: Some("ASK 21".to_owned()),
+            registration: Some("D-1234".to_owned()),
+            callsign: Some("XY".to_owned()),
+            category: 1,
+        };
+
+        let positions = vec![position(14, 19, 56, 1133), position(14, 20, 0, 1135)];
+
+        let igc = render("FLRDD9612", Some(&device), &positions);
+        let lines: Vec<&str> = igc.split_terminator("\r\n").collect();
+
+        assert_eq!(lines[0], "AXXXFLRDD9612");
+        assert_eq!(lines[1], "HFDTEDATE:170418,01");
+        assert!(lines.contains(&"HFGTYGLIDERTYPE:ASK 21"));
+        assert!(lines.contains(&"HFGIDGLIDERID:D-1234"));
+        assert!(lines.contains(&"HFCIDCOMPETITIONID:XY"));
+        assert_eq!(
+            lines[lines.len() - 2],
+            "B1419564911180N00815930EA0000001133"
+        );
+        assert_eq!(
+            lines[lines.len() - 1],
+            "B1420004911180N00815930EA0000001135"
+        );
+
+        let igc = render("FLRDD9612", None, &positions);
+        assert!(igc.contains("HFGIDGLIDERID:\r\n"));
+    }
+}
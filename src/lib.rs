@@ -0,0 +1,10 @@
+//! Decoding of the OGN APRS stream and indexing of the live subscriptions.
+//!
+//! This is split out of the `ogn-web-gateway` binary so that the parser and
+//! the subscription index can be used by the benchmarks and fuzz targets.
+
+pub mod area_index;
+pub mod filter;
+pub mod geo;
+pub mod ogn;
+pub mod units;
@@ -1,10 +1,12 @@
 use std::env;
+use std::fs;
 use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
 
 use ::actix::prelude::*;
 use ::actix_cors::Cors;
 use ::actix_files::NamedFile;
-use ::actix_ogn::OGNActor;
 use ::actix_web::middleware::Logger;
 use ::actix_web::{web, App, HttpServer};
 use ::anyhow::{anyhow, Context, Result};
@@ -12,18 +14,31 @@ use ::clap::{self, value_t, Arg};
 use ::log::debug;
 use ::r2d2_redis::RedisConnectionManager;
 
+mod airfields;
 mod api;
+mod archive;
+mod coverage;
+mod dedup;
+mod export;
+mod flights;
 mod gateway;
-mod geo;
-mod ogn;
+mod igc;
 mod ogn_ddb;
+mod protocol;
 mod redis;
-mod units;
+mod replay;
+mod upstream;
 mod ws_client;
 
+use ogn_web_gateway::{area_index, filter, geo, ogn};
+
+use crate::airfields::AirfieldsUpdater;
+use crate::archive::ArchiveRecorder;
 use crate::gateway::Gateway;
 use crate::ogn_ddb::OGNDevicesUpdater;
 use crate::redis::RedisExecutor;
+use crate::replay::ReplayActor;
+use crate::upstream::{Server, UpstreamActor, UpstreamConfig};
 use actix_web::Responder;
 
 const REDIS_WORKERS: usize = 7;
@@ -67,10 +82,157 @@ async fn main() -> Result<()> {
                 .default_value("8080")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("aprs-host")
+                .long("aprs-host")
+                .env("APRS_HOST")
+                .value_name("HOST[:PORT]")
+                .default_value("aprs.glidernet.org")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .use_delimiter(true)
+                .help("Upstream APRS-IS server, can be repeated to connect to multiple servers"),
+        )
+        .arg(
+            Arg::with_name("aprs-backup")
+                .long("aprs-backup")
+                .env("APRS_BACKUP")
+                .value_name("HOST[:PORT]")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .use_delimiter(true)
+                .help("Backup APRS-IS server that is used if an upstream server fails or stalls"),
+        )
+        .arg(
+            Arg::with_name("aprs-timeout")
+                .long("aprs-timeout")
+                .env("APRS_TIMEOUT")
+                .value_name("SECONDS")
+                .default_value("120")
+                .takes_value(true)
+                .help("Switches to the next server if no data was received for this duration"),
+        )
+        .arg(
+            Arg::with_name("aprs-port")
+                .long("aprs-port")
+                .env("APRS_PORT")
+                .value_name("PORT")
+                .default_value("10152")
+                .takes_value(true)
+                .help(
+                    "Default port of the upstream APRS-IS servers (use 14580 with --aprs-filter)",
+                ),
+        )
+        .arg(
+            Arg::with_name("aprs-filter")
+                .long("aprs-filter")
+                .env("APRS_FILTER")
+                .value_name("FILTER")
+                .takes_value(true)
+                .help("Server-side APRS filter, e.g. `r/50.9/6.1/200`"),
+        )
+        .arg(
+            Arg::with_name("aprs-login")
+                .long("aprs-login")
+                .env("APRS_LOGIN")
+                .value_name("CALLSIGN")
+                .default_value("OGNWEBGW")
+                .takes_value(true)
+                .help("Callsign used to log in to the upstream APRS-IS server"),
+        )
+        .arg(
+            Arg::with_name("airfields")
+                .long("airfields")
+                .env("AIRFIELDS_FILE")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("Imports the airfields for the takeoff/landing logbook from a CUP file"),
+        )
+        .arg(
+            Arg::with_name("store-receivers")
+                .long("store-receivers")
+                .help("Saves the receiver callsign of each position record"),
+        )
+        .arg(
+            Arg::with_name("replay")
+                .long("replay")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("Replays a recorded APRS log (plain or gzip) instead of connecting to OGN"),
+        )
+        .arg(
+            Arg::with_name("replay-speed")
+                .long("replay-speed")
+                .value_name("FACTOR")
+                .default_value("1")
+                .takes_value(true)
+                .help("Speeds up the replay of the recorded APRS log"),
+        )
+        .arg(
+            Arg::with_name("archive-dir")
+                .long("archive-dir")
+                .value_name("DIR")
+                .takes_value(true)
+                .help("Writes all raw APRS lines to daily gzip files in this folder"),
+        )
+        .arg(
+            Arg::with_name("archive-retention")
+                .long("archive-retention")
+                .value_name("DAYS")
+                .default_value("30")
+                .takes_value(true)
+                .help("Removes archive files older than this (0 keeps all files)"),
+        )
         .get_matches();
 
     let listen_host = value_t!(matches.value_of("host"), IpAddr)?;
     let listen_port = value_t!(matches.value_of("port"), u16)?;
+    let store_receivers = matches.is_present("store-receivers");
+    let replay_path = matches.value_of("replay");
+    let replay_speed = value_t!(matches.value_of("replay-speed"), f64)?;
+    if replay_speed.is_nan() || replay_speed <= 0. {
+        return Err(anyhow!("--replay-speed must be greater than zero"));
+    }
+
+    let aprs_port = value_t!(matches.value_of("aprs-port"), u16)?;
+    let aprs_login = matches.value_of("aprs-login").unwrap();
+    let aprs_filter = matches.value_of("aprs-filter");
+    let aprs_timeout = Duration::from_secs(value_t!(matches.value_of("aprs-timeout"), u64)?);
+
+    let parse_server = |server: &str| {
+        Server::parse(server, aprs_port)
+            .with_context(|| format!("Invalid APRS server address: {}", server))
+    };
+
+    let aprs_backups = matches
+        .values_of("aprs-backup")
+        .into_iter()
+        .flatten()
+        .map(parse_server)
+        .collect::<Result<Vec<_>>>()?;
+
+    let upstream_configs = matches
+        .values_of("aprs-host")
+        .unwrap()
+        .map(|server| {
+            let mut servers = vec![parse_server(server)?];
+            servers.extend(aprs_backups.iter().cloned());
+
+            Ok(UpstreamConfig {
+                servers,
+                login: aprs_login.to_owned(),
+                filter: aprs_filter.map(str::to_owned),
+                timeout: aprs_timeout,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let airfields_path = matches.value_of("airfields").map(PathBuf::from);
+
+    let archive_dir = matches.value_of("archive-dir").map(PathBuf::from);
+    let archive_retention = value_t!(matches.value_of("archive-retention"), u32)?;
 
     let redis_url = env::var("REDIS_URL").context("REDIS_URL must be set")?;
     let redis_url = r2d2_redis::redis::parse_redis_url(&redis_url)
@@ -89,13 +251,49 @@ async fn main() -> Result<()> {
     }
     .start();
 
+    if let Some(path) = airfields_path {
+        let _airfields_updater_addr = AirfieldsUpdater {
+            path,
+            redis: redis_executor_addr.clone(),
+        }
+        .start();
+    }
+
+    let archive_addr = match archive_dir {
+        Some(archive_dir) => {
+            fs::create_dir_all(&archive_dir)
+                .with_context(|| format!("Could not create {}", archive_dir.display()))?;
+
+            Some(SyncArbiter::start(1, move || {
+                ArchiveRecorder::new(archive_dir.clone(), archive_retention)
+            }))
+        }
+        None => None,
+    };
+
     // Start "gateway" actor in separate thread
     let gateway_redis_addr = redis_executor_addr.clone();
-    let gateway: Addr<_> = Gateway::new(gateway_redis_addr).start();
+    let gateway: Addr<_> = Gateway::new(gateway_redis_addr, store_receivers, archive_addr).start();
+
+    // Start OGN clients (or the replay of a recorded log) in separate threads
+    if let Some(replay_path) = replay_path {
+        let replay = ReplayActor::new(gateway.clone().recipient(), replay_path, replay_speed)
+            .with_context(|| format!("Could not open {}", replay_path))?;
 
-    // Start OGN client in separate thread
-    let gw = gateway.clone();
-    let _ogn_addr: Addr<_> = Supervisor::start(|_| OGNActor::new(gw.recipient()));
+        let _replay_addr: Addr<_> = replay.start();
+    } else {
+        for (feed, upstream_config) in upstream_configs.into_iter().enumerate() {
+            let gw = gateway.clone();
+            let _upstream_addr: Addr<_> = Supervisor::start(move |_| {
+                UpstreamActor::new(
+                    feed,
+                    upstream_config,
+                    gw.clone().recipient(),
+                    gw.recipient(),
+                )
+            });
+        }
+    }
 
     debug!("Listening on {}:{}", listen_host, listen_port);
 
@@ -108,8 +306,18 @@ async fn main() -> Result<()> {
             .service(
                 web::scope("/api")
                     .wrap(Cors::default())
+                    .route(
+                        "/airfields/{icao}/logbook",
+                        web::get().to(api::airfields::logbook),
+                    )
+                    .route("/coverage", web::get().to(api::coverage::get))
                     .route("/ddb", web::get().to(api::ddb::get))
+                    .route("/flights", web::get().to(api::flights::list))
+                    .route("/flights/{flight_id}", web::get().to(api::flights::get))
                     .route("/status", web::get().to(api::status::get))
+                    .route("/receivers", web::get().to(api::receivers::list))
+                    .route("/receivers/{name}", web::get().to(api::receivers::get))
+                    .route("/records/{id}.igc", web::get().to(api::records::get_igc))
                     .route("/records/{id}", web::get().to(api::records::get))
                     .route("/live", web::get().to(api::live::get)),
             )
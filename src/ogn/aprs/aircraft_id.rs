@@ -0,0 +1,116 @@
+/// Decoded OGN `idXXYYYYYY` token
+///
+/// `XX` contains the stealth and no-tracking flags, the aircraft type and the
+/// address type, `YYYYYY` is the device address.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AircraftID {
+    pub address_type: AddressType,
+    pub aircraft_type: AircraftType,
+    pub stealth: bool,
+    pub no_tracking: bool,
+    pub address: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddressType {
+    Random,
+    Icao,
+    Flarm,
+    Ogn,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AircraftType {
+    Unknown = 0,
+    Glider = 1,
+    TowPlane = 2,
+    Helicopter = 3,
+    Parachute = 4,
+    DropPlane = 5,
+    HangGlider = 6,
+    Paraglider = 7,
+    PoweredAircraft = 8,
+    JetAircraft = 9,
+    Ufo = 10,
+    Balloon = 11,
+    Airship = 12,
+    Uav = 13,
+    GroundSupport = 14,
+    StaticObject = 15,
+}
+
+impl AircraftType {
+    fn from_bits(bits: u8) -> AircraftType {
+        match bits & 0x0F {
+            1 => AircraftType::Glider,
+            2 => AircraftType::TowPlane,
+            3 => AircraftType::Helicopter,
+            4 => AircraftType::Parachute,
+            5 => AircraftType::DropPlane,
+            6 => AircraftType::HangGlider,
+            7 => AircraftType::Paraglider,
+            8 => AircraftType::PoweredAircraft,
+            9 => AircraftType::JetAircraft,
+            10 => AircraftType::Ufo,
+            11 => AircraftType::Balloon,
+            12 => AircraftType::Airship,
+            13 => AircraftType::Uav,
+            14 => AircraftType::GroundSupport,
+            15 => AircraftType::StaticObject,
+            _ => AircraftType::Unknown,
+        }
+    }
+}
+
+impl AircraftID {
+    pub fn try_parse(token: &str) -> Option<AircraftID> {
+        let hex = token.strip_prefix("id")?;
+        if hex.len() != 8 {
+            return None;
+        }
+
+        let flags = u8::from_str_radix(hex.get(0..2)?, 16).ok()?;
+        let address = u32::from_str_radix(hex.get(2..)?, 16).ok()?;
+
+        let address_type = match flags & 0b11 {
+            1 => AddressType::Icao,
+            2 => AddressType::Flarm,
+            3 => AddressType::Ogn,
+            _ => AddressType::Random,
+        };
+
+        Some(AircraftID {
+            address_type,
+            aircraft_type: AircraftType::from_bits(flags >> 2),
+            stealth: flags & 0b1000_0000 != 0,
+            no_tracking: flags & 0b0100_0000 != 0,
+            address,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aircraft_id() {
+        let id = AircraftID::try_parse("idC6DD9612").unwrap();
+        assert_eq!(id.address_type, AddressType::Flarm);
+        assert_eq!(id.aircraft_type, AircraftType::Glider);
+        assert!(id.stealth);
+        assert!(id.no_tracking);
+        assert_eq!(id.address, 0xDD9612);
+
+        let id = AircraftID::try_parse("id1F3E2A11").unwrap();
+        assert_eq!(id.address_type, AddressType::Ogn);
+        assert_eq!(id.aircraft_type, AircraftType::Paraglider);
+        assert!(!id.stealth);
+        assert!(!id.no_tracking);
+        assert_eq!(id.address, 0x3E2A11);
+
+        assert!(AircraftID::try_parse("id06DD961").is_none());
+        assert!(AircraftID::try_parse("idXYDD9612").is_none());
+        assert!(AircraftID::try_parse("06DD9612").is_none());
+    }
+}
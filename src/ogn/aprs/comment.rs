@@ -0,0 +1,66 @@
+use crate::ogn::aprs::{APRSPosition, AircraftID, GPSQuality};
+use crate::units::FeetToMeter;
+
+/// Decodes the comment of an aircraft position report into the corresponding
+/// fields of the `position`, depending on the APRS destination call.
+///
+/// Unknown or malformed tokens are ignored.
+pub fn decode(destination: &str, comment: &str, position: &mut APRSPosition) {
+    let decode_token = match destination {
+        "OGNTRK" | "OGADSB" => decode_tracker_token,
+        _ => decode_ogn_token,
+    };
+
+    for token in comment.split_whitespace() {
+        decode_token(token, position);
+    }
+}
+
+/// Decodes a token of the common OGN extended comment
+/// (e.g. `id06DD9612 -355fpm -1.2rot 3.0dB 2e -1.3kHz gps3x3`)
+fn decode_ogn_token(token: &str, position: &mut APRSPosition) -> bool {
+    if let Some(aircraft_id) = AircraftID::try_parse(token) {
+        position.aircraft_id = Some(aircraft_id);
+    } else if let Some(Ok(fpm)) = token.strip_suffix("fpm").map(str::parse::<f64>) {
+        position.climb_rate = Some(fpm.feet_to_meter() / 60.);
+    } else if let Some(Ok(rot)) = token.strip_suffix("rot").map(str::parse::<f64>) {
+        // 1 rot is a "standard rate turn" of 3 degrees per second
+        position.turn_rate = Some(rot * 3.);
+    } else if let Some(Ok(db)) = token.strip_suffix("dB").map(str::parse) {
+        position.signal_quality = Some(db);
+    } else if let Some(Ok(khz)) = token.strip_suffix("kHz").map(str::parse) {
+        position.frequency_offset = Some(khz);
+    } else if let Some(Ok(errors)) = token.strip_suffix('e').map(str::parse) {
+        position.error_count = Some(errors);
+    } else if let Some(gps) = token.strip_prefix("gps") {
+        let mut parts = gps.splitn(2, 'x').map(str::parse);
+        match (parts.next(), parts.next()) {
+            (Some(Ok(horizontal)), Some(Ok(vertical))) => {
+                position.gps_quality = Some(GPSQuality {
+                    horizontal,
+                    vertical,
+                });
+            }
+            _ => return false,
+        }
+    } else {
+        return false;
+    }
+
+    true
+}
+
+/// Decodes a token of the OGN tracker and ADS-B comments, which additionally
+/// contain the pressure altitude as flight level (e.g. `FL292.00`)
+fn decode_tracker_token(token: &str, position: &mut APRSPosition) -> bool {
+    if decode_ogn_token(token, position) {
+        return true;
+    }
+
+    if let Some(Ok(flight_level)) = token.strip_prefix("FL").map(str::parse::<f64>) {
+        position.pressure_altitude = Some((flight_level * 100.).feet_to_meter());
+        return true;
+    }
+
+    false
+}
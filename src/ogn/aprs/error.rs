@@ -0,0 +1,55 @@
+use std::fmt;
+
+/// Reason why an APRS line could not be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParseError {
+    /// the line is not a `SOURCE>DESTINATION,PATH:BODY` packet
+    InvalidHeader,
+    /// the packet type is not supported (e.g. messages or aircraft status reports)
+    UnknownFormat,
+    /// the position report does not match any known position format
+    InvalidPosition,
+    /// the timestamp is not a valid `HHMMSS` time
+    InvalidTime,
+    /// the latitude or longitude is malformed or out of range
+    InvalidCoordinate,
+    /// the altitude is malformed
+    InvalidAltitude,
+    /// the position report does not contain an altitude
+    MissingAltitude,
+    /// the receiver position or status beacon could not be decoded
+    InvalidBeacon,
+}
+
+impl ParseError {
+    /// Machine-readable name of the error (e.g. `invalid_time`)
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ParseError::InvalidHeader => "invalid_header",
+            ParseError::UnknownFormat => "unknown_format",
+            ParseError::InvalidPosition => "invalid_position",
+            ParseError::InvalidTime => "invalid_time",
+            ParseError::InvalidCoordinate => "invalid_coordinate",
+            ParseError::InvalidAltitude => "invalid_altitude",
+            ParseError::MissingAltitude => "missing_altitude",
+            ParseError::InvalidBeacon => "invalid_beacon",
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            ParseError::InvalidHeader => "invalid APRS header",
+            ParseError::UnknownFormat => "unknown packet format",
+            ParseError::InvalidPosition => "invalid position report",
+            ParseError::InvalidTime => "invalid time",
+            ParseError::InvalidCoordinate => "invalid coordinate",
+            ParseError::InvalidAltitude => "invalid altitude",
+            ParseError::MissingAltitude => "missing altitude",
+            ParseError::InvalidBeacon => "invalid receiver beacon",
+        };
+
+        f.write_str(description)
+    }
+}
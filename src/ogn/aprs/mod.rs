@@ -0,0 +1,445 @@
+mod aircraft_id;
+mod comment;
+mod error;
+mod network;
+mod packet;
+mod position;
+mod receiver;
+mod scan;
+mod scan_regex;
+
+pub use self::aircraft_id::AircraftID;
+pub use self::error::ParseError;
+pub use self::network::SourceNetwork;
+pub use self::packet::APRSPacket;
+pub use self::position::{APRSPosition, GPSQuality};
+pub use self::receiver::{ReceiverPosition, ReceiverStatus};
+
+/// A decoded APRS message from the OGN APRS servers
+#[derive(Debug)]
+pub enum APRSMessage<'a> {
+    /// position report of an aircraft
+    Position(APRSPosition<'a>),
+    /// position beacon of a receiver station
+    ReceiverPosition(ReceiverPosition<'a>),
+    /// status beacon of a receiver station
+    ReceiverStatus(ReceiverStatus<'a>),
+    /// server comment (e.g. `# aprsc 2.1.4-g408ed49`)
+    ServerComment(&'a str),
+}
+
+/// Parses the APRS header of the `line` and hands the body to the decoder
+/// matching the packet type.
+pub fn parse(line: &str) -> Result<APRSMessage<'_>, ParseError> {
+    parse_with(line, position::parse)
+}
+
+/// Same as `parse()`, but decoding position reports with the original
+/// regex-based implementation, which is kept as a reference for the tests,
+/// benchmarks and fuzz targets.
+pub fn parse_with_regex(line: &str) -> Result<APRSMessage<'_>, ParseError> {
+    parse_with(line, position::parse_with_regex)
+}
+
+fn parse_with<'a>(
+    line: &'a str,
+    parse_position: fn(&APRSPacket<'a>) -> Result<APRSPosition<'a>, ParseError>,
+) -> Result<APRSMessage<'a>, ParseError> {
+    if let Some(comment) = line.strip_prefix('#') {
+        return Ok(APRSMessage::ServerComment(comment.trim()));
+    }
+
+    let packet = APRSPacket::parse(line).ok_or(ParseError::InvalidHeader)?;
+
+    if packet.is_receiver_beacon() {
+        match packet.body.as_bytes().first() {
+            Some(b'/') => receiver::parse_position(&packet).map(APRSMessage::ReceiverPosition),
+            Some(b'>') => receiver::parse_status(&packet).map(APRSMessage::ReceiverStatus),
+            _ => Err(ParseError::UnknownFormat),
+        }
+    } else {
+        match packet.body.as_bytes().first() {
+            Some(b'/') | Some(b'@') => parse_position(&packet).map(APRSMessage::Position),
+            _ => Err(ParseError::UnknownFormat),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::aircraft_id::{AddressType, AircraftType};
+    use super::*;
+    use crate::units::FeetToMeter;
+    use approx::assert_relative_eq;
+
+    fn parse_position(line: &str) -> Option<APRSPosition<'_>> {
+        match parse(line) {
+            Ok(APRSMessage::Position(position)) => Some(position),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_parse_1() {
+        let result = parse_position(
+            r"FLRDD9612>APRS,qAS,VillaBlau:/141956h4911.18N/00815.93E'126/059/A=003716 !W75! id06DD9612 -355fpm -1.2rot 3.0dB 2e -1.3kHz gps3x3",
+        );
+        assert!(result.is_some());
+
+        let position = result.unwrap();
+        assert_eq!(position.id, "FLRDD9612");
+        assert_eq!(position.network, SourceNetwork::Flarm);
+        assert_eq!(position.time, "14:19:56".parse().unwrap());
+        assert_relative_eq!(position.latitude, 49. + 11.187 / 60.);
+        assert_relative_eq!(position.longitude, 8. + 15.935 / 60.);
+        assert_relative_eq!(position.altitude, 1132.6368);
+        assert_eq!(position.course, 126);
+        assert_eq!(position.receiver, Some("VillaBlau"));
+        assert_eq!(position.q_construct, Some("qAS"));
+        assert_relative_eq!(position.speed, 109.268);
+        assert_relative_eq!(position.climb_rate.unwrap(), -1.8034);
+        assert_relative_eq!(position.turn_rate.unwrap(), -3.6);
+        assert_relative_eq!(position.signal_quality.unwrap(), 3.0);
+        assert_eq!(position.error_count, Some(2));
+        assert_relative_eq!(position.frequency_offset.unwrap(), -1.3);
+        assert_eq!(
+            position.gps_quality,
+            Some(GPSQuality {
+                horizontal: 3,
+                vertical: 3
+            })
+        );
+        assert_eq!(
+            position.aircraft_id,
+            Some(AircraftID {
+                address_type: AddressType::Flarm,
+                aircraft_type: AircraftType::Glider,
+                stealth: false,
+                no_tracking: false,
+                address: 0xDD9612,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_2() {
+        let result = parse_position(
+            r"ICA4060D7>APRS,qAS,UKDUN2:/141953h5147.03N\00109.00W^210/143/A=003405 !W50! id214060D7 +079fpm +0.0rot 8.0dB 0e -11.9kHz gps3x4",
+        );
+        assert!(result.is_some());
+
+        let position = result.unwrap();
+        assert_eq!(position.id, "ICA4060D7");
+        assert_eq!(position.time, "14:19:53".parse().unwrap());
+        assert_relative_eq!(position.latitude, 51. + 47.035 / 60.);
+        assert_relative_eq!(position.longitude, -(1. + 9.000 / 60.));
+        assert_relative_eq!(position.altitude, 1037.844);
+        assert_eq!(position.course, 210);
+        assert_eq!(position.receiver, Some("UKDUN2"));
+        assert_eq!(position.q_construct, Some("qAS"));
+        assert_relative_eq!(position.speed, 264.836);
+        assert_relative_eq!(position.climb_rate.unwrap(), 0.40132);
+        assert_relative_eq!(position.turn_rate.unwrap(), 0.);
+        assert_relative_eq!(position.signal_quality.unwrap(), 8.0);
+        assert_eq!(position.error_count, Some(0));
+        assert_relative_eq!(position.frequency_offset.unwrap(), -11.9);
+        assert_eq!(
+            position.gps_quality,
+            Some(GPSQuality {
+                horizontal: 3,
+                vertical: 4
+            })
+        );
+        assert_eq!(
+            position.aircraft_id,
+            Some(AircraftID {
+                address_type: AddressType::Icao,
+                aircraft_type: AircraftType::PoweredAircraft,
+                stealth: false,
+                no_tracking: false,
+                address: 0x4060D7,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_3() {
+        let result = parse_position(
+            r"FLRDD87AC>APRS,qAS,LFQB:/141950h4818.33N/00401.87E'014/034/A=005199 id06DD87AC +218fpm +2.5rot 17.8dB 0e -2.4kHz gps3x4 -1.0dBm",
+        );
+        assert!(result.is_some());
+
+        let position = result.unwrap();
+        assert_eq!(position.id, "FLRDD87AC");
+        assert_eq!(position.time, "14:19:50".parse().unwrap());
+        assert_relative_eq!(position.latitude, 48. + 18.33 / 60.);
+        assert_relative_eq!(position.longitude, 4. + 1.87 / 60.);
+        assert_relative_eq!(position.altitude, 1584.6552);
+        assert_eq!(position.course, 14);
+        assert_relative_eq!(position.speed, 62.968);
+        assert_relative_eq!(position.climb_rate.unwrap(), 1.10744);
+        assert_relative_eq!(position.turn_rate.unwrap(), 7.5);
+        assert_relative_eq!(position.signal_quality.unwrap(), 17.8);
+        assert_eq!(position.error_count, Some(0));
+        assert_relative_eq!(position.frequency_offset.unwrap(), -2.4);
+        assert_eq!(
+            position.gps_quality,
+            Some(GPSQuality {
+                horizontal: 3,
+                vertical: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_without_comment() {
+        let result =
+            parse_position(r"FLRDD87AC>APRS,qAS,LFQB:/141950h4818.33N/00401.87E'014/034/A=005199");
+        assert!(result.is_some());
+
+        let position = result.unwrap();
+        assert_eq!(position.climb_rate, None);
+        assert_eq!(position.turn_rate, None);
+        assert_eq!(position.signal_quality, None);
+        assert_eq!(position.error_count, None);
+        assert_eq!(position.frequency_offset, None);
+        assert_eq!(position.gps_quality, None);
+        assert_eq!(position.aircraft_id, None);
+    }
+
+    #[test]
+    fn test_parse_other_networks() {
+        let position = parse_position(
+            r"FNT1103CE>OGNFNT,qAS,FNB1103CE:/183727h5057.94N/00801.00Eg355/002/A=001042 !W10! id1E1103CE +03fpm",
+        )
+        .unwrap();
+        assert_eq!(position.id, "FNT1103CE");
+        assert_eq!(position.network, SourceNetwork::Fanet);
+
+        let position = parse_position(
+            r"PAW404FB4>OGPAW,qAS,UKSPT:/104446h5131.08N/00113.67W'000/000/A=000338 !W06! id1F404FB4 +000fpm +0.0rot 23.8dB 0e",
+        )
+        .unwrap();
+        assert_eq!(position.id, "PAW404FB4");
+        assert_eq!(position.network, SourceNetwork::PilotAware);
+
+        let position = parse_position(
+            r"NAV042121>OGNAVI,qAS,NAVITER:/140648h4550.36N/01314.85E'090/152/A=001086 !W81! id0440042121 +000fpm +0.5rot",
+        )
+        .unwrap();
+        assert_eq!(position.network, SourceNetwork::Naviter);
+
+        let position =
+            parse_position(r"Glider-01>APRS,qAS,LFQB:/141950h4818.33N/00401.87E'014/034/A=005199")
+                .unwrap();
+        assert_eq!(position.id, "Glider-01");
+        assert_eq!(position.network, SourceNetwork::Unknown);
+    }
+
+    #[test]
+    fn test_parse_tracker() {
+        let position = parse_position(
+            r"OGN3E2A11>OGNTRK,qAS,LZHL:/093021h4729.29N/01907.61E'000/000/A=000387 !W61! id073E2A11 +000fpm +0.0rot FL003.44 44.5dB 0e -3.8kHz gps2x3",
+        )
+        .unwrap();
+        assert_eq!(position.network, SourceNetwork::OgnTracker);
+        assert_relative_eq!(position.pressure_altitude.unwrap(), 104.8512);
+        assert_relative_eq!(position.signal_quality.unwrap(), 44.5);
+
+        // flight levels are only decoded for trackers and ADS-B
+        let position = parse_position(
+            r"FLRDD87AC>APRS,qAS,LFQB:/141950h4818.33N/00401.87E'014/034/A=005199 FL003.44",
+        )
+        .unwrap();
+        assert_eq!(position.pressure_altitude, None);
+    }
+
+    #[test]
+    fn test_parse_receiver_beacons() {
+        match parse(r"Koenigsdf>APRS,TCPIP*,qAC,GLIDERN1:/132052h4807.51NI01132.48E&/A=001811") {
+            Ok(APRSMessage::ReceiverPosition(position)) => assert_eq!(position.name, "Koenigsdf"),
+            _ => panic!("expected a receiver position"),
+        }
+
+        match parse(r"LFNW>OGNSDR,TCPIP*,qAC,GLIDERN5:>151958h v0.2.8") {
+            Ok(APRSMessage::ReceiverStatus(status)) => assert_eq!(status.name, "LFNW"),
+            _ => panic!("expected a receiver status"),
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            parse(r"# aprsc 2.1.4-g408ed49 17 Apr 2018 14:20:01 GMT GLIDERN1"),
+            Ok(APRSMessage::ServerComment(_))
+        ));
+        assert_eq!(
+            parse(r"FLRDD9612 APRS qAS VillaBlau").err(),
+            Some(ParseError::InvalidHeader)
+        );
+        assert_eq!(
+            parse(r"FLRDD9612>APRS,qAS,VillaBlau:>141956h some status").err(),
+            Some(ParseError::UnknownFormat)
+        );
+        assert_eq!(
+            parse(r"FLRDD9612>APRS,qAS,VillaBlau:/141956h").err(),
+            Some(ParseError::InvalidPosition)
+        );
+        assert_eq!(
+            parse(r"FLRDD9612>APRS,qAS,VillaBlau:/141956h4911.18N/00815.93E'126/059 id06DD9612")
+                .err(),
+            Some(ParseError::MissingAltitude)
+        );
+        assert_eq!(
+            parse(r"Koenigsdf>APRS,TCPIP*,qAC,GLIDERN1:/132052h").err(),
+            Some(ParseError::InvalidBeacon)
+        );
+        assert_eq!(
+            parse(r"FLRDD9612>APRS,qAS,VillaBlau:/256199h4911.18N/00815.93E'126/059/A=003716")
+                .err(),
+            Some(ParseError::InvalidTime)
+        );
+        assert_eq!(
+            parse(r"FLRDD9612>APRS,qAS,VillaBlau:/141956h4971.18N/00815.93E'126/059/A=003716")
+                .err(),
+            Some(ParseError::InvalidCoordinate)
+        );
+        assert_eq!(
+            parse(r"FLRDD9612>APRS,qAS,VillaBlau:/141956h9511.18N/00815.93E'126/059/A=003716")
+                .err(),
+            Some(ParseError::InvalidCoordinate)
+        );
+        assert_eq!(
+            parse(r"Koenigsdf>APRS,TCPIP*,qAC,GLIDERN1:/132052h4807.51NI18132.48E&/A=001811").err(),
+            Some(ParseError::InvalidCoordinate)
+        );
+        assert_eq!(
+            parse(r"Koenigsdf>APRS,TCPIP*,qAC,GLIDERN1:>132099h v0.2.7.RPI-GPU").err(),
+            Some(ParseError::InvalidTime)
+        );
+    }
+
+    #[test]
+    fn test_parse_negative_altitude() {
+        let position = parse_position(
+            r"FLRDD9612>APRS,qAS,EnBokek:/141956h3111.18N/03523.93E'126/059/A=-01266 !W75! id06DD9612",
+        )
+        .unwrap();
+        assert_relative_eq!(position.altitude, -385.8768);
+    }
+
+    #[test]
+    fn test_parse_without_course_and_speed() {
+        let position = parse_position(
+            r"OGN3E2A11>OGNTRK,qAS,LZHL:/093021h4729.29N/01907.61E'/A=000387 !W61! id073E2A11 +000fpm",
+        )
+        .unwrap();
+        assert_relative_eq!(position.latitude, 47. + 29.296 / 60.);
+        assert_relative_eq!(position.longitude, 19. + 7.611 / 60.);
+        assert_relative_eq!(position.altitude, 117.9576);
+        assert_eq!(position.course, 0);
+        assert_relative_eq!(position.speed, 0.);
+        assert_relative_eq!(position.climb_rate.unwrap(), 0.);
+    }
+
+    #[test]
+    fn test_parse_precision_variants() {
+        let position = parse_position(
+            r"FLRDD9612>APRS,qAS,LFQB:/141950h4818.331N/00401.872E'014/034/A=005199",
+        )
+        .unwrap();
+        assert_relative_eq!(position.latitude, 48. + 18.331 / 60.);
+        assert_relative_eq!(position.longitude, 4. + 1.872 / 60.);
+
+        let position = parse_position(
+            r"FLRDD9612>APRS,qAS,LFQB:/141950h4818.3312N/00401.8723E'014/034/A=005199",
+        )
+        .unwrap();
+        assert_relative_eq!(position.latitude, 48. + 18.3312 / 60.);
+        assert_relative_eq!(position.longitude, 4. + 1.8723 / 60.);
+    }
+
+    #[test]
+    fn test_parse_compressed() {
+        // examples from the APRS 1.0.1 specification, chapter 9
+        let position = parse_position(
+            r"FNT1103CE>OGNFNT,qAS,FNB1103CE:/183727h/5L!!<*e7>7P[/A=001042 id1E1103CE +03fpm",
+        )
+        .unwrap();
+        assert_eq!(position.network, SourceNetwork::Fanet);
+        assert_relative_eq!(position.latitude, 49.5, epsilon = 1e-5);
+        assert_relative_eq!(position.longitude, -72.75, epsilon = 1e-5);
+        assert_relative_eq!(position.altitude, 317.6016);
+        assert_eq!(position.course, 88);
+        assert_relative_eq!(position.speed, 67.1, epsilon = 0.01);
+        assert_relative_eq!(position.climb_rate.unwrap(), 0.015_24);
+
+        let position =
+            parse_position(r"FNT1103CE>OGNFNT,qAS,FNB1103CE:/183727h/5L!!<*e7OS]S").unwrap();
+        assert_relative_eq!(
+            position.altitude,
+            10004.0_f64.feet_to_meter(),
+            epsilon = 0.5
+        );
+        assert_eq!(position.course, 0);
+
+        assert_eq!(
+            parse(r"FNT1103CE>OGNFNT,qAS,FNB1103CE:/183727h/5L!!<*e7> sT").err(),
+            Some(ParseError::MissingAltitude)
+        );
+    }
+
+    /// real beacon lines of the various OGN source networks
+    const CORPUS: &[&str] = &[
+        r"FLRDDA5BA>APRS,qAS,LFMX:/160829h4415.41N/00600.03E'342/049/A=005524 id0ADDA5BA -454fpm -1.1rot 8.8dB 0e +51.2kHz gps4x5",
+        r"ICA3D17F2>APRS,qAS,dl4mea:/074849h4821.61N\01224.49E^322/103/A=003054 !W46! id213D17F2 -039fpm +0.0rot 2.5dB 3e -0.0kHz gps1x1",
+        r"FLRDDE626>OGFLR,qAS,EGHL:/074548h5111.32N/00102.04W'086/007/A=000607 !W80! id0ADDE626 -019fpm +0.0rot 5.5dB 3e -4.3kHz",
+        r"OGN2FD00F>OGNTRK,qAS,LZHL:/093213h4848.78N/01708.32E'000/000/A=000538 !W12! id072FD00F -058fpm +0.0rot FL003.12 32.8dB 0e -0.8kHz gps3x5 +12.7dBm",
+        r"ICA4B0E3A>OGADSB,qAS,Letzi:/072319h4711.75N\00802.59E^124/426/A=035275 !W58! id254B0E3A -000fpm FL350.00 A3:SWR83K",
+        r"FNT1103CE>OGNFNT,qAS,FNB1103CE:/183727h5057.94N/00801.00Eg355/002/A=001042 !W10! id1E1103CE +03fpm",
+        r"PAW404FB4>OGPAW,qAS,UKSPT:/104446h5131.08N/00113.67W'000/000/A=000338 !W06! id1F404FB4 +000fpm +0.0rot 23.8dB 0e",
+        r"SKY3E5906>OGSKYL,qAS,SKYLINES:/184952h4821.00N/00703.16E'000/000/A=001129 !W52! id2816 +000fpm",
+        r"SPI19D5D6>OGSPID,qAS,SPIDER:/185224h4649.02N/00818.41E'000/000/A=001709 !W56! id300234010617040 +19dB LWE 3D",
+        r"NAV042121>OGNAVI,qAS,NAVITER:/140648h4550.36N/01314.85E'090/152/A=001086 !W81! id0440042121 +000fpm +0.5rot",
+        r"FLRDD87AC>APRS,qAS,LFQB:/141950h4818.33N/00401.87E'/A=-00064 id06DD87AC",
+        r"FNT1103CE>OGNFNT,qAS,FNB1103CE:/183727h/5L!!<*e7>7P[/A=001042 id1E1103CE +03fpm",
+    ];
+
+    #[test]
+    fn test_parse_corpus() {
+        for line in CORPUS {
+            assert!(parse_position(line).is_some(), "failed to parse: {}", line);
+        }
+    }
+
+    /// Checks that the hand-written and the regex-based parser agree
+    fn assert_same_as_regex(line: &str) {
+        assert_eq!(
+            format!("{:?}", parse(line)),
+            format!("{:?}", parse_with_regex(line)),
+            "different results for: {}",
+            line
+        );
+    }
+
+    #[test]
+    fn test_parse_corrupted_lines() {
+        for line in CORPUS {
+            assert_same_as_regex(line);
+
+            // truncated lines
+            for (index, _) in line.char_indices() {
+                assert_same_as_regex(&line[..index]);
+            }
+
+            // corrupted lines
+            for (index, character) in line.char_indices() {
+                for replacement in &[" ", "/", "-", ".", "9", "!", "{", "\n", "ä"] {
+                    let mut corrupted = line.to_string();
+                    corrupted.replace_range(index..index + character.len_utf8(), replacement);
+                    assert_same_as_regex(&corrupted);
+                }
+            }
+        }
+    }
+}
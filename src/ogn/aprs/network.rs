@@ -0,0 +1,143 @@
+use std::str::FromStr;
+
+/// Network or device family that the sender is reporting from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SourceNetwork {
+    Flarm,
+    OgnTracker,
+    Fanet,
+    PilotAware,
+    Spot,
+    InReach,
+    Spider,
+    Skylines,
+    Naviter,
+    Lt24,
+    Capturs,
+    Flymaster,
+    AdsB,
+    Unknown,
+}
+
+impl SourceNetwork {
+    /// Derives the network from the APRS destination call (e.g. `OGFLR`) or,
+    /// for the legacy `APRS` destination, from the sender ID prefix (e.g. `FLR`).
+    pub fn detect(id: &str, destination: &str) -> SourceNetwork {
+        match destination {
+            "OGFLR" => SourceNetwork::Flarm,
+            "OGNTRK" => SourceNetwork::OgnTracker,
+            "OGNFNT" | "OGFNT" => SourceNetwork::Fanet,
+            "OGPAW" => SourceNetwork::PilotAware,
+            "OGSPOT" => SourceNetwork::Spot,
+            "OGINRE" | "OGINREACH" => SourceNetwork::InReach,
+            "OGSPID" => SourceNetwork::Spider,
+            "OGSKYL" => SourceNetwork::Skylines,
+            "OGNAVI" => SourceNetwork::Naviter,
+            "OGLT24" => SourceNetwork::Lt24,
+            "OGCAPT" => SourceNetwork::Capturs,
+            "OGFLYM" => SourceNetwork::Flymaster,
+            "OGADSB" => SourceNetwork::AdsB,
+            "APRS" => match id.get(0..3) {
+                Some("FLR") | Some("ICA") | Some("RND") => SourceNetwork::Flarm,
+                Some("OGN") => SourceNetwork::OgnTracker,
+                Some("FNT") => SourceNetwork::Fanet,
+                Some("PAW") => SourceNetwork::PilotAware,
+                Some("SPO") => SourceNetwork::Spot,
+                Some("INR") => SourceNetwork::InReach,
+                Some("SPI") => SourceNetwork::Spider,
+                Some("SKY") => SourceNetwork::Skylines,
+                Some("NAV") => SourceNetwork::Naviter,
+                Some("LT2") => SourceNetwork::Lt24,
+                Some("CAP") => SourceNetwork::Capturs,
+                Some("FMT") => SourceNetwork::Flymaster,
+                _ => SourceNetwork::Unknown,
+            },
+            _ => SourceNetwork::Unknown,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SourceNetwork::Flarm => "flarm",
+            SourceNetwork::OgnTracker => "ogn-tracker",
+            SourceNetwork::Fanet => "fanet",
+            SourceNetwork::PilotAware => "pilotaware",
+            SourceNetwork::Spot => "spot",
+            SourceNetwork::InReach => "inreach",
+            SourceNetwork::Spider => "spider",
+            SourceNetwork::Skylines => "skylines",
+            SourceNetwork::Naviter => "naviter",
+            SourceNetwork::Lt24 => "lt24",
+            SourceNetwork::Capturs => "capturs",
+            SourceNetwork::Flymaster => "flymaster",
+            SourceNetwork::AdsB => "adsb",
+            SourceNetwork::Unknown => "unknown",
+        }
+    }
+}
+
+impl FromStr for SourceNetwork {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        ALL_SOURCE_NETWORKS
+            .iter()
+            .find(|it| it.as_str() == text)
+            .copied()
+            .ok_or(())
+    }
+}
+
+const ALL_SOURCE_NETWORKS: [SourceNetwork; 14] = [
+    SourceNetwork::Flarm,
+    SourceNetwork::OgnTracker,
+    SourceNetwork::Fanet,
+    SourceNetwork::PilotAware,
+    SourceNetwork::Spot,
+    SourceNetwork::InReach,
+    SourceNetwork::Spider,
+    SourceNetwork::Skylines,
+    SourceNetwork::Naviter,
+    SourceNetwork::Lt24,
+    SourceNetwork::Capturs,
+    SourceNetwork::Flymaster,
+    SourceNetwork::AdsB,
+    SourceNetwork::Unknown,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::SourceNetwork;
+
+    #[test]
+    fn test_source_network() {
+        assert_eq!(
+            SourceNetwork::detect("FLRDD9612", "OGFLR"),
+            SourceNetwork::Flarm
+        );
+        assert_eq!(
+            SourceNetwork::detect("ICA4060D7", "APRS"),
+            SourceNetwork::Flarm
+        );
+        assert_eq!(
+            SourceNetwork::detect("OGN3E2A11", "APRS"),
+            SourceNetwork::OgnTracker
+        );
+        assert_eq!(
+            SourceNetwork::detect("OGN3E2A11", "OGNTRK"),
+            SourceNetwork::OgnTracker
+        );
+        assert_eq!(
+            SourceNetwork::detect("SKY1234", "OGSKYL"),
+            SourceNetwork::Skylines
+        );
+        assert_eq!(
+            SourceNetwork::detect("XYZ123456", "APRS"),
+            SourceNetwork::Unknown
+        );
+
+        assert_eq!("fanet".parse(), Ok(SourceNetwork::Fanet));
+        assert_eq!("ogn-tracker".parse(), Ok(SourceNetwork::OgnTracker));
+        assert_eq!("foo".parse::<SourceNetwork>(), Err(()));
+    }
+}
@@ -0,0 +1,129 @@
+/// APRS packet split into its header fields and the body
+/// (e.g. `FLRDD9612>APRS,qAS,VillaBlau:/141956h…`)
+#[derive(Debug, PartialEq)]
+pub struct APRSPacket<'a> {
+    /// source callsign (e.g. `FLRDD9612`)
+    pub source: &'a str,
+    /// destination call (e.g. `APRS` or `OGFLR`)
+    pub destination: &'a str,
+    /// comma-separated path (e.g. `qAS,VillaBlau`)
+    pub path: &'a str,
+    /// everything after the `:` separator
+    pub body: &'a str,
+}
+
+impl<'a> APRSPacket<'a> {
+    pub fn parse(line: &'a str) -> Option<APRSPacket<'a>> {
+        let header_end = line.find(':')?;
+        let (header, body) = (&line[..header_end], &line[header_end + 1..]);
+
+        let source_end = header.find('>')?;
+        let source = &header[..source_end];
+        if !is_valid_callsign(source) {
+            return None;
+        }
+
+        let mut destination_and_path = header[source_end + 1..].splitn(2, ',');
+        let destination = destination_and_path.next()?;
+        let path = destination_and_path.next().unwrap_or("");
+        if destination.is_empty() {
+            return None;
+        }
+
+        Some(APRSPacket {
+            source,
+            destination,
+            path,
+            body,
+        })
+    }
+
+    /// APRS-IS q-construct of the path (e.g. `qAS`)
+    pub fn q_construct(&self) -> Option<&'a str> {
+        self.path.split(',').find(|it| is_q_construct(it))
+    }
+
+    /// Callsign following the q-construct in the path, which for OGN is the
+    /// receiver that heard the sender (e.g. `VillaBlau`)
+    pub fn receiver(&self) -> Option<&'a str> {
+        self.path
+            .split(',')
+            .skip_while(|it| !is_q_construct(it))
+            .nth(1)
+            .filter(|it| !it.is_empty())
+    }
+
+    /// Returns `true` for status and position beacons sent by OGN receivers
+    pub fn is_receiver_beacon(&self) -> bool {
+        match self.destination {
+            "OGNSDR" => true,
+            "APRS" => self.path.split(',').any(|it| it == "TCPIP*"),
+            _ => false,
+        }
+    }
+}
+
+fn is_valid_callsign(text: &str) -> bool {
+    !text.is_empty()
+        && text.len() <= 9
+        && text
+            .bytes()
+            .all(|it| it.is_ascii_alphanumeric() || it == b'-')
+}
+
+fn is_q_construct(text: &str) -> bool {
+    text.len() == 3 && text.starts_with("qA")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::APRSPacket;
+
+    #[test]
+    fn test_parse() {
+        let packet = APRSPacket::parse(
+            r"FLRDD9612>APRS,qAS,VillaBlau:/141956h4911.18N/00815.93E'126/059/A=003716",
+        )
+        .unwrap();
+        assert_eq!(packet.source, "FLRDD9612");
+        assert_eq!(packet.destination, "APRS");
+        assert_eq!(packet.path, "qAS,VillaBlau");
+        assert_eq!(packet.body, "/141956h4911.18N/00815.93E'126/059/A=003716");
+        assert_eq!(packet.q_construct(), Some("qAS"));
+        assert_eq!(packet.receiver(), Some("VillaBlau"));
+        assert!(!packet.is_receiver_beacon());
+    }
+
+    #[test]
+    fn test_parse_path() {
+        let packet = APRSPacket::parse(r"FLRDD9612>OGFLR,RELAY*,qAR,LFQB:/141956h").unwrap();
+        assert_eq!(packet.destination, "OGFLR");
+        assert_eq!(packet.q_construct(), Some("qAR"));
+        assert_eq!(packet.receiver(), Some("LFQB"));
+
+        let packet = APRSPacket::parse(r"LFNW>OGNSDR,TCPIP*,qAC,GLIDERN5:>151958h v0.2.8").unwrap();
+        assert_eq!(packet.q_construct(), Some("qAC"));
+        assert_eq!(packet.receiver(), Some("GLIDERN5"));
+        assert!(packet.is_receiver_beacon());
+
+        let packet = APRSPacket::parse(r"Koenigsdf>APRS,TCPIP*:>132052h").unwrap();
+        assert_eq!(packet.q_construct(), None);
+        assert_eq!(packet.receiver(), None);
+        assert!(packet.is_receiver_beacon());
+
+        let packet = APRSPacket::parse(r"FLRDD9612>APRS:/141956h").unwrap();
+        assert_eq!(packet.path, "");
+        assert_eq!(packet.q_construct(), None);
+        assert_eq!(packet.receiver(), None);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(APRSPacket::parse("# aprsc 2.1.4-g408ed49").is_none());
+        assert!(APRSPacket::parse("FLRDD9612>APRS,qAS,VillaBlau").is_none());
+        assert!(APRSPacket::parse(">APRS,qAS,VillaBlau:/141956h").is_none());
+        assert!(APRSPacket::parse("FLRDD9612XYZ>APRS,qAS,VillaBlau:/141956h").is_none());
+        assert!(APRSPacket::parse("FLR_DD961>APRS,qAS,VillaBlau:/141956h").is_none());
+        assert!(APRSPacket::parse("FLRDD9612>,qAS,VillaBlau:/141956h").is_none());
+    }
+}
@@ -0,0 +1,247 @@
+use std::fmt;
+
+use crate::ogn::aprs::scan::{self, RawCompressed, RawPosition, RawUncompressed};
+use crate::ogn::aprs::{comment, scan_regex, APRSPacket, AircraftID, ParseError, SourceNetwork};
+use crate::units::{FeetToMeter, KnotsToKilometersPerHour};
+use chrono::NaiveTime;
+
+#[derive(Debug)]
+pub struct APRSPosition<'a> {
+    pub id: &'a str,
+    pub network: SourceNetwork,
+    pub time: NaiveTime,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: f64,
+    /// course in degrees (`0` if not reported)
+    pub course: i32,
+    /// ground speed in km/h (`0` if not reported)
+    pub speed: f64,
+    /// climb rate in m/s
+    pub climb_rate: Option<f64>,
+    /// turn rate in degrees per second
+    pub turn_rate: Option<f64>,
+    /// signal-to-noise ratio in dB
+    pub signal_quality: Option<f64>,
+    /// number of bit errors corrected by the receiver
+    pub error_count: Option<u8>,
+    /// frequency offset in kHz
+    pub frequency_offset: Option<f64>,
+    pub gps_quality: Option<GPSQuality>,
+    pub aircraft_id: Option<AircraftID>,
+    /// pressure altitude in meters
+    pub pressure_altitude: Option<f64>,
+    /// callsign of the receiver that heard the sender (e.g. `VillaBlau`)
+    pub receiver: Option<&'a str>,
+    /// APRS-IS q-construct of the path (e.g. `qAS`)
+    pub q_construct: Option<&'a str>,
+}
+
+/// Horizontal and vertical GPS accuracy in meters (e.g. `gps3x4`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GPSQuality {
+    pub horizontal: u8,
+    pub vertical: u8,
+}
+
+impl fmt::Display for GPSQuality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{}", self.horizontal, self.vertical)
+    }
+}
+
+/// Fields shared by the uncompressed and compressed position formats
+struct PositionFields {
+    time: NaiveTime,
+    latitude: f64,
+    longitude: f64,
+    course: Option<i32>,
+    /// ground speed in km/h
+    speed: Option<f64>,
+    /// altitude in meters
+    altitude: Option<f64>,
+    /// byte offset of the comment in the packet body
+    comment_start: usize,
+}
+
+pub fn parse<'a>(packet: &APRSPacket<'a>) -> Result<APRSPosition<'a>, ParseError> {
+    decode(packet, scan::scan(packet.body))
+}
+
+/// Same as `parse()`, but using the regex-based scanner
+pub fn parse_with_regex<'a>(packet: &APRSPacket<'a>) -> Result<APRSPosition<'a>, ParseError> {
+    decode(packet, scan_regex::scan(packet.body))
+}
+
+fn decode<'a>(
+    packet: &APRSPacket<'a>,
+    raw: Option<RawPosition>,
+) -> Result<APRSPosition<'a>, ParseError> {
+    let fields = match raw {
+        Some(RawPosition::Uncompressed(raw)) => uncompressed_fields(&raw)?,
+        Some(RawPosition::Compressed(raw)) => compressed_fields(&raw)?,
+        None => return Err(ParseError::InvalidPosition),
+    };
+
+    let altitude = fields.altitude.ok_or(ParseError::MissingAltitude)?;
+
+    let mut position = APRSPosition {
+        id: packet.source,
+        network: SourceNetwork::detect(packet.source, packet.destination),
+        time: fields.time,
+        latitude: fields.latitude,
+        longitude: fields.longitude,
+        altitude,
+        course: fields.course.unwrap_or(0),
+        speed: fields.speed.unwrap_or(0.),
+        climb_rate: None,
+        turn_rate: None,
+        signal_quality: None,
+        error_count: None,
+        frequency_offset: None,
+        gps_quality: None,
+        aircraft_id: None,
+        pressure_altitude: None,
+        receiver: packet.receiver(),
+        q_construct: packet.q_construct(),
+    };
+
+    let comment = &packet.body[fields.comment_start..];
+    comment::decode(packet.destination, comment, &mut position);
+
+    Ok(position)
+}
+
+fn uncompressed_fields(raw: &RawUncompressed) -> Result<PositionFields, ParseError> {
+    let time = parse_time(raw.time)?;
+
+    // the position precision enhancement adds a third decimal to the minutes
+    let enhancement = raw.precision_enhancement.unwrap_or("");
+    let (lat_enhancement, lon_enhancement) = match enhancement.as_bytes() {
+        [lat, lon] => (
+            f64::from(lat.saturating_sub(b'0')),
+            f64::from(lon.saturating_sub(b'0')),
+        ),
+        _ => (0., 0.),
+    };
+
+    let latitude = parse_angle(raw.latitude, 2)? + lat_enhancement / 60000.;
+    if latitude > 90. {
+        return Err(ParseError::InvalidCoordinate);
+    }
+
+    let longitude = parse_angle(raw.longitude, 3)? + lon_enhancement / 60000.;
+    if longitude > 180. {
+        return Err(ParseError::InvalidCoordinate);
+    }
+
+    let course = raw.course.and_then(|it| it.parse::<i32>().ok());
+
+    let speed = raw
+        .speed
+        .and_then(|it| it.parse::<f64>().ok())
+        .map(|it| it.knots_to_kilometers_per_hour());
+
+    let altitude = raw.altitude.map(parse_altitude).transpose()?;
+
+    Ok(PositionFields {
+        time,
+        latitude: if raw.latitude_sign == b'S' {
+            -latitude
+        } else {
+            latitude
+        },
+        longitude: if raw.longitude_sign == b'W' {
+            -longitude
+        } else {
+            longitude
+        },
+        course,
+        speed,
+        altitude,
+        comment_start: raw.end,
+    })
+}
+
+fn compressed_fields(raw: &RawCompressed) -> Result<PositionFields, ParseError> {
+    let time = parse_time(raw.time)?;
+
+    let latitude = 90. - decode_base91(raw.latitude) / 380_926.;
+    let longitude = -180. + decode_base91(raw.longitude) / 190_463.;
+    if latitude.abs() > 90. || longitude.abs() > 180. {
+        return Err(ParseError::InvalidCoordinate);
+    }
+
+    let mut course = None;
+    let mut speed = None;
+    let mut altitude = raw.altitude.map(parse_altitude).transpose()?;
+
+    // a space in the `cs` bytes means that neither course/speed nor
+    // altitude are included
+    if let ([c @ b'!'..=b'{', s @ b'!'..=b'{'], t @ b'!'..=b'{') =
+        (raw.cs.as_bytes(), raw.compression_type)
+    {
+        let c = i32::from(c - 33);
+        let s = i32::from(s - 33);
+
+        // bits 3 and 4 of the compression type contain the NMEA source,
+        // `GGA` sentences carry the altitude instead of course and speed
+        if ((t - 33) >> 3) & 0b11 == 0b10 {
+            altitude = Some(1.002_f64.powi(c * 91 + s).feet_to_meter());
+        } else if c <= 89 {
+            course = Some(c * 4);
+            speed = Some((1.08_f64.powi(s) - 1.).knots_to_kilometers_per_hour());
+        }
+    }
+
+    Ok(PositionFields {
+        time,
+        latitude,
+        longitude,
+        course,
+        speed,
+        altitude,
+        comment_start: raw.end,
+    })
+}
+
+/// Parses a `HHMMSS` timestamp
+pub(super) fn parse_time(hhmmss: &str) -> Result<NaiveTime, ParseError> {
+    let digits = hhmmss.as_bytes();
+    if digits.len() != 6 || !digits.iter().all(u8::is_ascii_digit) {
+        return Err(ParseError::InvalidTime);
+    }
+
+    let value =
+        |index: usize| u32::from(digits[index] - b'0') * 10 + u32::from(digits[index + 1] - b'0');
+    NaiveTime::from_hms_opt(value(0), value(2), value(4)).ok_or(ParseError::InvalidTime)
+}
+
+/// Parses a `DDMM.mm` or `DDDMM.mm` angle into degrees.
+pub(super) fn parse_angle(raw_angle: &str, degree_digits: usize) -> Result<f64, ParseError> {
+    let degrees = raw_angle
+        .get(..degree_digits)
+        .and_then(|it| it.parse::<f64>().ok())
+        .ok_or(ParseError::InvalidCoordinate)?;
+
+    let minutes = raw_angle
+        .get(degree_digits..)
+        .and_then(|it| it.parse::<f64>().ok())
+        .filter(|it| *it < 60.)
+        .ok_or(ParseError::InvalidCoordinate)?;
+
+    Ok(degrees + minutes / 60.)
+}
+
+/// Parses an altitude in feet into meters.
+fn parse_altitude(feet: &str) -> Result<f64, ParseError> {
+    feet.parse::<f64>()
+        .map(|it| it.feet_to_meter())
+        .map_err(|_| ParseError::InvalidAltitude)
+}
+
+fn decode_base91(text: &str) -> f64 {
+    text.bytes().fold(0., |value, byte| {
+        value * 91. + f64::from(byte.saturating_sub(33))
+    })
+}
@@ -0,0 +1,226 @@
+use chrono::NaiveTime;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::ogn::aprs::position::{parse_angle, parse_time};
+use crate::ogn::aprs::{APRSPacket, ParseError};
+use crate::units::FeetToMeter;
+
+#[derive(Debug)]
+pub struct ReceiverPosition<'a> {
+    pub name: &'a str,
+    pub time: NaiveTime,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: f64,
+}
+
+#[derive(Debug)]
+pub struct ReceiverStatus<'a> {
+    pub name: &'a str,
+    pub time: NaiveTime,
+    /// software version (e.g. `0.2.7`)
+    pub version: Option<&'a str>,
+    /// hardware platform (e.g. `RPI-GPU`)
+    pub platform: Option<&'a str>,
+    pub cpu_load: Option<f64>,
+    /// free RAM in MB
+    pub ram_free: Option<f64>,
+    /// total RAM in MB
+    pub ram_total: Option<f64>,
+    /// NTP offset in milliseconds
+    pub ntp_offset: Option<f64>,
+    /// NTP frequency correction in ppm
+    pub ntp_correction: Option<f64>,
+    /// CPU temperature in degrees Celsius
+    pub cpu_temperature: Option<f64>,
+}
+
+pub fn parse_position<'a>(packet: &APRSPacket<'a>) -> Result<ReceiverPosition<'a>, ParseError> {
+    // Examples:
+    // Koenigsdf>APRS,TCPIP*,qAC,GLIDERN1:/132052h4807.51NI01132.48E&/A=001811
+    // LFNW>OGNSDR,TCPIP*,qAC,GLIDERN5:/151958h4346.70NI00119.82E&/A=000298
+
+    lazy_static! {
+        static ref RE: Regex = Regex::new(
+            r#"(?x)
+            ^/                         # position report indicator
+            (?P<time>\d{6})h           # time in HHMMSS incl. `h` indicator
+            (?P<lat>\d{4}\.\d{2})      # latitude angle in DDMM.mm
+            (?P<lat_sign>[NS])         # latitude hemisphere
+            .                          # symbol table identifier
+            (?P<lon>\d{5}\.\d{2})      # longitude angle in DDDMM.mm
+            (?P<lon_sign>[WE])         # longitude hemisphere
+            .                          # symbol code
+            /A=(?P<alt>-?\d{5,6})      # altitude in feet (converted to meters)
+        "#
+        )
+        .unwrap();
+    }
+
+    let caps = RE.captures(packet.body).ok_or(ParseError::InvalidBeacon)?;
+    let group = |name| caps.name(name).map(|it| it.as_str()).unwrap_or("");
+
+    let name = packet.source;
+    let time = parse_time(group("time"))?;
+
+    let latitude = parse_angle(group("lat"), 2)?;
+    let longitude = parse_angle(group("lon"), 3)?;
+    if latitude > 90. || longitude > 180. {
+        return Err(ParseError::InvalidCoordinate);
+    }
+
+    let latitude = if group("lat_sign") == "N" {
+        latitude
+    } else {
+        -latitude
+    };
+
+    let longitude = if group("lon_sign") == "E" {
+        longitude
+    } else {
+        -longitude
+    };
+
+    let altitude = group("alt")
+        .parse::<f64>()
+        .map_err(|_| ParseError::InvalidAltitude)?
+        .feet_to_meter();
+
+    Ok(ReceiverPosition {
+        name,
+        time,
+        latitude,
+        longitude,
+        altitude,
+    })
+}
+
+pub fn parse_status<'a>(packet: &APRSPacket<'a>) -> Result<ReceiverStatus<'a>, ParseError> {
+    // Examples:
+    // Koenigsdf>APRS,TCPIP*,qAC,GLIDERN1:>132052h v0.2.7.RPI-GPU CPU:0.7 RAM:770.2/968.2MB NTP:1.8ms/-3.3ppm +55.7C RF:+54-1.1ppm/-0.16dB
+    // LFNW>OGNSDR,TCPIP*,qAC,GLIDERN5:>151958h v0.2.7.arm CPU:0.3 RAM:221.7/458.9MB NTP:0.4ms/-6.2ppm +47.2C
+
+    lazy_static! {
+        static ref RE: Regex = Regex::new(
+            r#"(?x)
+            ^>                         # status report indicator
+            (?P<time>\d{6})h           # time in HHMMSS incl. `h` indicator
+            (?P<comment>.*)$           # status comment
+        "#
+        )
+        .unwrap();
+    }
+
+    let caps = RE.captures(packet.body).ok_or(ParseError::InvalidBeacon)?;
+    let group = |name| caps.name(name).map(|it| it.as_str()).unwrap_or("");
+
+    let name = packet.source;
+    let time = parse_time(group("time"))?;
+
+    let mut status = ReceiverStatus {
+        name,
+        time,
+        version: None,
+        platform: None,
+        cpu_load: None,
+        ram_free: None,
+        ram_total: None,
+        ntp_offset: None,
+        ntp_correction: None,
+        cpu_temperature: None,
+    };
+
+    for token in group("comment").split_whitespace() {
+        if let Some(cpu) = token.strip_prefix("CPU:") {
+            status.cpu_load = cpu.parse().ok();
+        } else if let Some(ram) = token.strip_prefix("RAM:") {
+            let mut parts = ram.trim_end_matches("MB").splitn(2, '/');
+            status.ram_free = parts.next().and_then(|it| it.parse().ok());
+            status.ram_total = parts.next().and_then(|it| it.parse().ok());
+        } else if let Some(ntp) = token.strip_prefix("NTP:") {
+            let mut parts = ntp.splitn(2, '/');
+            status.ntp_offset = parts
+                .next()
+                .and_then(|it| it.trim_end_matches("ms").parse().ok());
+            status.ntp_correction = parts
+                .next()
+                .and_then(|it| it.trim_end_matches("ppm").parse().ok());
+        } else if let Some(Ok(temperature)) = token.strip_suffix('C').map(str::parse) {
+            status.cpu_temperature = Some(temperature);
+        } else if let Some(version) = token.strip_prefix('v') {
+            if version.starts_with(|c: char| c.is_ascii_digit()) {
+                // the platform is appended after the third `.` (e.g. `v0.2.7.RPI-GPU`)
+                match version.match_indices('.').nth(2) {
+                    Some((index, _)) => {
+                        status.version = Some(&version[..index]);
+                        status.platform = Some(&version[index + 1..]).filter(|it| !it.is_empty());
+                    }
+                    None => status.version = Some(version),
+                }
+            }
+        }
+    }
+
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_position, parse_status};
+    use crate::ogn::aprs::APRSPacket;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_parse_position() {
+        let packet = APRSPacket::parse(
+            r"Koenigsdf>APRS,TCPIP*,qAC,GLIDERN1:/132052h4807.51NI01132.48E&/A=001811",
+        )
+        .unwrap();
+        let result = parse_position(&packet);
+        assert!(result.is_ok());
+
+        let position = result.unwrap();
+        assert_eq!(position.name, "Koenigsdf");
+        assert_eq!(position.time, "13:20:52".parse().unwrap());
+        assert_relative_eq!(position.latitude, 48. + 7.51 / 60.);
+        assert_relative_eq!(position.longitude, 11. + 32.48 / 60.);
+        assert_relative_eq!(position.altitude, 551.9928);
+    }
+
+    #[test]
+    fn test_parse_status() {
+        let packet = APRSPacket::parse(
+            r"Koenigsdf>APRS,TCPIP*,qAC,GLIDERN1:>132052h v0.2.7.RPI-GPU CPU:0.7 RAM:770.2/968.2MB NTP:1.8ms/-3.3ppm +55.7C RF:+54-1.1ppm/-0.16dB",
+        )
+        .unwrap();
+        let result = parse_status(&packet);
+        assert!(result.is_ok());
+
+        let status = result.unwrap();
+        assert_eq!(status.name, "Koenigsdf");
+        assert_eq!(status.time, "13:20:52".parse().unwrap());
+        assert_eq!(status.version, Some("0.2.7"));
+        assert_eq!(status.platform, Some("RPI-GPU"));
+        assert_relative_eq!(status.cpu_load.unwrap(), 0.7);
+        assert_relative_eq!(status.ram_free.unwrap(), 770.2);
+        assert_relative_eq!(status.ram_total.unwrap(), 968.2);
+        assert_relative_eq!(status.ntp_offset.unwrap(), 1.8);
+        assert_relative_eq!(status.ntp_correction.unwrap(), -3.3);
+        assert_relative_eq!(status.cpu_temperature.unwrap(), 55.7);
+    }
+
+    #[test]
+    fn test_parse_status_minimal() {
+        let packet = APRSPacket::parse(r"LFNW>OGNSDR,TCPIP*,qAC,GLIDERN5:>151958h v0.2.8").unwrap();
+        let result = parse_status(&packet);
+        assert!(result.is_ok());
+
+        let status = result.unwrap();
+        assert_eq!(status.name, "LFNW");
+        assert_eq!(status.version, Some("0.2.8"));
+        assert_eq!(status.platform, None);
+        assert_eq!(status.cpu_load, None);
+        assert_eq!(status.ram_total, None);
+    }
+}
@@ -0,0 +1,316 @@
+//! Hand-written scanner for the body of APRS position reports.
+//!
+//! The scanner only splits the body into its raw fields without converting
+//! them, which is left to the `position` module. It never allocates and
+//! borrows all fields from the input.
+
+/// Raw fields of a position report body
+#[derive(Debug, PartialEq)]
+pub enum RawPosition<'a> {
+    Uncompressed(RawUncompressed<'a>),
+    Compressed(RawCompressed<'a>),
+}
+
+/// Raw fields of an uncompressed position report
+/// (e.g. `/141956h4911.18N/00815.93E'126/059/A=003716 !W75!`)
+#[derive(Debug, PartialEq)]
+pub struct RawUncompressed<'a> {
+    /// `HHMMSS`
+    pub time: &'a str,
+    /// `DDMM.mm` with two to four decimals
+    pub latitude: &'a str,
+    /// `N` or `S`
+    pub latitude_sign: u8,
+    /// `DDDMM.mm` with two to four decimals
+    pub longitude: &'a str,
+    /// `E` or `W`
+    pub longitude_sign: u8,
+    /// `DDD` in degrees
+    pub course: Option<&'a str>,
+    /// `SSS` in knots
+    pub speed: Option<&'a str>,
+    /// `-?\d{5,6}` in feet
+    pub altitude: Option<&'a str>,
+    /// two digits of the `!Wab!` position precision enhancement
+    pub precision_enhancement: Option<&'a str>,
+    /// byte offset of the comment in the body
+    pub end: usize,
+}
+
+/// Raw fields of a compressed position report (e.g. `/183727h/5L!!<*e7>7P[`)
+#[derive(Debug, PartialEq)]
+pub struct RawCompressed<'a> {
+    /// `HHMMSS`
+    pub time: &'a str,
+    /// four base-91 digits
+    pub latitude: &'a str,
+    /// four base-91 digits
+    pub longitude: &'a str,
+    /// course/speed, radio range or altitude bytes
+    pub cs: &'a str,
+    /// compression type byte
+    pub compression_type: u8,
+    /// `-?\d{5,6}` in feet
+    pub altitude: Option<&'a str>,
+    /// byte offset of the comment in the body
+    pub end: usize,
+}
+
+/// Splits a position report `body` into its raw fields, or returns `None` if
+/// it is not a position report in any of the supported formats.
+pub fn scan(body: &str) -> Option<RawPosition<'_>> {
+    let mut scanner = Scanner::new(body);
+
+    scanner.byte_if(|it| it == b'/' || it == b'@')?;
+    let time = scanner.digits(6)?;
+    scanner.byte_if(|it| it == b'h')?;
+
+    if scanner.peek()?.is_ascii_digit() {
+        scan_uncompressed(scanner, time).map(RawPosition::Uncompressed)
+    } else {
+        scan_compressed(scanner, time).map(RawPosition::Compressed)
+    }
+}
+
+fn scan_uncompressed<'a>(mut scanner: Scanner<'a>, time: &'a str) -> Option<RawUncompressed<'a>> {
+    let latitude = scanner.angle(4)?;
+    let latitude_sign = scanner.byte_if(|it| it == b'N' || it == b'S')?;
+    scanner.char()?; // symbol table identifier
+    let longitude = scanner.angle(5)?;
+    let longitude_sign = scanner.byte_if(|it| it == b'E' || it == b'W')?;
+    scanner.char()?; // symbol code
+
+    let (course, speed) = scanner
+        .attempt(|scanner| {
+            let course = scanner.digits(3)?;
+            scanner.byte_if(|it| it == b'/')?;
+            let speed = scanner.digits(3)?;
+            Some((course, speed))
+        })
+        .map_or((None, None), |(course, speed)| (Some(course), Some(speed)));
+
+    let altitude = scanner.attempt(Scanner::altitude);
+
+    let precision_enhancement = scanner.attempt(|scanner| {
+        scanner.tag(" !W")?;
+        let enhancement = scanner.digits(2)?;
+        scanner.byte_if(|it| it == b'!')?;
+        Some(enhancement)
+    });
+
+    Some(RawUncompressed {
+        time,
+        latitude,
+        latitude_sign,
+        longitude,
+        longitude_sign,
+        course,
+        speed,
+        altitude,
+        precision_enhancement,
+        end: scanner.position,
+    })
+}
+
+fn scan_compressed<'a>(mut scanner: Scanner<'a>, time: &'a str) -> Option<RawCompressed<'a>> {
+    // symbol table identifier or overlay
+    scanner.byte_if(|it| matches!(it, b'/' | b'\\' | b'A'..=b'Z' | b'a'..=b'j'))?;
+
+    let latitude = scanner.bytes_if(4, is_base91)?;
+    let longitude = scanner.bytes_if(4, is_base91)?;
+    scanner.char()?; // symbol code
+    let cs = scanner.bytes_if(2, |it| (b' '..=b'{').contains(&it))?;
+    let compression_type = scanner.byte_if(|it| (b' '..=b'{').contains(&it))?;
+
+    let altitude = scanner.attempt(Scanner::altitude);
+
+    Some(RawCompressed {
+        time,
+        latitude,
+        longitude,
+        cs,
+        compression_type,
+        altitude,
+        end: scanner.position,
+    })
+}
+
+fn is_base91(byte: u8) -> bool {
+    (b'!'..=b'{').contains(&byte)
+}
+
+/// Cursor over the bytes of a packet body
+#[derive(Clone, Copy)]
+struct Scanner<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(text: &'a str) -> Scanner<'a> {
+        Scanner { text, position: 0 }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.position).copied()
+    }
+
+    /// Runs `f` and only advances the scanner if it returns `Some`
+    fn attempt<T>(&mut self, f: impl FnOnce(&mut Scanner<'a>) -> Option<T>) -> Option<T> {
+        let mut scanner = *self;
+        let result = f(&mut scanner)?;
+        *self = scanner;
+        Some(result)
+    }
+
+    /// Consumes a single byte if it matches the `predicate`
+    fn byte_if(&mut self, predicate: impl Fn(u8) -> bool) -> Option<u8> {
+        let byte = self.peek().filter(|it| predicate(*it))?;
+        self.position += 1;
+        Some(byte)
+    }
+
+    /// Consumes `count` bytes if all of them match the `predicate`
+    fn bytes_if(&mut self, count: usize, predicate: impl Fn(u8) -> bool) -> Option<&'a str> {
+        let end = self.position + count;
+        let bytes = self.text.as_bytes().get(self.position..end)?;
+        if !bytes.iter().all(|it| predicate(*it)) {
+            return None;
+        }
+
+        // only ASCII bytes match the predicates, so `end` is a char boundary
+        let slice = self.text.get(self.position..end)?;
+        self.position = end;
+        Some(slice)
+    }
+
+    /// Consumes a single (possibly multi-byte) character except for newlines
+    fn char(&mut self) -> Option<char> {
+        let character = self.text.get(self.position..)?.chars().next()?;
+        if character == '\n' {
+            return None;
+        }
+
+        self.position += character.len_utf8();
+        Some(character)
+    }
+
+    fn tag(&mut self, tag: &str) -> Option<()> {
+        let rest = self.text.as_bytes().get(self.position..)?;
+        if !rest.starts_with(tag.as_bytes()) {
+            return None;
+        }
+
+        self.position += tag.len();
+        Some(())
+    }
+
+    fn digits(&mut self, count: usize) -> Option<&'a str> {
+        self.bytes_if(count, |it| it.is_ascii_digit())
+    }
+
+    /// Consumes up to `max` digits, but at least `min`
+    fn digits_between(&mut self, min: usize, max: usize) -> Option<&'a str> {
+        let count = self
+            .text
+            .as_bytes()
+            .iter()
+            .skip(self.position)
+            .take(max)
+            .take_while(|it| it.is_ascii_digit())
+            .count();
+
+        if count < min {
+            return None;
+        }
+
+        self.digits(count)
+    }
+
+    /// Consumes an angle with `integer_digits` digits, a `.` and two to four
+    /// decimals (e.g. `4911.18`)
+    fn angle(&mut self, integer_digits: usize) -> Option<&'a str> {
+        let start = self.position;
+        self.digits(integer_digits)?;
+        self.byte_if(|it| it == b'.')?;
+        self.digits_between(2, 4)?;
+        self.text.get(start..self.position)
+    }
+
+    /// Consumes an `/A=` altitude in feet (e.g. `/A=003716` or `/A=-00123`)
+    fn altitude(&mut self) -> Option<&'a str> {
+        self.tag("/A=")?;
+        let start = self.position;
+        self.byte_if(|it| it == b'-');
+        self.digits_between(5, 6)?;
+        self.text.get(start..self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_uncompressed() {
+        let body = r"/141956h4911.18N/00815.93E'126/059/A=003716 !W75! id06DD9612 -355fpm";
+        assert_eq!(
+            scan(body),
+            Some(RawPosition::Uncompressed(RawUncompressed {
+                time: "141956",
+                latitude: "4911.18",
+                latitude_sign: b'N',
+                longitude: "00815.93",
+                longitude_sign: b'E',
+                course: Some("126"),
+                speed: Some("059"),
+                altitude: Some("003716"),
+                precision_enhancement: Some("75"),
+                end: 49,
+            }))
+        );
+
+        let body = r"/093021h4729.2901N/01907.612E'/A=-00012 id073E2A11";
+        assert_eq!(
+            scan(body),
+            Some(RawPosition::Uncompressed(RawUncompressed {
+                time: "093021",
+                latitude: "4729.2901",
+                latitude_sign: b'N',
+                longitude: "01907.612",
+                longitude_sign: b'E',
+                course: None,
+                speed: None,
+                altitude: Some("-00012"),
+                precision_enhancement: None,
+                end: 39,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_scan_compressed() {
+        assert_eq!(
+            scan(r"/183727h/5L!!<*e7>7P[/A=001042 id1E1103CE"),
+            Some(RawPosition::Compressed(RawCompressed {
+                time: "183727",
+                latitude: "5L!!",
+                longitude: "<*e7",
+                cs: "7P",
+                compression_type: b'[',
+                altitude: Some("001042"),
+                end: 30,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_scan_invalid() {
+        assert_eq!(scan(""), None);
+        assert_eq!(scan(r">141956h v0.2.7"), None);
+        assert_eq!(scan(r"/14195h4911.18N/00815.93E'"), None);
+        assert_eq!(scan(r"/141956h4911.1N/00815.93E'"), None);
+        assert_eq!(scan(r"/141956h4911.18N/00815.93E"), None);
+        assert_eq!(scan(r"/141956h/5L!!<*e7>7P"), None);
+    }
+}
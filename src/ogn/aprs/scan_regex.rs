@@ -0,0 +1,105 @@
+//! Regex-based scanner for the body of APRS position reports.
+//!
+//! This is the original implementation of the position parser, which is kept
+//! as a reference for the hand-written scanner in the `scan` module. It is
+//! only used by the tests, the benchmarks and the fuzz targets.
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+use crate::ogn::aprs::scan::{RawCompressed, RawPosition, RawUncompressed};
+
+/// Same as `scan::scan()`, but using regular expressions
+pub fn scan(body: &str) -> Option<RawPosition<'_>> {
+    scan_uncompressed(body)
+        .map(RawPosition::Uncompressed)
+        .or_else(|| scan_compressed(body).map(RawPosition::Compressed))
+}
+
+fn scan_uncompressed(body: &str) -> Option<RawUncompressed<'_>> {
+    // Examples:
+    // /141956h4911.18N/00815.93E'126/059/A=003716 !W75! id06DD9612 -355fpm -1.2rot 3.0dB 2e -1.3kHz gps3x3
+    // /141953h5147.03N\00109.00W^210/143/A=003405 !W50! id214060D7 +079fpm +0.0rot 8.0dB 0e -11.9kHz gps3x4
+    // /093021h4729.29N/01907.61E'/A=-00012 !W61! id073E2A11 +000fpm
+
+    lazy_static! {
+        static ref RE: Regex = Regex::new(
+            r#"(?x)
+            ^[/@]                      # position report indicator
+            (?P<time>\d{6})h           # time in HHMMSS incl. `h` indicator
+            (?P<lat>\d{4}\.\d{2,4})    # latitude angle in DDMM.mm (up to DDMM.mmmm)
+            (?P<lat_sign>[NS])         # latitude hemisphere
+            .                          # symbol table identifier
+            (?P<lon>\d{5}\.\d{2,4})    # longitude angle in DDDMM.mm (up to DDDMM.mmmm)
+            (?P<lon_sign>[WE])         # longitude hemisphere
+            .                          # symbol code
+            (?:
+              (?P<course>\d{3})        # course angle in DDD
+              /                        # separator
+              (?P<speed>\d{3})         # speed in knots
+            )?
+            (?:
+              /A=(?P<alt>-?\d{5,6})    # altitude in feet (converted to meters)
+            )?
+            (?:\x20!W(?P<ppe>\d\d)!)?  # position precision enhancement
+        "#
+        )
+        .unwrap();
+    }
+
+    let caps = RE.captures(body)?;
+
+    Some(RawUncompressed {
+        time: group(&caps, "time")?,
+        latitude: group(&caps, "lat")?,
+        latitude_sign: *group(&caps, "lat_sign")?.as_bytes().first()?,
+        longitude: group(&caps, "lon")?,
+        longitude_sign: *group(&caps, "lon_sign")?.as_bytes().first()?,
+        course: group(&caps, "course"),
+        speed: group(&caps, "speed"),
+        altitude: group(&caps, "alt"),
+        precision_enhancement: group(&caps, "ppe"),
+        end: caps.get(0)?.end(),
+    })
+}
+
+fn scan_compressed(body: &str) -> Option<RawCompressed<'_>> {
+    // Examples:
+    // /183727h/5L!!<*e7>7P[ id1E1103CE +03fpm
+    // /074548h/5L!!<*e7OS]S
+
+    lazy_static! {
+        static ref RE: Regex = Regex::new(
+            r#"(?x)
+            ^[/@]                      # position report indicator
+            (?P<time>\d{6})h           # time in HHMMSS incl. `h` indicator
+            [/\\A-Za-j]                # symbol table identifier or overlay
+            (?P<lat>[!-{]{4})          # base-91 encoded latitude
+            (?P<lon>[!-{]{4})          # base-91 encoded longitude
+            .                          # symbol code
+            (?P<cs>[\x20-{]{2})        # course/speed, radio range or altitude
+            (?P<type>[\x20-{])         # compression type
+            (?:
+              /A=(?P<alt>-?\d{5,6})    # altitude in feet (converted to meters)
+            )?
+        "#
+        )
+        .unwrap();
+    }
+
+    let caps = RE.captures(body)?;
+
+    Some(RawCompressed {
+        time: group(&caps, "time")?,
+        latitude: group(&caps, "lat")?,
+        longitude: group(&caps, "lon")?,
+        cs: group(&caps, "cs")?,
+        compression_type: *group(&caps, "type")?.as_bytes().first()?,
+        altitude: group(&caps, "alt"),
+        end: caps.get(0)?.end(),
+    })
+}
+
+fn group<'a>(caps: &Captures<'a>, name: &str) -> Option<&'a str> {
+    caps.name(name).map(|it| it.as_str())
+}
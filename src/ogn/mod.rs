@@ -1,4 +1,12 @@
+use std::fmt::Display;
+
 pub mod aprs;
 mod time;
 
 pub use crate::ogn::time::time_to_datetime;
+
+/// Formats an optional value for the `|`-separated record formats,
+/// using an empty string if the value is missing.
+pub fn optional<T: Display>(value: Option<T>) -> String {
+    value.map(|it| it.to_string()).unwrap_or_default()
+}
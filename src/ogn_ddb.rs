@@ -38,7 +38,6 @@ struct OGNDDBRecord {
     registration: String,
     cn: String,
     tracked: String,
-    identified: String,
 }
 
 impl OGNDDBRecord {
@@ -54,8 +53,8 @@ impl OGNDDBRecord {
     }
 }
 
-#[derive(Serialize)]
-struct DeviceInfo {
+#[derive(Serialize, Deserialize)]
+pub struct DeviceInfo {
     pub model: Option<String>,
     pub registration: Option<String>,
     pub callsign: Option<String>,
@@ -0,0 +1,835 @@
+//! Encodings of the live position updates that are sent to the websocket
+//! clients. See `docs/websocket.md` for the details.
+
+use std::collections::BTreeMap;
+
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::area_index::AreaSubscription;
+use crate::filter::{AircraftCategory, PositionFilter};
+use crate::geo::{Area, BoundingBox, Circle, Polygon};
+use crate::ogn::aprs::{APRSPosition, GPSQuality};
+use crate::ogn::optional;
+
+/// WebSocket subprotocol name of the binary protocol
+pub const BINARY_SUBPROTOCOL: &str = "ogn-binary.v1";
+
+/// WebSocket subprotocol name of the JSON protocol
+pub const JSON_SUBPROTOCOL: &str = "ogn-json.v1";
+
+/// Maximum length of the name of an area subscription
+const MAX_AREA_NAME_LENGTH: usize = 64;
+
+/// Version byte at the start of every binary frame
+const BINARY_VERSION: u8 = 1;
+
+const HAS_CLIMB_RATE: u8 = 1 << 0;
+const HAS_TURN_RATE: u8 = 1 << 1;
+const HAS_SIGNAL_QUALITY: u8 = 1 << 2;
+const HAS_ERROR_COUNT: u8 = 1 << 3;
+const HAS_FREQUENCY_OFFSET: u8 = 1 << 4;
+const HAS_GPS_QUALITY: u8 = 1 << 5;
+const HAS_AIRCRAFT_TYPE: u8 = 1 << 6;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    /// pipe-delimited records, one per line
+    #[serde(rename = "text")]
+    Text,
+    /// compact binary frames, see `encode_binary()`
+    #[serde(rename = "binary.v1")]
+    BinaryV1,
+    /// typed JSON requests and responses, see `Request` and `Response`
+    #[serde(rename = "json.v1")]
+    JsonV1,
+}
+
+impl Protocol {
+    /// Returns the WebSocket subprotocol names that select this protocol.
+    pub fn subprotocols(self) -> &'static [&'static str] {
+        match self {
+            Protocol::Text => &[],
+            Protocol::BinaryV1 => &[BINARY_SUBPROTOCOL],
+            Protocol::JsonV1 => &[JSON_SUBPROTOCOL],
+        }
+    }
+}
+
+/// Request of a client using the JSON protocol
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct Request {
+    /// arbitrary value chosen by the client, which is copied into the
+    /// response to this request
+    #[serde(default)]
+    pub id: Value,
+    #[serde(flatten)]
+    pub command: Command,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Command {
+    Subscribe {
+        aircraft: String,
+    },
+    Unsubscribe {
+        aircraft: String,
+    },
+    /// west, south, east and north side of the default area in degrees
+    Bbox {
+        bbox: [f64; 4],
+        #[serde(default)]
+        filter: FilterSpec,
+    },
+    AddArea {
+        name: String,
+        area: AreaSpec,
+        #[serde(default)]
+        filter: FilterSpec,
+    },
+    RemoveArea {
+        name: String,
+    },
+    Networks {
+        networks: Vec<String>,
+    },
+    Subscriptions,
+}
+
+/// Malformed request of a client using the JSON protocol
+#[derive(Debug, PartialEq)]
+pub struct InvalidRequest {
+    /// ID of the request, if it could be found
+    pub id: Value,
+    pub message: String,
+}
+
+impl Request {
+    /// Parses a request, returning the ID and error message for the reply
+    /// to the client if the request is malformed.
+    pub fn parse(text: &str) -> Result<Request, InvalidRequest> {
+        serde_json::from_str(text).map_err(|error| {
+            // try to find the request ID in otherwise invalid requests
+            let id = serde_json::from_str::<Value>(text)
+                .ok()
+                .and_then(|it| it.get("id").cloned())
+                .unwrap_or(Value::Null);
+
+            InvalidRequest {
+                id,
+                message: format!("Invalid request: {}", error),
+            }
+        })
+    }
+}
+
+/// Message sent to a client using the JSON protocol
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Response {
+    /// the request was processed successfully
+    Ack { id: Value },
+    /// the request was rejected
+    Error { id: Value, message: String },
+    Subscriptions {
+        id: Value,
+        aircraft: Vec<String>,
+        areas: BTreeMap<String, AreaSubscriptionSpec>,
+        networks: Vec<&'static str>,
+    },
+    /// positions of the subscribed aircraft, not a response to a request
+    Positions { positions: Vec<Value> },
+}
+
+impl Response {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("responses are always serializable")
+    }
+}
+
+/// Area of a subscription in the JSON protocol
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum AreaSpec {
+    /// west, south, east and north side in degrees
+    Bbox { bbox: [f64; 4] },
+    /// center as `[longitude, latitude]` and radius in meters
+    Circle { center: [f64; 2], radius: f64 },
+    /// corners as `[longitude, latitude]`
+    Polygon { points: Vec<[f64; 2]> },
+}
+
+impl AreaSpec {
+    /// Converts the spec into an `Area`, or returns `None` if it is invalid.
+    pub fn to_area(&self) -> Option<Area> {
+        Some(match self {
+            AreaSpec::Bbox {
+                bbox: [left, bottom, right, top],
+            } => Area::BoundingBox(BoundingBox::new(*left, *bottom, *right, *top)?),
+            AreaSpec::Circle {
+                center: [longitude, latitude],
+                radius,
+            } => Area::Circle(Circle::new(*longitude, *latitude, *radius)?),
+            AreaSpec::Polygon { points } => Area::Polygon(Polygon::new(
+                points.iter().map(|[lon, lat]| (*lon, *lat)).collect(),
+            )?),
+        })
+    }
+}
+
+impl From<&Area> for AreaSpec {
+    fn from(area: &Area) -> Self {
+        match area {
+            Area::BoundingBox(bbox) => AreaSpec::Bbox {
+                bbox: [bbox.left(), bbox.bottom(), bbox.right(), bbox.top()],
+            },
+            Area::Circle(circle) => AreaSpec::Circle {
+                center: [circle.longitude(), circle.latitude()],
+                radius: circle.radius(),
+            },
+            Area::Polygon(polygon) => AreaSpec::Polygon {
+                points: polygon
+                    .points()
+                    .iter()
+                    .map(|(lon, lat)| [*lon, *lat])
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// Restrictions of an area subscription in the JSON protocol. Missing
+/// values are not restricted.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct FilterSpec {
+    /// minimum altitude in meters
+    pub min_altitude: Option<f64>,
+    /// maximum altitude in meters
+    pub max_altitude: Option<f64>,
+    /// minimum ground speed in km/h
+    pub min_speed: Option<f64>,
+    /// allowed aircraft categories, empty if all categories are allowed
+    #[serde(default)]
+    pub categories: Vec<String>,
+}
+
+impl FilterSpec {
+    /// Converts the spec into a `PositionFilter`, or returns the first
+    /// unknown category.
+    pub fn to_filter(&self) -> Result<PositionFilter, String> {
+        let categories = self
+            .categories
+            .iter()
+            .map(|it| it.parse::<AircraftCategory>().map_err(|_| it.clone()))
+            .collect::<Result<_, _>>()?;
+
+        Ok(PositionFilter {
+            min_altitude: self.min_altitude,
+            max_altitude: self.max_altitude,
+            min_speed: self.min_speed,
+            categories,
+        })
+    }
+}
+
+impl From<&PositionFilter> for FilterSpec {
+    fn from(filter: &PositionFilter) -> Self {
+        let mut categories: Vec<String> = filter
+            .categories
+            .iter()
+            .map(|it| it.as_str().to_owned())
+            .collect();
+
+        categories.sort_unstable();
+
+        FilterSpec {
+            min_altitude: filter.min_altitude,
+            max_altitude: filter.max_altitude,
+            min_speed: filter.min_speed,
+            categories,
+        }
+    }
+}
+
+/// Area subscription in the `subscriptions` response of the JSON protocol
+#[derive(Serialize, Debug, PartialEq)]
+pub struct AreaSubscriptionSpec {
+    pub area: AreaSpec,
+    pub filter: FilterSpec,
+}
+
+impl From<&AreaSubscription> for AreaSubscriptionSpec {
+    fn from(subscription: &AreaSubscription) -> Self {
+        AreaSubscriptionSpec {
+            area: AreaSpec::from(&subscription.area),
+            filter: FilterSpec::from(&subscription.filter),
+        }
+    }
+}
+
+/// Splits the optional `|filter|<filter>` suffix off the arguments of the
+/// `bbox|` and `+area|` text commands and parses the filter.
+fn split_text_filter(text: &str) -> Option<(&str, PositionFilter)> {
+    match text.split_once("|filter|") {
+        Some((values, filter)) => Some((values, PositionFilter::try_parse(filter)?)),
+        None => Some((text, PositionFilter::default())),
+    }
+}
+
+/// Parses the `<west>|<south>|<east>|<north>[|filter|<filter>]` arguments
+/// of the `bbox|` text command.
+pub fn parse_text_bbox(text: &str) -> Option<(BoundingBox, PositionFilter)> {
+    let (values, filter) = split_text_filter(text)?;
+    Some((BoundingBox::try_parse(values)?, filter))
+}
+
+/// Parses the `<name>|<kind>|<values>[|filter|<filter>]` arguments of the
+/// `+area|` text command.
+pub fn parse_text_area(text: &str) -> Option<(String, Area, PositionFilter)> {
+    let (text, filter) = split_text_filter(text)?;
+
+    let mut parts = text.splitn(3, '|');
+    let name = parts.next()?;
+    let kind = parts.next()?;
+    let values = parts.next()?;
+
+    if !is_valid_area_name(name) {
+        return None;
+    }
+
+    let parse_numbers = || {
+        values
+            .split('|')
+            .map(|it| it.parse::<f64>().ok())
+            .collect::<Option<Vec<_>>>()
+    };
+
+    let area = match kind {
+        "bbox" => Area::BoundingBox(BoundingBox::try_parse(values)?),
+        "circle" => match parse_numbers()?[..] {
+            [longitude, latitude, radius] => {
+                Area::Circle(Circle::new(longitude, latitude, radius)?)
+            }
+            _ => return None,
+        },
+        "polygon" => {
+            let numbers = parse_numbers()?;
+            if numbers.len() % 2 != 0 {
+                return None;
+            }
+
+            let points = numbers.chunks(2).map(|it| (it[0], it[1])).collect();
+            Area::Polygon(Polygon::new(points)?)
+        }
+        _ => return None,
+    };
+
+    Some((name.to_owned(), area, filter))
+}
+
+pub fn is_valid_area_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_AREA_NAME_LENGTH
+        && !name.chars().any(|c| c == '|' || c.is_control())
+}
+
+/// Checks whether `id` looks like an APRS sender ID (e.g. `FLRDD87AC`).
+pub fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 16
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Position of an aircraft as it is sent to the websocket clients
+#[derive(Debug, Clone, PartialEq)]
+pub struct LivePosition {
+    pub id: String,
+    pub time: DateTime<Utc>,
+    pub longitude: f64,
+    pub latitude: f64,
+    pub course: i32,
+    pub altitude: i32,
+    pub climb_rate: Option<f64>,
+    pub turn_rate: Option<f64>,
+    pub signal_quality: Option<f64>,
+    pub error_count: Option<u8>,
+    pub frequency_offset: Option<f64>,
+    pub gps_quality: Option<GPSQuality>,
+    pub speed: f64,
+    pub aircraft_type: Option<u8>,
+}
+
+impl LivePosition {
+    pub fn new(position: &APRSPosition, time: DateTime<Utc>) -> LivePosition {
+        LivePosition {
+            id: position.id.to_owned(),
+            time,
+            longitude: position.longitude,
+            latitude: position.latitude,
+            course: position.course,
+            altitude: position.altitude as i32,
+            climb_rate: position.climb_rate,
+            turn_rate: position.turn_rate,
+            signal_quality: position.signal_quality,
+            error_count: position.error_count,
+            frequency_offset: position.frequency_offset,
+            gps_quality: position.gps_quality,
+            speed: position.speed,
+            aircraft_type: position.aircraft_id.map(|it| it.aircraft_type as u8),
+        }
+    }
+
+    /// Formats the position as a pipe-delimited text record
+    pub fn to_text(&self) -> String {
+        format!(
+            "{}|{}|{:.6}|{:.6}|{}|{}|{}|{}|{}|{}|{}|{}|{:.0}|{}",
+            self.id,
+            self.time.timestamp(),
+            self.longitude,
+            self.latitude,
+            self.course,
+            self.altitude,
+            optional(self.climb_rate.map(|it| format!("{:.1}", it))),
+            optional(self.turn_rate.map(|it| format!("{:.1}", it))),
+            optional(self.signal_quality.map(|it| format!("{:.1}", it))),
+            optional(self.error_count),
+            optional(self.frequency_offset.map(|it| format!("{:.1}", it))),
+            optional(self.gps_quality),
+            self.speed,
+            optional(self.aircraft_type),
+        )
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "time": self.time.timestamp(),
+            "longitude": self.longitude,
+            "latitude": self.latitude,
+            "course": self.course,
+            "altitude": self.altitude,
+            "climb_rate": self.climb_rate,
+            "turn_rate": self.turn_rate,
+            "signal_quality": self.signal_quality,
+            "error_count": self.error_count,
+            "frequency_offset": self.frequency_offset,
+            "gps_quality": self.gps_quality.map(|it| json!({
+                "horizontal": it.horizontal,
+                "vertical": it.vertical,
+            })),
+            "speed": self.speed,
+            "aircraft_type": self.aircraft_type,
+        })
+    }
+
+    fn write_binary(&self, frame: &mut Vec<u8>) {
+        // sender IDs are short ASCII strings, but the length has to fit into a byte
+        let id = &self.id.as_bytes()[..self.id.len().min(u8::MAX as usize)];
+        frame.push(id.len() as u8);
+        frame.extend_from_slice(id);
+
+        frame.extend_from_slice(&(self.time.timestamp() as u32).to_le_bytes());
+        frame.extend_from_slice(&(self.longitude as f32).to_le_bytes());
+        frame.extend_from_slice(&(self.latitude as f32).to_le_bytes());
+        frame.extend_from_slice(&(self.course as u16).to_le_bytes());
+        frame.extend_from_slice(&self.altitude.to_le_bytes());
+        frame.extend_from_slice(&(self.speed.round() as u16).to_le_bytes());
+
+        let mut flags = 0;
+        let flags_index = frame.len();
+        frame.push(flags);
+
+        if let Some(climb_rate) = self.climb_rate {
+            flags |= HAS_CLIMB_RATE;
+            frame.extend_from_slice(&tenths(climb_rate).to_le_bytes());
+        }
+        if let Some(turn_rate) = self.turn_rate {
+            flags |= HAS_TURN_RATE;
+            frame.extend_from_slice(&tenths(turn_rate).to_le_bytes());
+        }
+        if let Some(signal_quality) = self.signal_quality {
+            flags |= HAS_SIGNAL_QUALITY;
+            frame.extend_from_slice(&tenths(signal_quality).to_le_bytes());
+        }
+        if let Some(error_count) = self.error_count {
+            flags |= HAS_ERROR_COUNT;
+            frame.push(error_count);
+        }
+        if let Some(frequency_offset) = self.frequency_offset {
+            flags |= HAS_FREQUENCY_OFFSET;
+            frame.extend_from_slice(&tenths(frequency_offset).to_le_bytes());
+        }
+        if let Some(gps_quality) = self.gps_quality {
+            flags |= HAS_GPS_QUALITY;
+            frame.push(gps_quality.horizontal);
+            frame.push(gps_quality.vertical);
+        }
+        if let Some(aircraft_type) = self.aircraft_type {
+            flags |= HAS_AIRCRAFT_TYPE;
+            frame.push(aircraft_type);
+        }
+
+        frame[flags_index] = flags;
+    }
+}
+
+/// Converts a value to tenths of its unit, saturating at the `i16` bounds.
+fn tenths(value: f64) -> i16 {
+    (value * 10.).round() as i16
+}
+
+/// Encodes the positions as text frames with one record per line.
+pub fn encode_text<'a, I>(positions: I) -> String
+where
+    I: IntoIterator<Item = &'a LivePosition>,
+{
+    let mut text = String::new();
+    for position in positions {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text += &position.to_text();
+    }
+    text
+}
+
+/// Encodes the positions as a JSON `positions` message.
+pub fn encode_json<'a, I>(positions: I) -> String
+where
+    I: IntoIterator<Item = &'a LivePosition>,
+{
+    Response::Positions {
+        positions: positions.into_iter().map(LivePosition::to_json).collect(),
+    }
+    .to_json()
+}
+
+/// Encodes the positions as binary frames.
+///
+/// Each frame holds at most `u16::MAX` positions, so more than one frame
+/// is returned for very large batches.
+pub fn encode_binary(positions: &[&LivePosition]) -> Vec<Vec<u8>> {
+    positions
+        .chunks(u16::MAX as usize)
+        .map(|chunk| {
+            let mut frame = Vec::with_capacity(3 + chunk.len() * 40);
+            frame.push(BINARY_VERSION);
+            frame.extend_from_slice(&(chunk.len() as u16).to_le_bytes());
+            for position in chunk {
+                position.write_binary(&mut frame);
+            }
+            frame
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position() -> LivePosition {
+        LivePosition {
+            id: "FLRC04EFE".to_owned(),
+            time: Utc.with_ymd_and_hms(2018, 7, 14, 21, 51, 42).unwrap(),
+            longitude: -75.117_233,
+            latitude: 45.4939,
+            course: 16,
+            altitude: 743,
+            climb_rate: Some(-1.8),
+            turn_rate: Some(-3.6),
+            signal_quality: Some(3.0),
+            error_count: Some(2),
+            frequency_offset: Some(-1.3),
+            gps_quality: Some(GPSQuality {
+                horizontal: 3,
+                vertical: 3,
+            }),
+            speed: 109.,
+            aircraft_type: Some(1),
+        }
+    }
+
+    fn bare_position() -> LivePosition {
+        LivePosition {
+            climb_rate: None,
+            turn_rate: None,
+            signal_quality: None,
+            error_count: None,
+            frequency_offset: None,
+            gps_quality: None,
+            aircraft_type: None,
+            ..position()
+        }
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(
+            encode_text(vec![&position(), &bare_position()]),
+            "FLRC04EFE|1531605102|-75.117233|45.493900|16|743|-1.8|-3.6|3.0|2|-1.3|3x3|109|1\n\
+             FLRC04EFE|1531605102|-75.117233|45.493900|16|743|||||||109|"
+        );
+    }
+
+    #[test]
+    fn test_binary() {
+        let frames = encode_binary(&[&position(), &bare_position()]);
+        assert_eq!(frames.len(), 1);
+
+        let frame = &frames[0];
+        assert_eq!(frame[..3], [1u8, 2, 0]);
+
+        let mut expected = vec![9];
+        expected.extend_from_slice(b"FLRC04EFE");
+        expected.extend_from_slice(&1_531_605_102u32.to_le_bytes());
+        expected.extend_from_slice(&(-75.117_233f64 as f32).to_le_bytes());
+        expected.extend_from_slice(&(45.4939f64 as f32).to_le_bytes());
+        expected.extend_from_slice(&16u16.to_le_bytes());
+        expected.extend_from_slice(&743i32.to_le_bytes());
+        expected.extend_from_slice(&109u16.to_le_bytes());
+
+        let header_len = expected.len();
+        expected.push(0x7f);
+        expected.extend_from_slice(&(-18i16).to_le_bytes());
+        expected.extend_from_slice(&(-36i16).to_le_bytes());
+        expected.extend_from_slice(&30i16.to_le_bytes());
+        expected.push(2);
+        expected.extend_from_slice(&(-13i16).to_le_bytes());
+        expected.extend_from_slice(&[3, 3, 1]);
+
+        let full_len = expected.len();
+        assert_eq!(frame[3..3 + full_len], expected[..]);
+
+        // the position without optional fields only carries an empty flags byte
+        let bare = &frame[3 + full_len..];
+        assert_eq!(bare.len(), header_len + 1);
+        assert_eq!(bare[..header_len], expected[..header_len]);
+        assert_eq!(bare[header_len], 0);
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!(
+            bare_position().to_json(),
+            json!({
+                "id": "FLRC04EFE",
+                "time": 1_531_605_102,
+                "longitude": -75.117_233,
+                "latitude": 45.4939,
+                "course": 16,
+                "altitude": 743,
+                "climb_rate": null,
+                "turn_rate": null,
+                "signal_quality": null,
+                "error_count": null,
+                "frequency_offset": null,
+                "gps_quality": null,
+                "speed": 109.,
+                "aircraft_type": null,
+            })
+        );
+
+        let json: Value = serde_json::from_str(&encode_json(vec![&position()])).unwrap();
+        assert_eq!(json["type"], "positions");
+        assert_eq!(json["positions"][0]["id"], "FLRC04EFE");
+        assert_eq!(json["positions"][0]["gps_quality"]["vertical"], 3);
+    }
+
+    #[test]
+    fn test_parse_request() {
+        assert_eq!(
+            Request::parse(r#"{"type":"subscribe","id":1,"aircraft":"FLRDD87AC"}"#),
+            Ok(Request {
+                id: json!(1),
+                command: Command::Subscribe {
+                    aircraft: "FLRDD87AC".to_owned()
+                },
+            })
+        );
+
+        assert_eq!(
+            Request::parse(r#"{"type":"bbox","bbox":[-12.5,25.1,28.7,61.9]}"#),
+            Ok(Request {
+                id: Value::Null,
+                command: Command::Bbox {
+                    bbox: [-12.5, 25.1, 28.7, 61.9],
+                    filter: FilterSpec::default(),
+                },
+            })
+        );
+
+        assert_eq!(
+            Request::parse(r#"{"type":"subscriptions","id":"abc"}"#),
+            Ok(Request {
+                id: json!("abc"),
+                command: Command::Subscriptions,
+            })
+        );
+
+        match Request::parse(r#"{"type":"bbox","id":7,"bbox":"everywhere"}"#) {
+            Err(InvalidRequest { id, .. }) => assert_eq!(id, json!(7)),
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        match Request::parse("+id|FLRDD87AC") {
+            Err(InvalidRequest { id, .. }) => assert_eq!(id, Value::Null),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_response() {
+        assert_eq!(
+            Response::Ack { id: json!(1) }.to_json(),
+            r#"{"type":"ack","id":1}"#
+        );
+        assert_eq!(
+            Response::Error {
+                id: Value::Null,
+                message: "Invalid aircraft ID".to_owned()
+            }
+            .to_json(),
+            r#"{"type":"error","id":null,"message":"Invalid aircraft ID"}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_area_request() {
+        let request = Request::parse(
+            r#"{"type":"add-area","id":1,"name":"task","area":{"type":"circle","center":[6.5,50.5],"radius":5000}}"#,
+        )
+        .unwrap();
+
+        match request.command {
+            Command::AddArea { name, area, filter } => {
+                assert_eq!(name, "task");
+                assert_eq!(filter, FilterSpec::default());
+                assert_eq!(
+                    area.to_area(),
+                    Some(Area::Circle(Circle::new(6.5, 50.5, 5000.).unwrap()))
+                );
+            }
+            command => panic!("unexpected command: {:?}", command),
+        }
+
+        let spec = AreaSpec::Polygon {
+            points: vec![[0., 0.], [1., 0.], [1., 1.]],
+        };
+        assert_eq!(AreaSpec::from(&spec.to_area().unwrap()), spec);
+
+        let spec = AreaSpec::Polygon {
+            points: vec![[0., 0.], [1., 0.]],
+        };
+        assert_eq!(spec.to_area(), None);
+    }
+
+    #[test]
+    fn test_parse_text_area() {
+        assert_eq!(
+            parse_text_area("task|bbox|5|-2|14|12"),
+            Some((
+                "task".to_owned(),
+                Area::BoundingBox(BoundingBox::new(5., -2., 14., 12.).unwrap()),
+                PositionFilter::default(),
+            ))
+        );
+        assert_eq!(
+            parse_text_area("airfield|circle|6.5|50.5|5000"),
+            Some((
+                "airfield".to_owned(),
+                Area::Circle(Circle::new(6.5, 50.5, 5000.).unwrap()),
+                PositionFilter::default(),
+            ))
+        );
+        assert_eq!(
+            parse_text_area("border|polygon|0|0|1|0|1|1"),
+            Some((
+                "border".to_owned(),
+                Area::Polygon(Polygon::new(vec![(0., 0.), (1., 0.), (1., 1.)]).unwrap()),
+                PositionFilter::default(),
+            ))
+        );
+
+        let (_, area, filter) =
+            parse_text_area("border|polygon|0|0|1|0|1|1|filter|500||10|glider").unwrap();
+        assert_eq!(
+            area,
+            Area::Polygon(Polygon::new(vec![(0., 0.), (1., 0.), (1., 1.)]).unwrap())
+        );
+        assert_eq!(PositionFilter::try_parse("500||10|glider"), Some(filter));
+
+        assert_eq!(parse_text_area("airfield|circle|6.5|50.5"), None);
+        assert_eq!(parse_text_area("border|polygon|0|0|1|0|1"), None);
+        assert_eq!(parse_text_area("|bbox|5|-2|14|12"), None);
+        assert_eq!(parse_text_area("task|square|5|-2|14|12"), None);
+        assert_eq!(parse_text_area("task|bbox|5|-2|14|12|filter|high"), None);
+        assert_eq!(parse_text_area("task|bbox|5|-2|14|12|filter|||5||x"), None);
+    }
+
+    #[test]
+    fn test_parse_text_bbox() {
+        let bbox = BoundingBox::new(5., -2., 14., 12.).unwrap();
+        assert_eq!(
+            parse_text_bbox("5|-2|14|12"),
+            Some((bbox, PositionFilter::default()))
+        );
+        assert_eq!(
+            parse_text_bbox("5|-2|14|12|filter|||5|"),
+            Some((bbox, PositionFilter::try_parse("||5|").unwrap()))
+        );
+        assert_eq!(parse_text_bbox("5|-2|14|12|filter|||car"), None);
+        assert_eq!(parse_text_bbox("filter|||5|"), None);
+    }
+
+    #[test]
+    fn test_parse_filter_request() {
+        let request = Request::parse(
+            r#"{"type":"bbox","id":1,"bbox":[5,-2,14,12],"filter":{"min_altitude":500,"categories":["glider","paraglider"]}}"#,
+        )
+        .unwrap();
+
+        let spec = match request.command {
+            Command::Bbox { filter, .. } => filter,
+            command => panic!("unexpected command: {:?}", command),
+        };
+
+        let filter = spec.to_filter().unwrap();
+        assert_eq!(filter.min_altitude, Some(500.));
+        assert_eq!(filter.max_altitude, None);
+        assert_eq!(filter.categories.len(), 2);
+        assert_eq!(FilterSpec::from(&filter), spec);
+
+        match Request::parse(r#"{"type":"bbox","bbox":[5,-2,14,12],"filter":{}}"#)
+            .unwrap()
+            .command
+        {
+            Command::Bbox { filter, .. } => assert!(filter.to_filter().unwrap().is_empty()),
+            command => panic!("unexpected command: {:?}", command),
+        }
+
+        let spec = FilterSpec {
+            categories: vec!["car".to_owned()],
+            ..FilterSpec::default()
+        };
+        assert_eq!(spec.to_filter(), Err("car".to_owned()));
+    }
+
+    #[test]
+    fn test_is_valid_id() {
+        assert!(is_valid_id("FLRDD87AC"));
+        assert!(is_valid_id("ICA3D1C35"));
+        assert!(!is_valid_id(""));
+        assert!(!is_valid_id("FLR DD87AC"));
+        assert!(!is_valid_id("FLRDD87AC|bbox"));
+    }
+
+    #[test]
+    fn test_tenths() {
+        assert_eq!(tenths(-1.84), -18);
+        assert_eq!(tenths(1e9), i16::MAX);
+    }
+}
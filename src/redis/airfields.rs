@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use actix::prelude::*;
+use anyhow::Result;
+use r2d2_redis::redis::{pipe, Commands};
+
+use crate::airfields::Airfield;
+use crate::redis::executor::RedisExecutor;
+
+const AIRFIELDS_KEY: &str = "ogn-airfields";
+
+/// Replaces all saved airfields.
+pub struct WriteAirfields(pub Vec<Airfield>);
+
+impl Message for WriteAirfields {
+    type Result = Result<()>;
+}
+
+impl Handler<WriteAirfields> for RedisExecutor {
+    type Result = Result<()>;
+
+    fn handle(&mut self, msg: WriteAirfields, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.pool.get()?;
+
+        let mut pipeline = pipe();
+        pipeline.atomic().del(AIRFIELDS_KEY).ignore();
+        for airfield in msg.0 {
+            pipeline
+                .hset(
+                    AIRFIELDS_KEY,
+                    &airfield.icao,
+                    serde_json::to_string(&airfield)?,
+                )
+                .ignore();
+        }
+
+        pipeline.query::<()>(&mut *conn)?;
+
+        Ok(())
+    }
+}
+
+pub struct ReadAirfields;
+
+impl Message for ReadAirfields {
+    type Result = Result<Vec<Airfield>>;
+}
+
+impl Handler<ReadAirfields> for RedisExecutor {
+    type Result = Result<Vec<Airfield>>;
+
+    fn handle(&mut self, _msg: ReadAirfields, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.pool.get()?;
+
+        let airfields: HashMap<String, String> = conn.hgetall(AIRFIELDS_KEY)?;
+
+        let mut airfields = airfields
+            .values()
+            .map(|it| serde_json::from_str(it))
+            .collect::<serde_json::Result<Vec<Airfield>>>()?;
+
+        airfields.sort_unstable_by(|a, b| a.icao.cmp(&b.icao));
+
+        Ok(airfields)
+    }
+}
+
+pub struct ReadAirfield {
+    pub icao: String,
+}
+
+impl Message for ReadAirfield {
+    type Result = Result<Option<Airfield>>;
+}
+
+impl Handler<ReadAirfield> for RedisExecutor {
+    type Result = Result<Option<Airfield>>;
+
+    fn handle(&mut self, msg: ReadAirfield, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.pool.get()?;
+
+        let airfield: Option<String> = conn.hget(AIRFIELDS_KEY, msg.icao.to_uppercase())?;
+
+        Ok(airfield.map(|it| serde_json::from_str(&it)).transpose()?)
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+
+use actix::prelude::*;
+use anyhow::Result;
+use bincode::{deserialize, serialize};
+use chrono::prelude::*;
+use chrono::Duration;
+use r2d2_redis::redis::{cmd, pipe, transaction, ErrorKind, RedisError};
+
+use crate::coverage::{Cell, CoverageStats, Tile};
+use crate::geo::BoundingBox;
+use crate::redis::executor::RedisExecutor;
+use crate::redis::time_buckets::*;
+
+fn coverage_key(tile: Tile, bucket_time: i64) -> String {
+    format!("ogn-coverage:{},{}:{}", tile.y, tile.x, bucket_time)
+}
+
+fn cell_field(receiver: &str, cell: Cell) -> String {
+    format!("{}|{}|{}", receiver, cell.x, cell.y)
+}
+
+fn parse_cell_field(field: &str) -> Option<(&str, Cell)> {
+    let mut parts = field.rsplitn(3, '|');
+    let y = parts.next()?.parse().ok()?;
+    let x = parts.next()?.parse().ok()?;
+    let receiver = parts.next()?;
+    Some((receiver, Cell { x, y }))
+}
+
+/// Merges the buffered coverage statistics into the statistics saved in redis.
+pub struct AddCoverage {
+    pub cells: HashMap<(i64, String, Cell), CoverageStats>,
+}
+
+impl Message for AddCoverage {
+    type Result = Result<()>;
+}
+
+impl Handler<AddCoverage> for RedisExecutor {
+    type Result = Result<()>;
+
+    fn handle(&mut self, msg: AddCoverage, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.pool.get()?;
+
+        let mut updates = HashMap::new();
+        for ((bucket_time, receiver, cell), stats) in msg.cells {
+            updates
+                .entry(coverage_key(cell.tile(), bucket_time))
+                .or_insert_with(Vec::new)
+                .push((cell_field(&receiver, cell), stats));
+        }
+
+        // the merge happens in Rust, so the keys are WATCHed and the update is
+        // retried if another connection modified them in the meantime
+        for (key, cells) in updates {
+            let fields: Vec<&String> = cells.iter().map(|(field, _)| field).collect();
+            transaction(&mut *conn, &[&key], |conn, pipeline| {
+                let existing: Vec<Option<Vec<u8>>> =
+                    cmd("HMGET").arg(&key).arg(&fields[..]).query(conn)?;
+
+                let values = merge_cells(&cells, existing).map_err(|error| {
+                    RedisError::from((
+                        ErrorKind::TypeError,
+                        "Invalid coverage statistics",
+                        error.to_string(),
+                    ))
+                })?;
+
+                pipeline
+                    .hset_multiple(&key, &values)
+                    .ignore()
+                    .query::<Option<()>>(conn)
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Merges the new statistics of the cells with the `existing` serialized
+/// statistics and returns the serialized results.
+fn merge_cells(
+    cells: &[(String, CoverageStats)],
+    existing: Vec<Option<Vec<u8>>>,
+) -> bincode::Result<Vec<(&str, Vec<u8>)>> {
+    let mut values = Vec::with_capacity(cells.len());
+    for ((field, stats), existing) in cells.iter().zip(existing) {
+        let mut stats = *stats;
+        if let Some(existing) = existing {
+            stats.merge(&deserialize(&existing)?);
+        }
+        values.push((field.as_str(), serialize(&stats)?));
+    }
+    Ok(values)
+}
+
+/// Coverage statistics of a single receiver in a single cell,
+/// aggregated over all buckets of the last 24 hours.
+pub struct CoverageCell {
+    pub receiver: String,
+    pub cell: Cell,
+    pub stats: CoverageStats,
+}
+
+pub struct ReadCoverage {
+    pub bbox: BoundingBox,
+    pub receiver: Option<String>,
+}
+
+impl Message for ReadCoverage {
+    type Result = Result<Vec<CoverageCell>>;
+}
+
+impl Handler<ReadCoverage> for RedisExecutor {
+    type Result = Result<Vec<CoverageCell>>;
+
+    fn handle(&mut self, msg: ReadCoverage, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.pool.get()?;
+
+        let now = Utc::now();
+        let bucket_times = bucket_times_between(now - Duration::days(1), now);
+
+        let keys: Vec<String> = Tile::covering(&msg.bbox)
+            .into_iter()
+            .flat_map(|tile| {
+                bucket_times
+                    .iter()
+                    .map(move |bucket_time| coverage_key(tile, *bucket_time))
+            })
+            .collect();
+
+        let mut pipeline = pipe();
+        for key in &keys {
+            pipeline.hgetall(key);
+        }
+        let results: Vec<HashMap<String, Vec<u8>>> = pipeline.query(&mut *conn)?;
+
+        let mut cells: HashMap<(String, Cell), CoverageStats> = HashMap::new();
+        for values in results {
+            for (field, value) in values {
+                let (receiver, cell) = match parse_cell_field(&field) {
+                    Some(it) => it,
+                    None => continue,
+                };
+
+                if let Some(filter) = &msg.receiver {
+                    if filter != receiver {
+                        continue;
+                    }
+                }
+
+                if !cell.is_in(&msg.bbox) {
+                    continue;
+                }
+
+                let stats: CoverageStats = deserialize(&value)?;
+                cells
+                    .entry((receiver.to_owned(), cell))
+                    .and_modify(|it| it.merge(&stats))
+                    .or_insert(stats);
+            }
+        }
+
+        Ok(cells
+            .into_iter()
+            .map(|((receiver, cell), stats)| CoverageCell {
+                receiver,
+                cell,
+                stats,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_field() {
+        let field = cell_field("VillaBlau", Cell { x: -826, y: 4999 });
+        assert_eq!(field, "VillaBlau|-826|4999");
+        assert_eq!(
+            parse_cell_field(&field),
+            Some(("VillaBlau", Cell { x: -826, y: 4999 }))
+        );
+
+        assert_eq!(parse_cell_field("VillaBlau|826"), None);
+        assert_eq!(parse_cell_field("VillaBlau|a|4999"), None);
+    }
+
+    #[test]
+    fn test_merge_cells() {
+        let cells = vec![
+            (
+                "a|1|2".to_string(),
+                CoverageStats::new(500, Some(1000.), None),
+            ),
+            (
+                "b|1|2".to_string(),
+                CoverageStats::new(800, None, Some(10.)),
+            ),
+        ];
+        let existing = vec![
+            Some(serialize(&CoverageStats::new(300, Some(200.), Some(5.))).unwrap()),
+            None,
+        ];
+
+        let values = merge_cells(&cells, existing).unwrap();
+        assert_eq!(values.len(), 2);
+
+        assert_eq!(values[0].0, "a|1|2");
+        let stats: CoverageStats = deserialize(&values[0].1).unwrap();
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.min_altitude, 300);
+        assert_eq!(stats.max_range, 1000.);
+        assert_eq!(stats.average_snr(), Some(5.));
+
+        assert_eq!(values[1].0, "b|1|2");
+        let stats: CoverageStats = deserialize(&values[1].1).unwrap();
+        assert_eq!(stats, cells[1].1);
+
+        assert!(merge_cells(&cells, vec![Some(vec![1]), None]).is_err());
+    }
+}
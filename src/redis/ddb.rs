@@ -1,9 +1,18 @@
+use std::collections::HashMap;
+
 use actix::prelude::*;
 use anyhow::Result;
-use r2d2_redis::redis::Commands;
+use log::warn;
+use r2d2_redis::redis::{cmd, pipe, Commands};
+use serde::Deserialize;
 
+use crate::ogn_ddb::DeviceInfo;
 use crate::redis::executor::RedisExecutor;
 
+/// Hash of the JSON encoded `DeviceInfo` by device ID, so that single
+/// devices can be looked up without parsing the whole `ogn-ddb` value
+const DEVICES_KEY: &str = "ogn-ddb-devices";
+
 pub struct ReadOGNDDB;
 
 impl Message for ReadOGNDDB {
@@ -20,6 +29,75 @@ impl Handler<ReadOGNDDB> for RedisExecutor {
     }
 }
 
+/// Reads the `DeviceInfo` of the given devices, skipping unknown devices.
+pub struct ReadOGNDevices {
+    pub ids: Vec<String>,
+}
+
+impl Message for ReadOGNDevices {
+    type Result = Result<HashMap<String, DeviceInfo>>;
+}
+
+impl Handler<ReadOGNDevices> for RedisExecutor {
+    type Result = Result<HashMap<String, DeviceInfo>>;
+
+    fn handle(&mut self, msg: ReadOGNDevices, _ctx: &mut Self::Context) -> Self::Result {
+        if msg.ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut conn = self.pool.get()?;
+        let values: Vec<Option<String>> = cmd("HMGET")
+            .arg(DEVICES_KEY)
+            .arg(&msg.ids[..])
+            .query(&mut *conn)?;
+
+        let mut devices = HashMap::new();
+        for (id, value) in msg.ids.into_iter().zip(values) {
+            if let Some(value) = value {
+                match serde_json::from_str(&value) {
+                    Ok(device) => {
+                        devices.insert(id, device);
+                    }
+                    Err(error) => warn!("Could not parse OGN device {}: {}", id, error),
+                }
+            }
+        }
+
+        Ok(devices)
+    }
+}
+
+/// Reads the `DeviceInfo.category` of all devices in the OGN Device Database.
+pub struct ReadOGNDeviceCategories;
+
+impl Message for ReadOGNDeviceCategories {
+    type Result = Result<HashMap<String, i16>>;
+}
+
+impl Handler<ReadOGNDeviceCategories> for RedisExecutor {
+    type Result = Result<HashMap<String, i16>>;
+
+    fn handle(&mut self, _msg: ReadOGNDeviceCategories, _ctx: &mut Self::Context) -> Self::Result {
+        #[derive(Deserialize)]
+        struct Device {
+            category: i16,
+        }
+
+        let mut conn = self.pool.get()?;
+        let result: Option<String> = conn.get("ogn-ddb")?;
+        let devices: HashMap<String, Device> = match result {
+            Some(json) => serde_json::from_str(&json)?,
+            None => HashMap::new(),
+        };
+
+        Ok(devices
+            .into_iter()
+            .map(|(id, device)| (id, device.category))
+            .collect())
+    }
+}
+
 pub struct WriteOGNDDB(pub String);
 
 impl Message for WriteOGNDDB {
@@ -30,8 +108,21 @@ impl Handler<WriteOGNDDB> for RedisExecutor {
     type Result = Result<()>;
 
     fn handle(&mut self, msg: WriteOGNDDB, _ctx: &mut Self::Context) -> Self::Result {
+        let devices: HashMap<String, serde_json::Value> = serde_json::from_str(&msg.0)?;
+        let devices: Vec<(String, String)> = devices
+            .into_iter()
+            .map(|(id, device)| (id, device.to_string()))
+            .collect();
+
         let mut conn = self.pool.get()?;
-        conn.set("ogn-ddb", msg.0)?;
+
+        let mut pipeline = pipe();
+        pipeline.atomic().set("ogn-ddb", msg.0).del(DEVICES_KEY);
+        if !devices.is_empty() {
+            pipeline.hset_multiple(DEVICES_KEY, &devices);
+        }
+        pipeline.query::<()>(&mut *conn)?;
+
         Ok(())
     }
 }
@@ -67,7 +158,7 @@ impl Handler<WriteOGNIgnore> for RedisExecutor {
 
     fn handle(&mut self, msg: WriteOGNIgnore, _ctx: &mut Self::Context) -> Self::Result {
         let mut conn = self.pool.get()?;
-        conn.set("ogn-ignore", serde_json::to_string(&msg.0)?)?;
+        conn.set::<_, _, ()>("ogn-ignore", serde_json::to_string(&msg.0)?)?;
         Ok(())
     }
 }
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use actix::prelude::*;
+use anyhow::Result;
+use chrono::prelude::*;
+use chrono::Duration;
+use r2d2_redis::redis::{pipe, Commands};
+use serde::{Deserialize, Serialize};
+
+use crate::redis::executor::RedisExecutor;
+
+/// Number of days that detected flights are kept for
+const FLIGHT_RETENTION_DAYS: i64 = 7;
+
+fn flight_key(flight_id: &str) -> String {
+    format!("ogn-flight:{}", flight_id)
+}
+
+/// Sorted set of the flight IDs of an aircraft, scored by their start time
+fn flights_index_key(id: &str) -> String {
+    format!("ogn-flights:{}", id)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct OGNFlightLocation {
+    pub time: DateTime<Utc>,
+    pub longitude: f32,
+    pub latitude: f32,
+    pub altitude: i16,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OGNFlight {
+    /// OGN ID of the aircraft (e.g. `FLRDD9612`)
+    pub id: String,
+    /// takeoff time, or the time the aircraft was first seen airborne
+    pub start: DateTime<Utc>,
+    /// landing time, or the time the aircraft was last seen if the landing
+    /// was not observed (`None` while the aircraft is still airborne)
+    pub end: Option<DateTime<Utc>>,
+    pub takeoff: Option<OGNFlightLocation>,
+    pub landing: Option<OGNFlightLocation>,
+}
+
+impl OGNFlight {
+    /// Unique ID of the flight (e.g. `FLRDD9612-1523974796`)
+    pub fn flight_id(&self) -> String {
+        format!("{}-{}", self.id, self.start.timestamp())
+    }
+}
+
+/// Saves new or updated flights, replacing previously saved versions of
+/// the same flights.
+pub struct AddOGNFlights {
+    pub flights: Vec<OGNFlight>,
+}
+
+impl Message for AddOGNFlights {
+    type Result = Result<()>;
+}
+
+impl Handler<AddOGNFlights> for RedisExecutor {
+    type Result = Result<()>;
+
+    fn handle(&mut self, msg: AddOGNFlights, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.pool.get()?;
+
+        let retention = Duration::days(FLIGHT_RETENTION_DAYS);
+        let cutoff = (Utc::now() - retention).timestamp();
+
+        let mut pipeline = pipe();
+        for flight in msg.flights {
+            let flight_id = flight.flight_id();
+            let index_key = flights_index_key(&flight.id);
+
+            pipeline
+                .set_ex(
+                    flight_key(&flight_id),
+                    serde_json::to_string(&flight)?,
+                    retention.num_seconds() as usize,
+                )
+                .ignore()
+                .zadd(&index_key, &flight_id, flight.start.timestamp())
+                .ignore()
+                .zrembyscore(&index_key, "-inf", cutoff)
+                .ignore()
+                .expire(&index_key, retention.num_seconds() as usize)
+                .ignore();
+        }
+
+        pipeline.query::<()>(&mut *conn)?;
+
+        Ok(())
+    }
+}
+
+/// Reads the flights of the given aircraft that started within the time range.
+pub struct ReadOGNFlights {
+    pub ids: Vec<String>,
+    pub after: Option<DateTime<Utc>>,
+    pub before: Option<DateTime<Utc>>,
+}
+
+impl Message for ReadOGNFlights {
+    type Result = Result<HashMap<String, Vec<OGNFlight>>>;
+}
+
+impl Handler<ReadOGNFlights> for RedisExecutor {
+    type Result = Result<HashMap<String, Vec<OGNFlight>>>;
+
+    fn handle(&mut self, msg: ReadOGNFlights, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.pool.get()?;
+
+        let after = msg
+            .after
+            .unwrap_or_else(|| Utc::now() - Duration::days(FLIGHT_RETENTION_DAYS));
+        let before = msg.before.unwrap_or_else(Utc::now);
+
+        let mut result = HashMap::new();
+        for id in msg.ids {
+            let flight_ids: Vec<String> = conn.zrangebyscore(
+                flights_index_key(&id),
+                after.timestamp(),
+                before.timestamp(),
+            )?;
+
+            let keys: Vec<String> = flight_ids.iter().map(|it| flight_key(it)).collect();
+            let values: Vec<Option<String>> = match keys.len() {
+                0 => Vec::new(),
+                1 => vec![conn.get(&keys[0])?],
+                _ => conn.get(keys)?,
+            };
+
+            let flights = values
+                .into_iter()
+                .flatten()
+                .map(|it| serde_json::from_str(&it))
+                .collect::<serde_json::Result<Vec<OGNFlight>>>()?;
+
+            result.insert(id, flights);
+        }
+
+        Ok(result)
+    }
+}
+
+pub struct ReadOGNFlight {
+    pub flight_id: String,
+}
+
+impl Message for ReadOGNFlight {
+    type Result = Result<Option<OGNFlight>>;
+}
+
+impl Handler<ReadOGNFlight> for RedisExecutor {
+    type Result = Result<Option<OGNFlight>>;
+
+    fn handle(&mut self, msg: ReadOGNFlight, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.pool.get()?;
+
+        let flight: Option<String> = conn.get(flight_key(&msg.flight_id))?;
+
+        Ok(flight.map(|it| serde_json::from_str(&it)).transpose()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flight_id() {
+        let flight = OGNFlight {
+            id: "FLRDD9612".to_owned(),
+            start: Utc.with_ymd_and_hms(2018, 4, 17, 14, 19, 56).unwrap(),
+            end: None,
+            takeoff: None,
+            landing: None,
+        };
+
+        assert_eq!(flight.flight_id(), "FLRDD9612-1523974796");
+        assert_eq!(
+            flight_key(&flight.flight_id()),
+            "ogn-flight:FLRDD9612-1523974796"
+        );
+        assert_eq!(flights_index_key(&flight.id), "ogn-flights:FLRDD9612");
+    }
+}
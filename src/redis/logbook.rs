@@ -0,0 +1,102 @@
+use actix::prelude::*;
+use anyhow::Result;
+use chrono::prelude::*;
+use chrono::Duration;
+use r2d2_redis::redis::{pipe, Commands};
+use serde::{Deserialize, Serialize};
+
+use crate::redis::executor::RedisExecutor;
+
+/// Number of days that the logbook entries are kept for
+const LOGBOOK_RETENTION_DAYS: i64 = 30;
+
+fn logbook_key(icao: &str, date: NaiveDate) -> String {
+    format!("ogn-logbook:{}:{}", icao, date.format("%Y-%m-%d"))
+}
+
+/// Takeoff and landing of a flight at the airfields they were attributed to
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LogbookEntry {
+    pub flight_id: String,
+    /// OGN ID of the aircraft (e.g. `FLRDD9612`)
+    pub id: String,
+    pub takeoff: Option<DateTime<Utc>>,
+    pub takeoff_airfield: Option<String>,
+    pub landing: Option<DateTime<Utc>>,
+    pub landing_airfield: Option<String>,
+}
+
+/// Saves new or updated logbook entries in the logbook of the given
+/// airfield and day.
+pub struct AddLogbookEntries {
+    pub entries: Vec<(String, NaiveDate, LogbookEntry)>,
+}
+
+impl Message for AddLogbookEntries {
+    type Result = Result<()>;
+}
+
+impl Handler<AddLogbookEntries> for RedisExecutor {
+    type Result = Result<()>;
+
+    fn handle(&mut self, msg: AddLogbookEntries, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.pool.get()?;
+
+        let retention = Duration::days(LOGBOOK_RETENTION_DAYS).num_seconds() as usize;
+
+        let mut pipeline = pipe();
+        for (icao, date, entry) in msg.entries {
+            let key = logbook_key(&icao, date);
+            pipeline
+                .hset(&key, &entry.flight_id, serde_json::to_string(&entry)?)
+                .ignore()
+                .expire(&key, retention)
+                .ignore();
+        }
+
+        pipeline.query::<()>(&mut *conn)?;
+
+        Ok(())
+    }
+}
+
+pub struct ReadLogbook {
+    pub icao: String,
+    pub date: NaiveDate,
+}
+
+impl Message for ReadLogbook {
+    type Result = Result<Vec<LogbookEntry>>;
+}
+
+impl Handler<ReadLogbook> for RedisExecutor {
+    type Result = Result<Vec<LogbookEntry>>;
+
+    fn handle(&mut self, msg: ReadLogbook, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.pool.get()?;
+
+        let entries: Vec<String> = conn.hvals(logbook_key(&msg.icao.to_uppercase(), msg.date))?;
+
+        let mut entries = entries
+            .iter()
+            .map(|it| serde_json::from_str(it))
+            .collect::<serde_json::Result<Vec<LogbookEntry>>>()?;
+
+        entries.sort_by_key(|it| it.takeoff.or(it.landing));
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logbook_key() {
+        assert_eq!(
+            logbook_key("EDKA", NaiveDate::from_ymd_opt(2018, 4, 17).unwrap()),
+            "ogn-logbook:EDKA:2018-04-17"
+        );
+    }
+}
@@ -1,8 +1,19 @@
+mod airfields;
+mod coverage;
 mod ddb;
 mod executor;
+mod flights;
+mod logbook;
 mod positions;
+mod receivers;
 mod time_buckets;
 
+pub use crate::redis::airfields::*;
+pub use crate::redis::coverage::*;
 pub use crate::redis::ddb::*;
 pub use crate::redis::executor::RedisExecutor;
+pub use crate::redis::flights::*;
+pub use crate::redis::logbook::*;
 pub use crate::redis::positions::*;
+pub use crate::redis::receivers::*;
+pub use crate::redis::time_buckets::ToBucketTime;
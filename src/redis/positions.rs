@@ -1,5 +1,4 @@
 use std::collections::HashMap;
-use std::mem::size_of;
 
 use actix::prelude::*;
 use anyhow::Result;
@@ -8,20 +7,78 @@ use chrono::prelude::*;
 use chrono::{Duration, Utc};
 use itertools::Itertools;
 use lazy_static::lazy_static;
-use log::{error, info};
+use log::{error, info, warn};
 use r2d2_redis::redis::{pipe, Commands, Connection};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+use crate::ogn::aprs::GPSQuality;
 use crate::redis::executor::RedisExecutor;
 use crate::redis::time_buckets::*;
 
+/// Size of a serialized `RedisOGNRecord` in bytes
+const RECORD_SIZE: usize = 25;
+
+/// Version of the `RedisOGNRecord` layout, which is part of the record keys.
+///
+/// Records are appended to the existing bucket keys and read in chunks of
+/// `RECORD_SIZE`, so changing the layout without incrementing the version
+/// would mix up old and new records.
+const RECORD_VERSION: u8 = 2;
+
+/// Key pattern of the unversioned records that were written before the
+/// `RedisOGNRecord` layout was versioned. These are only dropped.
+const LEGACY_RECORDS_PATTERN: &str = "ogn:*:*";
+
+fn records_key(id: &str, bucket_time: i64) -> String {
+    format!("ogn{}:{}:{}", RECORD_VERSION, id, bucket_time)
+}
+
+fn records_pattern() -> String {
+    format!("ogn{}:*:*", RECORD_VERSION)
+}
+
+/// Marker for missing optional values in a `RedisOGNRecord`
+const NONE_I16: i16 = i16::MIN;
+const NONE_U8: u8 = u8::MAX;
+
+/// Position record as stored in redis.
+///
+/// The fields are serialized in declaration order, so adding, removing or
+/// reordering fields requires incrementing `RECORD_VERSION`.
 #[derive(Serialize, Deserialize, Debug)]
 struct RedisOGNRecord {
     seconds: u16,
     altitude: i16,
     longitude: f32,
     latitude: f32,
+    /// ground speed in 0.1 km/h
+    speed: u16,
+    /// climb rate in cm/s
+    climb_rate: i16,
+    /// turn rate in 0.1 degrees per second
+    turn_rate: i16,
+    /// signal-to-noise ratio in 0.1 dB
+    signal_quality: i16,
+    /// frequency offset in 0.1 kHz
+    frequency_offset: i16,
+    error_count: u8,
+    gps_horizontal: u8,
+    gps_vertical: u8,
+}
+
+/// Size of a serialized `RedisOGNReceptionRecord` in bytes
+const RECEPTION_RECORD_SIZE: usize = 12;
+
+/// Receiver information of a `RedisOGNRecord`, saved in a separate
+/// `ogn-rx:{id}:{bucket}` key next to the `ogn2:{id}:{bucket}` records.
+#[derive(Serialize, Deserialize, Debug)]
+struct RedisOGNReceptionRecord {
+    seconds: u16,
+    /// last character of the q-construct (e.g. `S` for `qAS`)
+    q_construct: u8,
+    /// receiver callsign, padded with zero bytes
+    receiver: [u8; 9],
 }
 
 #[derive(Debug)]
@@ -30,6 +87,94 @@ pub struct OGNPosition {
     pub longitude: f32,
     pub latitude: f32,
     pub altitude: i16,
+    /// ground speed in km/h
+    pub speed: f32,
+    pub climb_rate: Option<f32>,
+    pub turn_rate: Option<f32>,
+    pub signal_quality: Option<f32>,
+    pub error_count: Option<u8>,
+    pub frequency_offset: Option<f32>,
+    pub gps_quality: Option<GPSQuality>,
+    pub reception: Option<OGNReception>,
+}
+
+#[cfg(test)]
+impl OGNPosition {
+    /// Creates a position of `FLRDD9612` near Speyer with only the values
+    /// that most tests care about.
+    pub fn fixture(time: DateTime<Utc>, altitude: i16, speed: f32) -> OGNPosition {
+        OGNPosition {
+            time,
+            longitude: 8.265_5,
+            latitude: 49.186_333,
+            altitude,
+            speed,
+            climb_rate: None,
+            turn_rate: None,
+            signal_quality: None,
+            error_count: None,
+            frequency_offset: None,
+            gps_quality: None,
+            reception: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OGNReception {
+    /// callsign of the receiver (e.g. `VillaBlau`)
+    pub receiver: String,
+    /// APRS-IS q-construct (e.g. `qAS`)
+    pub q_construct: String,
+}
+
+impl OGNReception {
+    fn to_record(&self, seconds: u16) -> RedisOGNReceptionRecord {
+        let mut receiver = [0; 9];
+        for (target, byte) in receiver.iter_mut().zip(self.receiver.bytes()) {
+            *target = byte;
+        }
+
+        RedisOGNReceptionRecord {
+            seconds,
+            q_construct: self.q_construct.bytes().last().unwrap_or(0),
+            receiver,
+        }
+    }
+
+    fn from_record(record: &RedisOGNReceptionRecord) -> OGNReception {
+        let receiver = record
+            .receiver
+            .iter()
+            .take_while(|it| **it != 0)
+            .map(|it| *it as char)
+            .collect();
+
+        OGNReception {
+            receiver,
+            q_construct: format!("qA{}", record.q_construct as char),
+        }
+    }
+}
+
+fn encode_i16(value: Option<f32>, factor: f32) -> i16 {
+    value.map_or(NONE_I16, |it| (it * factor).round() as i16)
+}
+
+fn decode_i16(value: i16, factor: f32) -> Option<f32> {
+    if value == NONE_I16 {
+        None
+    } else {
+        Some(f32::from(value) / factor)
+    }
+}
+
+fn decode_u8(value: u8) -> Option<u8> {
+    if value == NONE_U8 {
+        None
+    } else {
+        Some(value)
+    }
 }
 
 pub struct AddOGNPositions {
@@ -47,6 +192,7 @@ impl Handler<AddOGNPositions> for RedisExecutor {
         let mut conn = self.pool.get()?;
 
         let mut appends = HashMap::new();
+        let mut reception_appends = HashMap::new();
         for (id, pos) in msg.positions {
             let bucket_time = pos.time.to_bucket_time();
             let seconds = (pos.time.minute() * 60 + pos.time.second()) as u16;
@@ -56,8 +202,27 @@ impl Handler<AddOGNPositions> for RedisExecutor {
                 altitude: pos.altitude,
                 latitude: pos.latitude,
                 longitude: pos.longitude,
+                speed: (pos.speed * 10.).round() as u16,
+                climb_rate: encode_i16(pos.climb_rate, 100.),
+                turn_rate: encode_i16(pos.turn_rate, 10.),
+                signal_quality: encode_i16(pos.signal_quality, 10.),
+                frequency_offset: encode_i16(pos.frequency_offset, 10.),
+                error_count: pos.error_count.unwrap_or(NONE_U8),
+                gps_horizontal: pos.gps_quality.map_or(NONE_U8, |it| it.horizontal),
+                gps_vertical: pos.gps_quality.map_or(NONE_U8, |it| it.vertical),
             })?;
 
+            if let Some(reception) = &pos.reception {
+                let value = serialize(&reception.to_record(seconds))?;
+
+                reception_appends
+                    .entry(id.clone())
+                    .or_insert_with(HashMap::new)
+                    .entry(bucket_time)
+                    .or_insert_with(Vec::new)
+                    .extend(value);
+            }
+
             appends
                 .entry(id)
                 .or_insert_with(HashMap::new)
@@ -69,12 +234,17 @@ impl Handler<AddOGNPositions> for RedisExecutor {
         let mut pipeline = pipe();
         for (id, records) in appends {
             for (bucket_time, records) in records {
-                let key = format!("ogn:{}:{}", id, bucket_time);
+                pipeline.append(records_key(&id, bucket_time), records);
+            }
+        }
+        for (id, records) in reception_appends {
+            for (bucket_time, records) in records {
+                let key = format!("ogn-rx:{}:{}", id, bucket_time);
                 pipeline.append(key, records);
             }
         }
 
-        pipeline.query(&mut *conn)?;
+        pipeline.query::<()>(&mut *conn)?;
 
         Ok(())
     }
@@ -94,12 +264,12 @@ impl Handler<CountOGNPositions> for RedisExecutor {
         let mut conn = self.pool.get()?;
 
         let mut sum = 0;
-        for key in iter_conn.scan_match::<&str, String>("ogn:*:*")? {
+        for key in iter_conn.scan_match::<_, String>(records_pattern())? {
             let length: u64 = conn.strlen(key)?;
             sum += length;
         }
 
-        Ok(sum / size_of::<RedisOGNRecord>() as u64)
+        Ok(sum / RECORD_SIZE as u64)
     }
 }
 
@@ -113,20 +283,39 @@ impl Handler<DropOldOGNPositions> for RedisExecutor {
     type Result = Result<u64>;
 
     fn handle(&mut self, _msg: DropOldOGNPositions, _ctx: &mut Self::Context) -> Self::Result {
-        lazy_static! {
-            static ref RE: Regex = Regex::new(r"ogn:[^:]+:(?P<bucket_time>\d+)").unwrap();
-        }
-
-        let mut iter_conn = self.pool.get()?;
-        let mut conn = self.pool.get()?;
-
         info!("Dropping outdated OGN position records from redis…");
 
         let now = Utc::now();
         let cutoff_date = now - Duration::days(1);
         let max = cutoff_date.timestamp();
 
-        let iter = iter_conn.scan_match("ogn:*:*");
+        let num_deleted_bytes = self.drop_keys_before(&records_pattern(), max)?;
+        self.drop_keys_before(LEGACY_RECORDS_PATTERN, max)?;
+        self.drop_keys_before("ogn-rx:*:*", max)?;
+        self.drop_keys_before("ogn-coverage:*:*", max)?;
+
+        let num_deleted = num_deleted_bytes / RECORD_SIZE as u64;
+        info!(
+            "Dropped {} outdated OGN position records from redis",
+            num_deleted
+        );
+        Ok(num_deleted)
+    }
+}
+
+impl RedisExecutor {
+    /// Deletes all keys matching the `pattern` with a bucket time before `max`
+    /// and returns the number of deleted bytes.
+    fn drop_keys_before(&mut self, pattern: &str, max: i64) -> Result<u64> {
+        lazy_static! {
+            static ref RE: Regex =
+                Regex::new(r"ogn(?:\d+|-rx|-coverage)?:[^:]+:(?P<bucket_time>\d+)").unwrap();
+        }
+
+        let mut iter_conn = self.pool.get()?;
+        let mut conn = self.pool.get()?;
+
+        let iter = iter_conn.scan_match(pattern);
         if iter.is_err() {
             let error = iter.err().unwrap();
             error!("Could not read OGN position records keys: {}", error);
@@ -156,12 +345,7 @@ impl Handler<DropOldOGNPositions> for RedisExecutor {
             })
             .sum::<u64>();
 
-        let num_deleted = num_deleted_bytes / size_of::<RedisOGNRecord>() as u64;
-        info!(
-            "Dropped {} outdated OGN position records from redis",
-            num_deleted
-        );
-        Ok(num_deleted)
+        Ok(num_deleted_bytes)
     }
 }
 
@@ -169,6 +353,8 @@ pub struct ReadOGNPositions {
     pub ids: Vec<String>,
     pub after: Option<DateTime<Utc>>,
     pub before: Option<DateTime<Utc>>,
+    /// also read the receiver information of the positions, if available
+    pub include_receivers: bool,
 }
 
 impl Message for ReadOGNPositions {
@@ -186,7 +372,7 @@ impl Handler<ReadOGNPositions> for RedisExecutor {
 
         let mut result = HashMap::new();
         for id in msg.ids {
-            let records = conn.get_ogn_records(&id, after, before)?;
+            let records = conn.get_ogn_records(&id, after, before, msg.include_receivers)?;
             result.insert(id, records);
         }
 
@@ -200,11 +386,12 @@ trait OGNRedisCommands: Commands {
         id: &str,
         from: DateTime<Utc>,
         to: DateTime<Utc>,
+        include_receivers: bool,
     ) -> Result<Vec<OGNPosition>> {
         let mut result: Vec<OGNPosition> = Vec::new();
         for bucket_time in bucket_times_between(from, to) {
             result.extend(
-                self.get_ogn_records_for_bucket(id, bucket_time)?
+                self.get_ogn_records_for_bucket(id, bucket_time, include_receivers)?
                     .into_iter()
                     .filter(|it| it.time >= from && it.time <= to),
             );
@@ -219,41 +406,82 @@ trait OGNRedisCommands: Commands {
         &mut self,
         id: &str,
         bucket_time: i64,
+        include_receivers: bool,
     ) -> Result<Vec<OGNPosition>> {
-        let key = format!("ogn:{}:{}", id, bucket_time);
-        let value: Vec<u8> = self.get(key)?;
+        let key = records_key(id, bucket_time);
+        let value: Vec<u8> = self.get(&key)?;
+        if !value.len().is_multiple_of(RECORD_SIZE) {
+            warn!("Skipping {} with unexpected record layout", key);
+            return Ok(Vec::new());
+        }
+
+        let mut receptions = if include_receivers {
+            self.get_ogn_receptions_for_bucket(id, bucket_time)?
+        } else {
+            HashMap::new()
+        };
 
         let results_iter = value
-            .chunks(size_of::<RedisOGNRecord>())
-            .map(|chunk| deserialize::<RedisOGNRecord>(chunk))
+            .chunks_exact(RECORD_SIZE)
+            .map(deserialize::<RedisOGNRecord>)
             .unique_by(|result| result.as_ref().map(|record| record.seconds).unwrap_or(0));
 
         let mut vec = Vec::new();
         for result in results_iter {
             let record = result?;
             let timestamp = bucket_time + i64::from(record.seconds);
-            let time = Utc.timestamp(timestamp, 0);
+            let time = Utc.timestamp_opt(timestamp, 0).unwrap();
+
+            let gps_quality = decode_u8(record.gps_horizontal).and_then(|horizontal| {
+                decode_u8(record.gps_vertical).map(|vertical| GPSQuality {
+                    horizontal,
+                    vertical,
+                })
+            });
 
             vec.push(OGNPosition {
                 time,
                 latitude: record.latitude,
                 longitude: record.longitude,
                 altitude: record.altitude,
+                speed: f32::from(record.speed) / 10.,
+                climb_rate: decode_i16(record.climb_rate, 100.),
+                turn_rate: decode_i16(record.turn_rate, 10.),
+                signal_quality: decode_i16(record.signal_quality, 10.),
+                error_count: decode_u8(record.error_count),
+                frequency_offset: decode_i16(record.frequency_offset, 10.),
+                gps_quality,
+                reception: receptions.remove(&record.seconds),
             });
         }
 
         Ok(vec)
     }
+
+    fn get_ogn_receptions_for_bucket(
+        &mut self,
+        id: &str,
+        bucket_time: i64,
+    ) -> Result<HashMap<u16, OGNReception>> {
+        let key = format!("ogn-rx:{}:{}", id, bucket_time);
+        let value: Vec<u8> = self.get(key)?;
+
+        let mut map = HashMap::new();
+        for chunk in value.chunks_exact(RECEPTION_RECORD_SIZE) {
+            let record = deserialize::<RedisOGNReceptionRecord>(chunk)?;
+            map.entry(record.seconds)
+                .or_insert_with(|| OGNReception::from_record(&record));
+        }
+
+        Ok(map)
+    }
 }
 
 impl OGNRedisCommands for Connection {}
 
 #[cfg(test)]
 mod tests {
-    use bincode::{deserialize, serialize};
-    use std::mem::size_of;
-
-    use super::RedisOGNRecord;
+    use super::*;
 
     #[test]
     fn test_deserialization() {
@@ -262,6 +490,14 @@ mod tests {
             altitude: 1234,
             longitude: 52.987,
             latitude: 7.456,
+            speed: 1093,
+            climb_rate: 123,
+            turn_rate: -36,
+            signal_quality: 30,
+            frequency_offset: -13,
+            error_count: 2,
+            gps_horizontal: 3,
+            gps_vertical: 3,
         };
 
         let record2 = RedisOGNRecord {
@@ -269,6 +505,14 @@ mod tests {
             altitude: 2345,
             longitude: 51.987,
             latitude: 7.356,
+            speed: 1093,
+            climb_rate: 123,
+            turn_rate: -36,
+            signal_quality: 30,
+            frequency_offset: -13,
+            error_count: 2,
+            gps_horizontal: 3,
+            gps_vertical: 3,
         };
 
         let record3 = RedisOGNRecord {
@@ -276,6 +520,14 @@ mod tests {
             altitude: 678,
             longitude: 50.987,
             latitude: 7.256,
+            speed: 1093,
+            climb_rate: 123,
+            turn_rate: -36,
+            signal_quality: 30,
+            frequency_offset: -13,
+            error_count: 2,
+            gps_horizontal: 3,
+            gps_vertical: 3,
         };
 
         let mut vec1 = serialize(&record1).unwrap();
@@ -286,12 +538,102 @@ mod tests {
         vec1.append(&mut vec3);
 
         let records: Vec<RedisOGNRecord> = vec1
-            .chunks(size_of::<RedisOGNRecord>())
+            .chunks_exact(RECORD_SIZE)
             .map(|it| deserialize(it).unwrap())
             .collect();
 
         assert_eq!(records.len(), 3);
         assert_eq!(records[0].seconds, 123);
         assert_eq!(records[1].altitude, 2345);
+        assert_eq!(records[2].turn_rate, -36);
+    }
+
+    #[test]
+    fn test_record_size() {
+        let record = RedisOGNRecord {
+            seconds: 0,
+            altitude: 0,
+            longitude: 0.,
+            latitude: 0.,
+            speed: 1093,
+            climb_rate: NONE_I16,
+            turn_rate: NONE_I16,
+            signal_quality: NONE_I16,
+            frequency_offset: NONE_I16,
+            error_count: NONE_U8,
+            gps_horizontal: NONE_U8,
+            gps_vertical: NONE_U8,
+        };
+
+        assert_eq!(serialize(&record).unwrap().len(), RECORD_SIZE);
+    }
+
+    #[test]
+    fn test_record_layout() {
+        let record = RedisOGNRecord {
+            seconds: 0x0102,
+            altitude: 0x0304,
+            longitude: 1.,
+            latitude: -2.,
+            speed: 0x0506,
+            climb_rate: 0x0708,
+            turn_rate: 0x090a,
+            signal_quality: 0x0b0c,
+            frequency_offset: 0x0d0e,
+            error_count: 0x0f,
+            gps_horizontal: 0x10,
+            gps_vertical: 0x11,
+        };
+
+        // changing this layout requires a new `RECORD_VERSION`
+        assert_eq!(
+            serialize(&record).unwrap(),
+            vec![
+                0x02, 0x01, 0x04, 0x03, 0x00, 0x00, 0x80, 0x3f, 0x00, 0x00, 0x00, 0xc0, 0x06, 0x05,
+                0x08, 0x07, 0x0a, 0x09, 0x0c, 0x0b, 0x0e, 0x0d, 0x0f, 0x10, 0x11,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_records_key() {
+        assert_eq!(
+            records_key("FLRDD9612", 1523974800),
+            "ogn2:FLRDD9612:1523974800"
+        );
+        assert_eq!(records_pattern(), "ogn2:*:*");
+    }
+
+    #[test]
+    fn test_reception_record() {
+        let reception = OGNReception {
+            receiver: "VillaBlau".to_string(),
+            q_construct: "qAS".to_string(),
+        };
+
+        let serialized = serialize(&reception.to_record(123)).unwrap();
+        assert_eq!(serialized.len(), RECEPTION_RECORD_SIZE);
+
+        let record: RedisOGNReceptionRecord = deserialize(&serialized).unwrap();
+        assert_eq!(record.seconds, 123);
+        assert_eq!(OGNReception::from_record(&record), reception);
+
+        let reception = OGNReception {
+            receiver: "LFQB".to_string(),
+            q_construct: "qAR".to_string(),
+        };
+
+        let record = reception.to_record(0);
+        assert_eq!(OGNReception::from_record(&record), reception);
+    }
+
+    #[test]
+    fn test_optional_values() {
+        assert_eq!(encode_i16(None, 100.), NONE_I16);
+        assert_eq!(encode_i16(Some(-1.8034), 100.), -180);
+        assert_eq!(decode_i16(NONE_I16, 100.), None);
+        assert_eq!(decode_i16(-180, 100.), Some(-1.8));
+        assert_eq!(decode_u8(NONE_U8), None);
+        assert_eq!(decode_u8(3), Some(3));
     }
 }
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+
+use actix::prelude::*;
+use anyhow::Result;
+use chrono::prelude::*;
+use chrono::Duration;
+use log::{info, warn};
+use r2d2_redis::redis::{pipe, Commands};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::redis::executor::RedisExecutor;
+
+const POSITIONS_KEY: &str = "ogn-receiver-positions";
+const STATUS_KEY: &str = "ogn-receiver-status";
+
+/// Receivers without any beacon in this many days are dropped from redis
+const MAX_AGE_DAYS: i64 = 30;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OGNReceiverPosition {
+    pub time: DateTime<Utc>,
+    pub longitude: f32,
+    pub latitude: f32,
+    pub altitude: i16,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OGNReceiverStatus {
+    pub time: DateTime<Utc>,
+    pub version: Option<String>,
+    pub platform: Option<String>,
+    pub cpu_load: Option<f32>,
+    pub ram_free: Option<f32>,
+    pub ram_total: Option<f32>,
+    pub ntp_offset: Option<f32>,
+    pub ntp_correction: Option<f32>,
+    pub cpu_temperature: Option<f32>,
+}
+
+#[derive(Debug)]
+pub struct OGNReceiver {
+    pub name: String,
+    pub position: Option<OGNReceiverPosition>,
+    pub status: Option<OGNReceiverStatus>,
+}
+
+impl OGNReceiver {
+    /// Time of the most recent position or status beacon of this receiver
+    pub fn last_seen(&self) -> Option<DateTime<Utc>> {
+        let position_time = self.position.as_ref().map(|it| it.time);
+        let status_time = self.status.as_ref().map(|it| it.time);
+        position_time.max(status_time)
+    }
+}
+
+/// Saves the latest position and status beacons of the OGN receivers,
+/// replacing any previously saved beacons of the same receivers.
+pub struct UpdateOGNReceivers {
+    pub positions: HashMap<String, OGNReceiverPosition>,
+    pub statuses: HashMap<String, OGNReceiverStatus>,
+}
+
+impl Message for UpdateOGNReceivers {
+    type Result = Result<()>;
+}
+
+impl Handler<UpdateOGNReceivers> for RedisExecutor {
+    type Result = Result<()>;
+
+    fn handle(&mut self, msg: UpdateOGNReceivers, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.pool.get()?;
+
+        let mut pipeline = pipe();
+        for (name, position) in msg.positions {
+            pipeline.hset(POSITIONS_KEY, name, serde_json::to_string(&position)?);
+        }
+        for (name, status) in msg.statuses {
+            pipeline.hset(STATUS_KEY, name, serde_json::to_string(&status)?);
+        }
+
+        pipeline.query::<()>(&mut *conn)?;
+
+        Ok(())
+    }
+}
+
+pub struct ReadOGNReceivers;
+
+impl Message for ReadOGNReceivers {
+    type Result = Result<Vec<OGNReceiver>>;
+}
+
+impl Handler<ReadOGNReceivers> for RedisExecutor {
+    type Result = Result<Vec<OGNReceiver>>;
+
+    fn handle(&mut self, _msg: ReadOGNReceivers, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.pool.get()?;
+
+        let positions: HashMap<String, String> = conn.hgetall(POSITIONS_KEY)?;
+        let mut statuses: HashMap<String, String> = conn.hgetall(STATUS_KEY)?;
+
+        let mut receivers = Vec::new();
+        for (name, position) in positions {
+            let position = parse(&name, POSITIONS_KEY, &position);
+            let status = statuses
+                .remove(&name)
+                .and_then(|it| parse(&name, STATUS_KEY, &it));
+
+            if position.is_some() || status.is_some() {
+                receivers.push(OGNReceiver {
+                    name,
+                    position,
+                    status,
+                });
+            }
+        }
+        for (name, status) in statuses {
+            if let Some(status) = parse(&name, STATUS_KEY, &status) {
+                receivers.push(OGNReceiver {
+                    name,
+                    position: None,
+                    status: Some(status),
+                });
+            }
+        }
+
+        receivers.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(receivers)
+    }
+}
+
+pub struct ReadOGNReceiver {
+    pub name: String,
+}
+
+impl Message for ReadOGNReceiver {
+    type Result = Result<Option<OGNReceiver>>;
+}
+
+impl Handler<ReadOGNReceiver> for RedisExecutor {
+    type Result = Result<Option<OGNReceiver>>;
+
+    fn handle(&mut self, msg: ReadOGNReceiver, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.pool.get()?;
+
+        let position: Option<String> = conn.hget(POSITIONS_KEY, &msg.name)?;
+        let status: Option<String> = conn.hget(STATUS_KEY, &msg.name)?;
+
+        let position = position.and_then(|it| parse(&msg.name, POSITIONS_KEY, &it));
+        let status = status.and_then(|it| parse(&msg.name, STATUS_KEY, &it));
+        if position.is_none() && status.is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(OGNReceiver {
+            name: msg.name,
+            position,
+            status,
+        }))
+    }
+}
+
+/// Removes the beacons of receivers that have not been seen for
+/// `MAX_AGE_DAYS`, and beacons that can not be parsed anymore.
+pub struct DropOldOGNReceivers;
+
+impl Message for DropOldOGNReceivers {
+    type Result = Result<u64>;
+}
+
+impl Handler<DropOldOGNReceivers> for RedisExecutor {
+    type Result = Result<u64>;
+
+    fn handle(&mut self, _msg: DropOldOGNReceivers, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.pool.get()?;
+
+        let cutoff = Utc::now() - Duration::days(MAX_AGE_DAYS);
+
+        let mut num_deleted = 0;
+        for key in &[POSITIONS_KEY, STATUS_KEY] {
+            let values: HashMap<String, String> = conn.hgetall(*key)?;
+            let outdated = outdated_entries(&values, cutoff);
+            if !outdated.is_empty() {
+                num_deleted += outdated.len() as u64;
+                conn.hdel::<_, _, ()>(*key, outdated)?;
+            }
+        }
+
+        if num_deleted > 0 {
+            info!(
+                "Dropped {} outdated OGN receiver beacons from redis",
+                num_deleted
+            );
+        }
+
+        Ok(num_deleted)
+    }
+}
+
+/// Common part of `OGNReceiverPosition` and `OGNReceiverStatus`
+#[derive(Deserialize)]
+struct Beacon {
+    time: DateTime<Utc>,
+}
+
+/// Returns the receivers with a beacon older than `cutoff`, or with a
+/// beacon that can not be parsed.
+fn outdated_entries(values: &HashMap<String, String>, cutoff: DateTime<Utc>) -> Vec<&str> {
+    values
+        .iter()
+        .filter(|(_, value)| {
+            serde_json::from_str::<Beacon>(value).map_or(true, |beacon| beacon.time < cutoff)
+        })
+        .map(|(name, _)| name.as_str())
+        .collect()
+}
+
+fn parse<T: DeserializeOwned>(name: &str, key: &str, value: &str) -> Option<T> {
+    match serde_json::from_str(value) {
+        Ok(it) => Some(it),
+        Err(error) => {
+            warn!("Could not parse {} of {}: {}", key, name, error);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outdated_entries() {
+        let mut values = HashMap::new();
+        values.insert(
+            "LFNW".to_string(),
+            r#"{"time":"2018-04-17T14:19:56Z","longitude":1.0,"latitude":2.0,"altitude":3}"#
+                .to_string(),
+        );
+        values.insert(
+            "VillaBlau".to_string(),
+            r#"{"time":"2018-03-01T08:00:00Z","longitude":1.0,"latitude":2.0,"altitude":3}"#
+                .to_string(),
+        );
+        values.insert("Broken".to_string(), "{".to_string());
+
+        let cutoff = Utc.with_ymd_and_hms(2018, 4, 1, 0, 0, 0).unwrap();
+        let mut outdated = outdated_entries(&values, cutoff);
+        outdated.sort_unstable();
+
+        assert_eq!(outdated, vec!["Broken", "VillaBlau"]);
+    }
+
+    #[test]
+    fn test_parse() {
+        let status: Option<OGNReceiverStatus> = parse(
+            "LFNW",
+            STATUS_KEY,
+            r#"{"time":"2018-04-17T14:19:56Z","version":"0.2.8","platform":null,"cpu_load":null,"ram_free":null,"ram_total":null,"ntp_offset":null,"ntp_correction":null,"cpu_temperature":null}"#,
+        );
+        assert_eq!(status.unwrap().version.as_deref(), Some("0.2.8"));
+
+        let status: Option<OGNReceiverStatus> = parse("LFNW", STATUS_KEY, "{}");
+        assert!(status.is_none());
+    }
+}
@@ -0,0 +1,241 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use actix::prelude::*;
+use actix_ogn::OGNMessage;
+use chrono::prelude::*;
+use flate2::bufread::MultiGzDecoder;
+use log::{error, info, warn};
+
+use crate::ogn::aprs::APRSPacket;
+
+/// An actor that replays a recorded APRS log (plain text or gzip compressed)
+/// instead of connecting to the [OGN](https://www.glidernet.org/) APRS servers.
+///
+/// The lines are sent with the same spacing as their APRS timestamps divided
+/// by the `speed` factor, and the timestamps are rewritten to the current
+/// time so that the `Gateway` handles them like live data.
+pub struct ReplayActor {
+    recipient: Recipient<OGNMessage>,
+    path: PathBuf,
+    speed: f64,
+    reader: Box<dyn BufRead>,
+    /// latest APRS timestamp that was sent so far
+    last_time: Option<NaiveTime>,
+    /// line that was already read, but has to wait for its turn
+    pending: Option<String>,
+}
+
+impl ReplayActor {
+    pub fn new<P: AsRef<Path>>(
+        recipient: Recipient<OGNMessage>,
+        path: P,
+        speed: f64,
+    ) -> io::Result<ReplayActor> {
+        let path = path.as_ref().to_path_buf();
+        let reader = open(&path)?;
+
+        Ok(ReplayActor {
+            recipient,
+            path,
+            speed,
+            reader,
+            last_time: None,
+            pending: None,
+        })
+    }
+
+    fn read_line(&mut self) -> Option<String> {
+        let mut buffer = Vec::new();
+        match self.reader.read_until(b'\n', &mut buffer) {
+            Ok(0) => None,
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&buffer);
+                Some(line.trim_end_matches(&['\r', '\n'][..]).to_owned())
+            }
+            Err(error) => {
+                error!("Could not read from {}: {}", self.path.display(), error);
+                None
+            }
+        }
+    }
+
+    /// Sends all lines up to the next timestamp that is in the future and
+    /// schedules sending the rest.
+    fn send_next(&mut self, ctx: &mut Context<Self>) {
+        loop {
+            let line = match self.pending.take().or_else(|| self.read_line()) {
+                Some(line) => line,
+                None => {
+                    info!("Finished replaying {}", self.path.display());
+                    ctx.stop();
+                    return;
+                }
+            };
+
+            // server comments are not forwarded by `UpstreamActor` either
+            if line.starts_with('#') {
+                continue;
+            }
+
+            if let Some((_, time)) = find_timestamp(&line) {
+                match self
+                    .last_time
+                    .map(|last_time| time_difference(last_time, time))
+                {
+                    Some(difference) if difference > chrono::Duration::zero() => {
+                        let delay = replay_delay(difference, self.speed);
+
+                        self.last_time = Some(time);
+                        self.pending = Some(line);
+                        ctx.run_later(delay, |act, ctx| act.send_next(ctx));
+                        return;
+                    }
+                    Some(_) => {}
+                    None => self.last_time = Some(time),
+                }
+            }
+
+            let raw = retime(&line, Utc::now().time());
+            if let Err(error) = self.recipient.do_send(OGNMessage { raw }) {
+                warn!("do_send failed: {}", error);
+            }
+        }
+    }
+}
+
+impl Actor for ReplayActor {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        info!(
+            "Replaying {} at {}x speed...",
+            self.path.display(),
+            self.speed
+        );
+
+        self.send_next(ctx);
+    }
+}
+
+/// Opens a plain text or gzip compressed file, depending on its content.
+fn open(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let is_gzip = reader.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+    if is_gzip {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// Returns the byte offset and value of the `HHMMSSh` timestamp in the
+/// APRS `line`, if it has one.
+fn find_timestamp(line: &str) -> Option<(usize, NaiveTime)> {
+    let packet = APRSPacket::parse(line)?;
+
+    let body = packet.body.as_bytes();
+    match body.first() {
+        Some(b'/') | Some(b'@') | Some(b'>') => {}
+        _ => return None,
+    }
+
+    if body.get(7) != Some(&b'h') {
+        return None;
+    }
+
+    let time = NaiveTime::parse_from_str(packet.body.get(1..7)?, "%H%M%S").ok()?;
+
+    // the body is a suffix of the line
+    let body_start = line.len() - packet.body.len();
+    Some((body_start + 1, time))
+}
+
+/// Replaces the timestamp in the APRS `line` by `now`.
+fn retime(line: &str, now: NaiveTime) -> String {
+    match find_timestamp(line) {
+        Some((start, _)) => format!(
+            "{}{}{}",
+            &line[..start],
+            now.format("%H%M%S"),
+            &line[start + 6..]
+        ),
+        None => line.to_owned(),
+    }
+}
+
+/// Difference between two APRS timestamps, taking the wrap-around at
+/// midnight into account.
+fn time_difference(from: NaiveTime, to: NaiveTime) -> chrono::Duration {
+    let difference = to - from;
+    if difference < chrono::Duration::hours(-12) {
+        difference + chrono::Duration::days(1)
+    } else if difference > chrono::Duration::hours(12) {
+        difference - chrono::Duration::days(1)
+    } else {
+        difference
+    }
+}
+
+fn replay_delay(difference: chrono::Duration, speed: f64) -> Duration {
+    let millis = difference.num_milliseconds() as f64 / speed;
+    Duration::from_millis(millis.max(0.) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, minute: u32, second: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, second).unwrap()
+    }
+
+    #[test]
+    fn test_find_timestamp() {
+        let line = r"FLRDD9612>APRS,qAS,VillaBlau:/141956h4911.18N/00815.93E'126/059/A=003716";
+        assert_eq!(find_timestamp(line), Some((30, time(14, 19, 56))));
+
+        let line = r"LFNW>OGNSDR,TCPIP*,qAC,GLIDERN5:>151958h v0.2.8";
+        assert_eq!(find_timestamp(line), Some((33, time(15, 19, 58))));
+
+        assert_eq!(find_timestamp(r"# aprsc 2.1.4-g408ed49"), None);
+        assert_eq!(find_timestamp(r"FLRDD9612>APRS,qAS,VillaBlau:>foo"), None);
+        assert_eq!(
+            find_timestamp(r"FLRDD9612>APRS,qAS,VillaBlau:/991956h"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_retime() {
+        assert_eq!(
+            retime(
+                r"FLRDD9612>APRS,qAS,VillaBlau:/141956h4911.18N/00815.93E'126/059/A=003716",
+                time(8, 5, 3)
+            ),
+            r"FLRDD9612>APRS,qAS,VillaBlau:/080503h4911.18N/00815.93E'126/059/A=003716"
+        );
+
+        assert_eq!(
+            retime(r"FLRDD9612>APRS,qAS,VillaBlau:>foo", time(8, 5, 3)),
+            r"FLRDD9612>APRS,qAS,VillaBlau:>foo"
+        );
+    }
+
+    #[test]
+    fn test_replay_delay() {
+        let difference = time_difference(time(14, 19, 56), time(14, 19, 58));
+        assert_eq!(difference, chrono::Duration::seconds(2));
+        assert_eq!(replay_delay(difference, 1.), Duration::from_secs(2));
+        assert_eq!(replay_delay(difference, 10.), Duration::from_millis(200));
+
+        let difference = time_difference(time(23, 59, 59), time(0, 0, 1));
+        assert_eq!(difference, chrono::Duration::seconds(2));
+
+        let difference = time_difference(time(0, 0, 1), time(23, 59, 59));
+        assert_eq!(difference, chrono::Duration::seconds(-2));
+    }
+}
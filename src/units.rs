@@ -1,22 +1,38 @@
 pub trait FeetToMeter {
-    fn feet_to_meter(self: Self) -> Self;
+    fn feet_to_meter(self) -> Self;
 }
 
 impl FeetToMeter for f32 {
-    fn feet_to_meter(self: f32) -> f32 {
+    fn feet_to_meter(self) -> f32 {
         self * 0.3048
     }
 }
 
 impl FeetToMeter for f64 {
-    fn feet_to_meter(self: f64) -> f64 {
+    fn feet_to_meter(self) -> f64 {
         self * 0.3048
     }
 }
 
+pub trait KnotsToKilometersPerHour {
+    fn knots_to_kilometers_per_hour(self) -> Self;
+}
+
+impl KnotsToKilometersPerHour for f32 {
+    fn knots_to_kilometers_per_hour(self) -> f32 {
+        self * 1.852
+    }
+}
+
+impl KnotsToKilometersPerHour for f64 {
+    fn knots_to_kilometers_per_hour(self) -> f64 {
+        self * 1.852
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::FeetToMeter;
+    use super::{FeetToMeter, KnotsToKilometersPerHour};
     use approx::assert_relative_eq;
 
     #[test]
@@ -30,4 +46,16 @@ mod tests {
         assert_relative_eq!(0f64.feet_to_meter(), 0f64);
         assert_relative_eq!(4500f64.feet_to_meter(), 1371.6f64);
     }
+
+    #[test]
+    fn test_knots_to_kilometers_per_hour_f32() {
+        assert_relative_eq!(0f32.knots_to_kilometers_per_hour(), 0f32);
+        assert_relative_eq!(59f32.knots_to_kilometers_per_hour(), 109.268f32);
+    }
+
+    #[test]
+    fn test_knots_to_kilometers_per_hour_f64() {
+        assert_relative_eq!(0f64.knots_to_kilometers_per_hour(), 0f64);
+        assert_relative_eq!(59f64.knots_to_kilometers_per_hour(), 109.268f64);
+    }
 }
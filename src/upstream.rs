@@ -0,0 +1,637 @@
+use std::fmt;
+use std::num::ParseIntError;
+use std::time::{Duration, Instant};
+
+use actix::io::{FramedWrite, WriteHandler};
+use actix::prelude::*;
+use actix_ogn::OGNMessage;
+use backoff::backoff::Backoff;
+use backoff::ExponentialBackoff;
+use chrono::prelude::*;
+use log::{error, info, warn};
+use serde::Serialize;
+use tokio::io::{split, WriteHalf};
+use tokio::net::TcpStream;
+use tokio_util::codec::{FramedRead, LinesCodec, LinesCodecError};
+
+/// Interval in which a keepalive comment is sent to the APRS server
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Interval in which the connection health is checked and reported
+const HEALTH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Upper limit for the length of a single APRS line
+const MAX_LINE_LENGTH: usize = 1024;
+
+/// Number of consecutive connections to a server that fail or end without
+/// receiving any data, after which the next configured server is used
+const MAX_ATTEMPTS: u32 = 2;
+
+/// Duration after which a healthy connection to a backup server is replaced
+/// by a new attempt to connect to the primary server
+const PRIMARY_RETRY_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// Host and port of an APRS-IS server
+#[derive(Clone, Debug, PartialEq)]
+pub struct Server {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Server {
+    /// Parses a `HOST[:PORT]` server address, using `default_port` if no
+    /// port is given. IPv6 addresses have to be enclosed in brackets
+    /// (e.g. `[::1]:10152`).
+    pub fn parse(value: &str, default_port: u16) -> Result<Server, ServerParseError> {
+        let (host, port) = match value.strip_prefix('[') {
+            Some(rest) => {
+                let (host, rest) = rest
+                    .split_once(']')
+                    .ok_or(ServerParseError::InvalidBrackets)?;
+                if rest.is_empty() {
+                    (host, None)
+                } else {
+                    let port = rest
+                        .strip_prefix(':')
+                        .ok_or(ServerParseError::InvalidBrackets)?;
+                    (host, Some(port))
+                }
+            }
+            None => match value.split_once(':') {
+                Some((_, port)) if port.contains(':') => {
+                    return Err(ServerParseError::UnbracketedIPv6);
+                }
+                Some((host, port)) => (host, Some(port)),
+                None => (value, None),
+            },
+        };
+
+        if host.is_empty() || host.contains(['[', ']']) {
+            return Err(ServerParseError::InvalidHost);
+        }
+
+        let port = match port {
+            Some(port) => port.parse().map_err(ServerParseError::InvalidPort)?,
+            None => default_port,
+        };
+
+        Ok(Server {
+            host: host.to_owned(),
+            port,
+        })
+    }
+}
+
+impl fmt::Display for Server {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// Reason why a `Server` address could not be parsed
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerParseError {
+    /// the host is empty or contains brackets
+    InvalidHost,
+    /// the brackets around an IPv6 address are not closed or are not
+    /// followed by `:PORT`
+    InvalidBrackets,
+    /// an IPv6 address without brackets, which is ambiguous with `HOST:PORT`
+    UnbracketedIPv6,
+    InvalidPort(ParseIntError),
+}
+
+impl fmt::Display for ServerParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ServerParseError::InvalidHost => f.write_str("invalid host"),
+            ServerParseError::InvalidBrackets => {
+                f.write_str("IPv6 addresses must be written as [ADDRESS] or [ADDRESS]:PORT")
+            }
+            ServerParseError::UnbracketedIPv6 => {
+                f.write_str("IPv6 addresses must be enclosed in brackets (e.g. [::1]:10152)")
+            }
+            ServerParseError::InvalidPort(error) => write!(f, "invalid port: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ServerParseError {}
+
+/// Connection settings for one upstream feed.
+#[derive(Clone, Debug)]
+pub struct UpstreamConfig {
+    /// the primary server, followed by the backup servers that are used
+    /// in turn if the current server fails
+    pub servers: Vec<Server>,
+    /// login callsign, the connection is always read-only (`pass -1`)
+    pub login: String,
+    /// server-side APRS filter (e.g. `r/50.9/6.1/200`)
+    pub filter: Option<String>,
+    /// duration without any received APRS data after which the connection
+    /// is considered stalled
+    pub timeout: Duration,
+}
+
+impl UpstreamConfig {
+    fn login_line(&self) -> String {
+        let mut line = format!(
+            "user {} pass -1 vers {} {}",
+            self.login,
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION")
+        );
+
+        if let Some(filter) = &self.filter {
+            line.push_str(" filter ");
+            line.push_str(filter);
+        }
+
+        line
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    Disconnected,
+}
+
+/// Connection state and health of an upstream feed, sent to the `Gateway`
+/// whenever it changes and in regular intervals.
+#[derive(Clone, Debug, Message, Serialize)]
+#[rtype(result = "()")]
+pub struct UpstreamStatus {
+    /// index of the upstream feed
+    pub feed: usize,
+    /// address of the currently used server
+    pub server: String,
+    pub filter: Option<String>,
+    pub state: ConnectionState,
+    /// time of the last `state` change
+    pub since: DateTime<Utc>,
+    pub last_message_at: Option<DateTime<Utc>>,
+    pub messages_per_minute: u32,
+}
+
+/// Number of messages received within the last minute, counted in
+/// `HEALTH_INTERVAL` sized buckets.
+#[derive(Debug, Default)]
+struct MessageRate {
+    buckets: [u32; 6],
+    current: usize,
+}
+
+impl MessageRate {
+    fn count(&mut self) {
+        self.buckets[self.current] += 1;
+    }
+
+    /// Starts a new bucket, dropping the oldest one.
+    fn advance(&mut self) {
+        self.current = (self.current + 1) % self.buckets.len();
+        self.buckets[self.current] = 0;
+    }
+
+    fn per_minute(&self) -> u32 {
+        self.buckets.iter().sum()
+    }
+}
+
+/// Selects the server to connect to: the current server is retried until
+/// it failed `MAX_ATTEMPTS` times in a row, then the next server is used.
+#[derive(Debug)]
+struct ServerSelection {
+    count: usize,
+    /// index of the current server in `UpstreamConfig.servers`
+    current: usize,
+    /// consecutive failed attempts with the current server
+    failures: u32,
+}
+
+impl ServerSelection {
+    fn new(count: usize) -> ServerSelection {
+        ServerSelection {
+            count,
+            current: 0,
+            failures: 0,
+        }
+    }
+
+    fn is_primary(&self) -> bool {
+        self.current == 0
+    }
+
+    /// Resets the failure count once data was received from the server.
+    fn succeeded(&mut self) {
+        self.failures = 0;
+    }
+
+    fn failed(&mut self) {
+        self.failures += 1;
+        if self.failures >= MAX_ATTEMPTS {
+            self.current = (self.current + 1) % self.count;
+            self.failures = 0;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.current = 0;
+        self.failures = 0;
+    }
+}
+
+/// An actor that connects to an APRS-IS server (e.g. the
+/// [OGN](https://www.glidernet.org/) APRS servers), logs in with the
+/// configured callsign and filter and forwards all received lines as
+/// `OGNMessage`.
+///
+/// Lost connections are reestablished with an exponential backoff. If the
+/// connection to a server repeatedly fails or stalls, the next configured
+/// backup server is used, and the primary server is tried again after
+/// `PRIMARY_RETRY_INTERVAL`.
+pub struct UpstreamActor {
+    feed: usize,
+    config: UpstreamConfig,
+    server: ServerSelection,
+    recipient: Recipient<OGNMessage>,
+    status_recipient: Recipient<UpstreamStatus>,
+    backoff: ExponentialBackoff,
+    state: ConnectionState,
+    since: DateTime<Utc>,
+    reader: Option<SpawnHandle>,
+    writer: Option<FramedWrite<String, WriteHalf<TcpStream>, LinesCodec>>,
+    /// time of the last received APRS data, or of the connection
+    /// establishment if nothing was received yet
+    last_activity: Instant,
+    last_message_at: Option<DateTime<Utc>>,
+    rate: MessageRate,
+}
+
+impl UpstreamActor {
+    pub fn new(
+        feed: usize,
+        config: UpstreamConfig,
+        recipient: Recipient<OGNMessage>,
+        status_recipient: Recipient<UpstreamStatus>,
+    ) -> UpstreamActor {
+        let backoff = ExponentialBackoff {
+            max_interval: Duration::from_secs(5 * 60),
+            max_elapsed_time: None,
+            ..Default::default()
+        };
+
+        UpstreamActor {
+            feed,
+            server: ServerSelection::new(config.servers.len()),
+            config,
+            recipient,
+            status_recipient,
+            backoff,
+            state: ConnectionState::Disconnected,
+            since: Utc::now(),
+            reader: None,
+            writer: None,
+            last_activity: Instant::now(),
+            last_message_at: None,
+            rate: MessageRate::default(),
+        }
+    }
+
+    fn server(&self) -> &Server {
+        &self.config.servers[self.server.current]
+    }
+
+    fn set_state(&mut self, state: ConnectionState) {
+        self.state = state;
+        self.since = Utc::now();
+        self.report_status();
+    }
+
+    fn report_status(&self) {
+        let status = UpstreamStatus {
+            feed: self.feed,
+            server: self.server().to_string(),
+            filter: self.config.filter.clone(),
+            state: self.state,
+            since: self.since,
+            last_message_at: self.last_message_at,
+            messages_per_minute: self.rate.per_minute(),
+        };
+
+        if let Err(error) = self.status_recipient.do_send(status) {
+            warn!("do_send failed: {}", error);
+        }
+    }
+
+    fn connect(&mut self, ctx: &mut Context<Self>) {
+        let server = self.server().clone();
+        info!("Connecting to APRS server at {}...", server);
+        self.set_state(ConnectionState::Connecting);
+
+        TcpStream::connect(server.to_string())
+            .into_actor(self)
+            .map(move |result, act, ctx| match result {
+                Ok(stream) => {
+                    info!("Connected to APRS server at {}", server);
+                    act.backoff.reset();
+                    act.last_activity = Instant::now();
+                    act.set_state(ConnectionState::Connected);
+
+                    let (reader, writer) = split(stream);
+
+                    let mut writer = FramedWrite::new(writer, LinesCodec::new(), ctx);
+                    writer.write(act.config.login_line());
+                    act.writer = Some(writer);
+
+                    act.reader = Some(ctx.add_stream(FramedRead::new(
+                        reader,
+                        LinesCodec::new_with_max_length(MAX_LINE_LENGTH),
+                    )));
+                }
+                Err(error) => {
+                    error!("Could not connect to APRS server at {}: {}", server, error);
+                    act.reconnect(ctx);
+                }
+            })
+            .wait(ctx);
+    }
+
+    fn disconnect(&mut self, ctx: &mut Context<Self>) {
+        if let Some(reader) = self.reader.take() {
+            ctx.cancel_future(reader);
+        }
+        self.writer = None;
+        self.set_state(ConnectionState::Disconnected);
+    }
+
+    /// Closes the current connection and connects again after the backoff
+    /// delay, using the next configured server if the current one keeps
+    /// failing.
+    fn reconnect(&mut self, ctx: &mut Context<Self>) {
+        self.disconnect(ctx);
+        self.server.failed();
+
+        let delay = self
+            .backoff
+            .next_backoff()
+            .unwrap_or(self.backoff.max_interval);
+
+        info!(
+            "Connecting to APRS server at {} in {:?}...",
+            self.server(),
+            delay
+        );
+        ctx.run_later(delay, |act, ctx| act.connect(ctx));
+    }
+
+    fn check_health(&mut self, ctx: &mut Context<Self>) {
+        self.rate.advance();
+
+        if self.state == ConnectionState::Connected
+            && self.last_activity.elapsed() > self.config.timeout
+        {
+            warn!(
+                "No data received from APRS server at {} for {:?}",
+                self.server(),
+                self.config.timeout
+            );
+            self.reconnect(ctx);
+        } else if self.state == ConnectionState::Connected
+            && !self.server.is_primary()
+            && (Utc::now() - self.since)
+                .to_std()
+                .is_ok_and(|it| it > PRIMARY_RETRY_INTERVAL)
+        {
+            info!(
+                "Trying the primary APRS server {} again...",
+                self.config.servers[0]
+            );
+            self.disconnect(ctx);
+            self.server.reset();
+            self.backoff.reset();
+            self.connect(ctx);
+        } else {
+            self.report_status();
+        }
+    }
+}
+
+impl Actor for UpstreamActor {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.connect(ctx);
+
+        ctx.run_interval(KEEPALIVE_INTERVAL, |act, _ctx| {
+            if let Some(writer) = &mut act.writer {
+                writer.write("# keepalive".to_owned());
+            }
+        });
+
+        ctx.run_interval(HEALTH_INTERVAL, |act, ctx| act.check_health(ctx));
+    }
+}
+
+impl Supervised for UpstreamActor {
+    fn restarting(&mut self, _ctx: &mut Self::Context) {
+        self.reader = None;
+        self.writer = None;
+    }
+}
+
+impl WriteHandler<LinesCodecError> for UpstreamActor {
+    fn error(&mut self, error: LinesCodecError, _ctx: &mut Self::Context) -> Running {
+        warn!("Could not write to APRS server: {}", error);
+        Running::Continue
+    }
+}
+
+impl StreamHandler<Result<String, LinesCodecError>> for UpstreamActor {
+    fn handle(&mut self, line: Result<String, LinesCodecError>, _ctx: &mut Self::Context) {
+        match line {
+            // server comments are not forwarded (e.g. `# aprsc 2.1.4-g408ed49`)
+            // and do not count as data since they are also sent by stalled servers
+            Ok(line) if line.starts_with('#') => {}
+            Ok(raw) => {
+                self.server.succeeded();
+                self.last_activity = Instant::now();
+                self.last_message_at = Some(Utc::now());
+                self.rate.count();
+
+                if let Err(error) = self.recipient.do_send(OGNMessage { raw }) {
+                    warn!("do_send failed: {}", error);
+                }
+            }
+            Err(error) => warn!("Could not read from APRS server: {}", error),
+        }
+    }
+
+    fn finished(&mut self, ctx: &mut Self::Context) {
+        warn!("Lost connection to APRS server at {}", self.server());
+        self.reader = None;
+        self.reconnect(ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(filter: Option<&str>) -> UpstreamConfig {
+        UpstreamConfig {
+            servers: vec![Server {
+                host: "aprs.glidernet.org".to_owned(),
+                port: 14580,
+            }],
+            login: "OGNWEB".to_owned(),
+            filter: filter.map(str::to_owned),
+            timeout: Duration::from_secs(300),
+        }
+    }
+
+    #[test]
+    fn test_login_line() {
+        let version = env!("CARGO_PKG_VERSION");
+
+        assert_eq!(
+            config(None).login_line(),
+            format!("user OGNWEB pass -1 vers ogn-web-gateway {}", version)
+        );
+
+        assert_eq!(
+            config(Some("r/50.9/6.1/200")).login_line(),
+            format!(
+                "user OGNWEB pass -1 vers ogn-web-gateway {} filter r/50.9/6.1/200",
+                version
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_server() {
+        assert_eq!(
+            Server::parse("aprs.glidernet.org", 10152),
+            Ok(Server {
+                host: "aprs.glidernet.org".to_owned(),
+                port: 10152
+            })
+        );
+        assert_eq!(
+            Server::parse("localhost:50001", 10152),
+            Ok(Server {
+                host: "localhost".to_owned(),
+                port: 50001
+            })
+        );
+        assert!(Server::parse("localhost:foo", 10152).is_err());
+
+        assert_eq!(
+            Server::parse("aprs.glidernet.org:14580", 10152)
+                .unwrap()
+                .to_string(),
+            "aprs.glidernet.org:14580"
+        );
+    }
+
+    #[test]
+    fn test_parse_ipv6_server() {
+        assert_eq!(
+            Server::parse("[::1]:50001", 10152),
+            Ok(Server {
+                host: "::1".to_owned(),
+                port: 50001
+            })
+        );
+        assert_eq!(
+            Server::parse("[2001:db8::1]", 10152),
+            Ok(Server {
+                host: "2001:db8::1".to_owned(),
+                port: 10152
+            })
+        );
+        assert_eq!(
+            Server::parse("[::1]:50001", 10152).unwrap().to_string(),
+            "[::1]:50001"
+        );
+
+        assert_eq!(
+            Server::parse("::1", 10152),
+            Err(ServerParseError::UnbracketedIPv6)
+        );
+        assert_eq!(
+            Server::parse("2001:db8::1:10152", 10152),
+            Err(ServerParseError::UnbracketedIPv6)
+        );
+        assert_eq!(
+            Server::parse("[::1", 10152),
+            Err(ServerParseError::InvalidBrackets)
+        );
+        assert_eq!(
+            Server::parse("[::1]10152", 10152),
+            Err(ServerParseError::InvalidBrackets)
+        );
+        assert_eq!(
+            Server::parse("[]:10152", 10152),
+            Err(ServerParseError::InvalidHost)
+        );
+        assert!(matches!(
+            Server::parse("[::1]:foo", 10152),
+            Err(ServerParseError::InvalidPort(_))
+        ));
+    }
+
+    #[test]
+    fn test_server_selection() {
+        let mut selection = ServerSelection::new(2);
+        assert!(selection.is_primary());
+
+        // an ordinary disconnect retries the current server
+        selection.failed();
+        assert_eq!(selection.current, 0);
+        selection.succeeded();
+        selection.failed();
+        assert_eq!(selection.current, 0);
+
+        // repeated failures switch to the backup server, and back
+        selection.failed();
+        assert_eq!(selection.current, 1);
+        assert!(!selection.is_primary());
+        selection.failed();
+        selection.failed();
+        assert_eq!(selection.current, 0);
+
+        selection.failed();
+        selection.failed();
+        selection.reset();
+        assert!(selection.is_primary());
+        assert_eq!(selection.failures, 0);
+    }
+
+    #[test]
+    fn test_message_rate() {
+        let mut rate = MessageRate::default();
+        assert_eq!(rate.per_minute(), 0);
+
+        rate.count();
+        rate.count();
+        rate.advance();
+        rate.count();
+        assert_eq!(rate.per_minute(), 3);
+
+        // the first bucket is dropped after one minute
+        for _ in 0..5 {
+            rate.advance();
+        }
+        assert_eq!(rate.per_minute(), 1);
+
+        rate.advance();
+        assert_eq!(rate.per_minute(), 0);
+    }
+}
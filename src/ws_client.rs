@@ -1,64 +1,308 @@
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
 
 use actix::prelude::*;
 use actix_web::client::WsProtocolError;
 use actix_web_actors::ws;
+use serde_json::Value;
 
 use crate::gateway;
-use crate::geo::BoundingBox;
+use crate::geo::{Area, BoundingBox};
+use crate::ogn::aprs::SourceNetwork;
+use crate::protocol::{
+    self, AreaSubscriptionSpec, Command, LivePosition, Protocol, Request, Response,
+};
 
 pub struct WSClient {
-    fast_buffer: String,
-    slow_buffer: String,
+    protocol: Protocol,
+    fast_buffer: Vec<Arc<LivePosition>>,
+    slow_buffer: Vec<Arc<LivePosition>>,
     gateway: Arc<Addr<gateway::Gateway>>,
 }
 
 impl WSClient {
-    pub fn new(gateway: Arc<Addr<gateway::Gateway>>) -> WSClient {
+    pub fn new(gateway: Arc<Addr<gateway::Gateway>>, protocol: Protocol) -> WSClient {
         WSClient {
-            fast_buffer: String::new(),
-            slow_buffer: String::new(),
+            protocol,
+            fast_buffer: Vec::new(),
+            slow_buffer: Vec::new(),
             gateway,
         }
     }
 
     pub fn handle_message(&mut self, text: &str, ctx: &mut <Self as Actor>::Context) {
-        if text.starts_with("+id|") {
+        if let Some(id) = text.strip_prefix("+id|") {
             self.gateway.do_send(gateway::SubscribeToId {
-                id: text[4..].to_owned(),
+                id: id.to_owned(),
                 addr: ctx.address(),
             });
-        } else if text.starts_with("-id|") {
+        } else if let Some(id) = text.strip_prefix("-id|") {
             self.gateway.do_send(gateway::UnsubscribeFromId {
-                id: text[4..].to_owned(),
+                id: id.to_owned(),
                 addr: ctx.address(),
             });
-        } else if text.starts_with("bbox|") {
-            if let Some(bbox) = BoundingBox::try_parse(&text[5..]) {
-                self.gateway.do_send(gateway::SetBoundingBox {
+        } else if let Some(networks) = text.strip_prefix("networks|") {
+            // ignore the command if any network is unknown, instead of
+            // removing the filter because of a typo
+            let networks = networks
+                .split(',')
+                .filter(|it| !it.is_empty())
+                .map(|it| it.parse::<SourceNetwork>().ok())
+                .collect::<Option<_>>();
+
+            if let Some(networks) = networks {
+                self.gateway.do_send(gateway::SetNetworkFilter {
+                    addr: ctx.address(),
+                    networks,
+                });
+            }
+        } else if let Some(bbox) = text.strip_prefix("bbox|") {
+            if let Some((bbox, filter)) = protocol::parse_text_bbox(bbox) {
+                self.gateway.do_send(gateway::AddArea {
                     addr: ctx.address(),
-                    bbox,
+                    name: gateway::DEFAULT_AREA.to_owned(),
+                    area: Area::BoundingBox(bbox),
+                    filter,
                 });
             }
+        } else if let Some(area) = text.strip_prefix("+area|") {
+            if let Some((name, area, filter)) = protocol::parse_text_area(area) {
+                self.gateway.do_send(gateway::AddArea {
+                    addr: ctx.address(),
+                    name,
+                    area,
+                    filter,
+                });
+            }
+        } else if let Some(name) = text.strip_prefix("-area|") {
+            self.gateway.do_send(gateway::RemoveArea {
+                addr: ctx.address(),
+                name: name.to_owned(),
+            });
         }
     }
 
-    pub fn flush_fast(&mut self, ctx: &mut <Self as Actor>::Context) {
-        if !self.fast_buffer.is_empty() {
-            ctx.text(&self.fast_buffer);
-            self.fast_buffer.clear();
+    pub fn handle_json_message(&mut self, text: &str, ctx: &mut <Self as Actor>::Context) {
+        let Request { id, command } = match Request::parse(text) {
+            Ok(request) => request,
+            Err(error) => return reply_error(error.id, error.message, ctx),
+        };
+
+        match command {
+            Command::Subscribe { aircraft } => {
+                if !protocol::is_valid_id(&aircraft) {
+                    return reply_error(id, "Invalid aircraft ID", ctx);
+                }
+
+                let msg = gateway::SubscribeToId {
+                    id: aircraft,
+                    addr: ctx.address(),
+                };
+                self.forward(msg, id, ctx);
+            }
+            Command::Unsubscribe { aircraft } => {
+                if !protocol::is_valid_id(&aircraft) {
+                    return reply_error(id, "Invalid aircraft ID", ctx);
+                }
+
+                let msg = gateway::UnsubscribeFromId {
+                    id: aircraft,
+                    addr: ctx.address(),
+                };
+                self.forward(msg, id, ctx);
+            }
+            Command::Bbox {
+                bbox: [left, bottom, right, top],
+                filter,
+            } => {
+                let bbox = match BoundingBox::new(left, bottom, right, top) {
+                    Some(bbox) => bbox,
+                    None => return reply_error(id, "Invalid bounding box", ctx),
+                };
+
+                let filter = match filter.to_filter() {
+                    Ok(filter) => filter,
+                    Err(category) => return reply_unknown_category(id, category, ctx),
+                };
+
+                let msg = gateway::AddArea {
+                    addr: ctx.address(),
+                    name: gateway::DEFAULT_AREA.to_owned(),
+                    area: Area::BoundingBox(bbox),
+                    filter,
+                };
+                self.forward(msg, id, ctx);
+            }
+            Command::AddArea { name, area, filter } => {
+                if !protocol::is_valid_area_name(&name) {
+                    return reply_error(id, "Invalid area name", ctx);
+                }
+
+                let area = match area.to_area() {
+                    Some(area) => area,
+                    None => return reply_error(id, "Invalid area", ctx),
+                };
+
+                let filter = match filter.to_filter() {
+                    Ok(filter) => filter,
+                    Err(category) => return reply_unknown_category(id, category, ctx),
+                };
+
+                let msg = gateway::AddArea {
+                    addr: ctx.address(),
+                    name,
+                    area,
+                    filter,
+                };
+                self.forward(msg, id, ctx);
+            }
+            Command::RemoveArea { name } => {
+                let msg = gateway::RemoveArea {
+                    addr: ctx.address(),
+                    name,
+                };
+                self.forward(msg, id, ctx);
+            }
+            Command::Networks { networks } => {
+                let mut parsed = HashSet::new();
+                for network in networks {
+                    match network.parse::<SourceNetwork>() {
+                        Ok(it) => {
+                            parsed.insert(it);
+                        }
+                        Err(_) => {
+                            let message = format!("Unknown network: {}", network);
+                            return reply_error(id, message, ctx);
+                        }
+                    }
+                }
+
+                let msg = gateway::SetNetworkFilter {
+                    addr: ctx.address(),
+                    networks: parsed,
+                };
+                self.forward(msg, id, ctx);
+            }
+            Command::Subscriptions => {
+                let msg = gateway::ListSubscriptions {
+                    addr: ctx.address(),
+                };
+
+                let fut = self
+                    .gateway
+                    .send(msg)
+                    .into_actor(self)
+                    .map(move |result, _act, ctx| match result {
+                        Ok(subscriptions) => {
+                            let response = Response::Subscriptions {
+                                id,
+                                aircraft: subscriptions.ids,
+                                areas: subscriptions
+                                    .areas
+                                    .iter()
+                                    .map(|(name, it)| {
+                                        (name.clone(), AreaSubscriptionSpec::from(it))
+                                    })
+                                    .collect(),
+                                networks: subscriptions
+                                    .networks
+                                    .into_iter()
+                                    .map(SourceNetwork::as_str)
+                                    .collect(),
+                            };
+                            ctx.text(response.to_json());
+                        }
+                        Err(error) => reply_error(id, error.to_string(), ctx),
+                    });
+
+                ctx.spawn(fut);
+            }
         }
     }
 
+    /// Sends the message to the gateway and acknowledges the request once
+    /// the gateway has processed it.
+    fn forward<M>(&self, msg: M, id: Value, ctx: &mut <Self as Actor>::Context)
+    where
+        M: Message + Send + 'static,
+        M::Result: Acknowledgement + Send,
+        gateway::Gateway: Handler<M>,
+    {
+        let fut =
+            self.gateway
+                .send(msg)
+                .into_actor(self)
+                .map(move |result, _act, ctx| match result {
+                    Ok(result) => match result.error() {
+                        None => ctx.text(Response::Ack { id }.to_json()),
+                        Some(error) => reply_error(id, error, ctx),
+                    },
+                    Err(error) => reply_error(id, error.to_string(), ctx),
+                });
+
+        ctx.spawn(fut);
+    }
+
+    pub fn flush_fast(&mut self, ctx: &mut <Self as Actor>::Context) {
+        let buffer = self.fast_buffer.split_off(0);
+        self.send(buffer, ctx);
+    }
+
     pub fn flush_slow(&mut self, ctx: &mut <Self as Actor>::Context) {
-        if !self.slow_buffer.is_empty() {
-            ctx.text(&self.slow_buffer);
-            self.slow_buffer.clear();
+        let buffer = self.slow_buffer.split_off(0);
+        self.send(buffer, ctx);
+    }
+
+    fn send(&self, positions: Vec<Arc<LivePosition>>, ctx: &mut <Self as Actor>::Context) {
+        if positions.is_empty() {
+            return;
+        }
+
+        let positions: Vec<&LivePosition> = positions.iter().map(|it| it.as_ref()).collect();
+
+        match self.protocol {
+            Protocol::Text => ctx.text(protocol::encode_text(positions)),
+            Protocol::JsonV1 => ctx.text(protocol::encode_json(positions)),
+            Protocol::BinaryV1 => {
+                for frame in protocol::encode_binary(&positions) {
+                    ctx.binary(frame);
+                }
+            }
         }
     }
 }
 
+/// Result of a gateway message that is acknowledged to JSON protocol clients
+trait Acknowledgement {
+    fn error(self) -> Option<String>;
+}
+
+impl Acknowledgement for () {
+    fn error(self) -> Option<String> {
+        None
+    }
+}
+
+impl Acknowledgement for anyhow::Result<()> {
+    fn error(self) -> Option<String> {
+        self.err().map(|it| it.to_string())
+    }
+}
+
+fn reply_error<S: Into<String>>(id: Value, message: S, ctx: &mut <WSClient as Actor>::Context) {
+    let response = Response::Error {
+        id,
+        message: message.into(),
+    };
+    ctx.text(response.to_json());
+}
+
+fn reply_unknown_category(id: Value, category: String, ctx: &mut <WSClient as Actor>::Context) {
+    let message = format!("Unknown aircraft category: {}", category);
+    reply_error(id, message, ctx);
+}
+
 impl Actor for WSClient {
     type Context = ws::WebsocketContext<Self>;
 
@@ -85,35 +329,31 @@ impl Actor for WSClient {
     }
 }
 
+/// Position of an aircraft that the client has subscribed to by ID,
+/// which is sent with a short delay.
 #[derive(Message, Clone)]
 #[rtype(result = "()")]
-pub struct SendTextFast(pub String);
+pub struct SendPositionFast(pub Arc<LivePosition>);
 
-impl Handler<SendTextFast> for WSClient {
+impl Handler<SendPositionFast> for WSClient {
     type Result = ();
 
-    fn handle(&mut self, message: SendTextFast, _ctx: &mut Self::Context) {
-        if !self.fast_buffer.is_empty() {
-            self.fast_buffer += "\n";
-        }
-
-        self.fast_buffer += &message.0;
+    fn handle(&mut self, message: SendPositionFast, _ctx: &mut Self::Context) {
+        self.fast_buffer.push(message.0);
     }
 }
 
+/// Position of an aircraft inside of the bounding box of the client,
+/// which is batched with the other positions of the last second.
 #[derive(Message, Clone)]
 #[rtype(result = "()")]
-pub struct SendTextSlow(pub String);
+pub struct SendPositionSlow(pub Arc<LivePosition>);
 
-impl Handler<SendTextSlow> for WSClient {
+impl Handler<SendPositionSlow> for WSClient {
     type Result = ();
 
-    fn handle(&mut self, message: SendTextSlow, _ctx: &mut Self::Context) {
-        if !self.slow_buffer.is_empty() {
-            self.slow_buffer += "\n";
-        }
-
-        self.slow_buffer += &message.0;
+    fn handle(&mut self, message: SendPositionSlow, _ctx: &mut Self::Context) {
+        self.slow_buffer.push(message.0);
     }
 }
 
@@ -123,7 +363,10 @@ impl StreamHandler<Result<ws::Message, WsProtocolError>> for WSClient {
         match msg {
             Ok(ws::Message::Ping(msg)) => ctx.pong(&msg),
             Ok(ws::Message::Close(_)) => ctx.stop(),
-            Ok(ws::Message::Text(text)) => self.handle_message(&text, ctx),
+            Ok(ws::Message::Text(text)) => match self.protocol {
+                Protocol::JsonV1 => self.handle_json_message(&text, ctx),
+                _ => self.handle_message(&text, ctx),
+            },
             _ => {}
         }
     }